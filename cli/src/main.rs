@@ -0,0 +1,1492 @@
+//! `driplet`: operate driplet_vaults from the command line.
+//!
+//! Defaults to a local `solana-test-validator`; pass `--url` for anything
+//! else. Commands create any token accounts the program expects to exist
+//! (idempotently, in the same transaction), so nothing has to be set up by hand.
+
+use std::rc::Rc;
+
+use anchor_client::solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use anchor_client::solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use anchor_client::solana_sdk::{
+    account::from_account,
+    clock::Clock,
+    commitment_config::CommitmentConfig,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    system_program, sysvar,
+};
+use anchor_client::anchor_lang::{Discriminator, InstructionData, ToAccountMetas};
+use anchor_client::{Client, Cluster, Program};
+use anchor_spl::associated_token::{
+    self, get_associated_token_address_with_program_id,
+    spl_associated_token_account::instruction::create_associated_token_account_idempotent,
+};
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use driplet_vaults::{accounts, instruction};
+use driplet_vaults_sdk::{
+    decode_vault, pda, projected_emissions, simulate, EmissionSchedule, GlobalState, MintListing,
+    BacklogPolicy, PrincipalPolicy, RewardStream, UnclaimedPrincipal, UserStakeV1, Vault,
+    VaultPolicy, VaultV1, RATE_SCALE,
+};
+use solana_account_decoder::UiAccountEncoding;
+
+// Byte offsets into a Vault account: discriminator, then id, creator, mint
+const VAULT_CREATOR_OFFSET: usize = 8 + 8;
+const VAULT_MINT_OFFSET: usize = 8 + 8 + 32;
+
+type DripletProgram = Program<Rc<Keypair>>;
+
+#[derive(Parser)]
+#[command(name = "driplet", version, about = "Operate driplet_vaults")]
+struct Cli {
+    /// RPC endpoint
+    #[arg(
+        long,
+        short = 'u',
+        global = true,
+        env = "DRIPLET_URL",
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: String,
+
+    /// Keypair that signs and pays
+    #[arg(
+        long,
+        short = 'k',
+        global = true,
+        env = "DRIPLET_KEYPAIR",
+        default_value = "~/.config/solana/id.json"
+    )]
+    keypair: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a vault and fund its primary reward stream
+    CreateVault(CreateVaultArgs),
+    /// Stake into a vault
+    Deposit {
+        #[arg(long)]
+        vault: Pubkey,
+        #[arg(long)]
+        amount: u64,
+        /// Lock the whole position for this many seconds for a boosted weight
+        #[arg(long)]
+        lock_secs: Option<i64>,
+    },
+    /// Unstake from a vault
+    Withdraw {
+        #[arg(long)]
+        vault: Pubkey,
+        #[arg(long)]
+        amount: u64,
+        /// Also pay out every stream's rewards
+        #[arg(long)]
+        claim: bool,
+    },
+    /// Pay out rewards from every stream
+    Claim {
+        #[arg(long)]
+        vault: Pubkey,
+    },
+    /// Creator only: move a vault nobody has staked in before it starts
+    RescheduleStart {
+        #[arg(long)]
+        vault: Pubkey,
+        /// New unix start timestamp
+        #[arg(long)]
+        start_time: i64,
+    },
+    /// Creator only: refund and close a vault before anyone has used it
+    CancelVault {
+        #[arg(long)]
+        vault: Pubkey,
+    },
+    /// Creator only: return a stream's unallocated backlog to its funder
+    ReclaimUnallocated {
+        #[arg(long)]
+        vault: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        stream: u8,
+    },
+    /// Take back the whole stake without paying rewards; waives lock and penalty only while paused
+    EmergencyWithdraw {
+        #[arg(long)]
+        vault: Pubkey,
+    },
+    /// Close your empty, fully claimed stake record and recover its rent
+    CloseStake {
+        #[arg(long)]
+        vault: Pubkey,
+    },
+    /// Bring one or more vaults' emissions up to date; open to anyone
+    Crank {
+        /// Repeat to crank several vaults in one transaction
+        #[arg(long, required = true)]
+        vault: Vec<Pubkey>,
+        /// Leave the crank pool out and skip any tip
+        #[arg(long)]
+        no_tip: bool,
+    },
+    /// Add lamports to the pool that pays crank tips; open to anyone
+    FundCrankPool {
+        #[arg(long)]
+        lamports: u64,
+    },
+    /// Admin only: sweep and close a vault after its grace period
+    CloseVault {
+        #[arg(long)]
+        vault: Pubkey,
+    },
+    /// Move a position's principal into its owner's unclaimed-principal
+    /// account and pay out its rewards, so a held vault can close; open to
+    /// anyone after the grace period
+    ReleasePosition {
+        #[arg(long)]
+        vault: Pubkey,
+        #[arg(long)]
+        owner: Pubkey,
+    },
+    /// Take back principal held for you after your vault was wound down
+    OwnerReclaim {
+        #[arg(long)]
+        vault: Pubkey,
+    },
+    /// Admin only: close an expired UserStake record
+    AdminCloseStake {
+        #[arg(long)]
+        vault: Pubkey,
+        #[arg(long)]
+        owner: Pubkey,
+    },
+    /// Admin only: stop new vaults from being created
+    DisableNewVaults,
+    /// Admin only: allow new vaults again
+    EnableNewVaults,
+    /// Admin only: allow or deny a mint for new vaults
+    ListMint {
+        #[arg(long)]
+        mint: Pubkey,
+        /// Deny the mint instead of allowing it
+        #[arg(long)]
+        deny: bool,
+    },
+    /// Admin only: drop a mint's listing
+    UnlistMint {
+        #[arg(long)]
+        mint: Pubkey,
+    },
+    /// Admin only: require new vaults to use allowed mints only
+    AllowlistOnly {
+        #[arg(action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Admin only: start handing the admin role to another key
+    ProposeAdmin {
+        /// 11111111111111111111111111111111 (the default pubkey) cancels a pending transfer
+        #[arg(long)]
+        new_admin: Pubkey,
+    },
+    /// Become admin; the signer must be the proposed key
+    AcceptAdmin,
+    /// Admin only: change the fee recipient or fees; omitted values are kept
+    UpdateFees {
+        #[arg(long)]
+        fee_recipient: Option<Pubkey>,
+        #[arg(long)]
+        creation_fee_lamports: Option<u64>,
+        #[arg(long)]
+        fee_bps: Option<u16>,
+        #[arg(long)]
+        cancel_fee_lamports: Option<u64>,
+        /// Hold token fees in the vault so cancel_vault refunds them
+        #[arg(long)]
+        cancel_refunds_fee: Option<bool>,
+    },
+    /// Admin only: block new vaults and optionally shorten the close grace
+    RetireProgram {
+        /// Grace after the later of a vault's end and the retirement
+        /// (defaults to the usual three years)
+        #[arg(long)]
+        grace_secs: Option<i64>,
+    },
+    /// Admin only: start the timelock for lifting a retirement
+    RequestUnretire,
+    /// Admin only: lift a retirement once the timelock has passed
+    UnretireProgram,
+    /// Admin only: grow a state account still in the original layout
+    MigrateGlobalState,
+    /// Rewrite a vault and its owners' stakes still in the v1 layout; the
+    /// vault needs the admin, stakes are open to anyone
+    MigrateVault {
+        #[arg(long)]
+        vault: Pubkey,
+        /// Stake owner to migrate as well; repeatable
+        #[arg(long)]
+        owner: Vec<Pubkey>,
+        /// Keep sweeping unwithdrawn principal to the treasury on close, as
+        /// v1 did, instead of holding it for owners
+        #[arg(long)]
+        sweep_principal: bool,
+    },
+    /// Admin only: set the notice, horizon and backdating allowed for vault starts
+    SetStartBounds {
+        #[arg(long)]
+        min_lead_secs: i64,
+        #[arg(long)]
+        max_horizon_secs: i64,
+        #[arg(long)]
+        max_backdate_secs: i64,
+    },
+    /// Admin only: block vault creation, deposits and claims and freeze
+    /// emissions everywhere
+    Pause,
+    /// Admin only: lift the global pause
+    Unpause,
+    /// Admin only: halt one vault and freeze its emissions
+    PauseVault {
+        #[arg(long)]
+        vault: Pubkey,
+    },
+    /// Admin only: resume a vault, pushing its schedule back by the pause
+    UnpauseVault {
+        #[arg(long)]
+        vault: Pubkey,
+    },
+    /// Admin only: set the crank tip, how often a vault can earn it and how
+    /// many times in all
+    SetCrankTip {
+        /// 0 stops tips
+        #[arg(long)]
+        tip_lamports: u64,
+        #[arg(long, default_value_t = 3_600)]
+        interval_secs: i64,
+        #[arg(long, default_value_t = 2_000)]
+        max_tips_per_vault: u64,
+    },
+    /// Admin only: move unspent crank pool lamports to the fee recipient
+    WithdrawCrankPool {
+        #[arg(long)]
+        lamports: u64,
+    },
+    /// List vaults, optionally filtered
+    ListVaults {
+        #[arg(long)]
+        mint: Option<Pubkey>,
+        #[arg(long)]
+        creator: Option<Pubkey>,
+    },
+    /// Decoded vault state with projected emissions
+    ShowVault {
+        #[arg(long)]
+        vault: Pubkey,
+        /// Accrue to this unix timestamp instead of the cluster's clock
+        #[arg(long)]
+        at: Option<i64>,
+    },
+}
+
+#[derive(Args)]
+struct CreateVaultArgs {
+    /// Mint that gets staked
+    #[arg(long)]
+    mint: Pubkey,
+    /// Mint paid out as rewards (defaults to the staking mint)
+    #[arg(long)]
+    reward_mint: Option<Pubkey>,
+    /// Rewards to stream, in base units; the token fee is charged on top
+    #[arg(long)]
+    reward_amount: u64,
+    #[arg(long, default_value_t = 0)]
+    vault_id: u64,
+    #[arg(long, default_value_t = 182 * 86_400)]
+    duration_secs: i64,
+    /// Unix timestamp (defaults to five minutes after creation, or the
+    /// configured minimum lead if longer)
+    #[arg(long)]
+    start_time: Option<i64>,
+    /// Halve the emission rate every this many seconds
+    #[arg(long, conflicts_with = "cliff_secs")]
+    half_life_secs: Option<i64>,
+    /// Emit nothing for this many seconds, then linearly
+    #[arg(long)]
+    cliff_secs: Option<i64>,
+    #[arg(long, default_value_t = 0)]
+    early_exit_penalty_bps: u16,
+    #[arg(long, default_value_t = 0)]
+    min_stake_secs: i64,
+    /// Sweep unwithdrawn principal to the treasury on close instead of
+    /// holding it for owners
+    #[arg(long)]
+    sweep_principal: bool,
+    /// Keep emissions made while nobody is staked for the funder instead
+    /// of paying them to the next staker
+    #[arg(long)]
+    backlog_to_funder: bool,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let keypair_path = shellexpand::tilde(&cli.keypair).into_owned();
+    let payer = Rc::new(
+        read_keypair_file(&keypair_path)
+            .map_err(|e| anyhow!("reading keypair {keypair_path}: {e}"))?,
+    );
+    let cluster = Cluster::Custom(cli.url.clone(), ws_url(&cli.url));
+    let client = Client::new_with_options(cluster, payer.clone(), CommitmentConfig::confirmed());
+    let program = client.program(driplet_vaults::ID)?;
+
+    match cli.command {
+        Command::CreateVault(args) => create_vault(&program, &payer, args),
+        Command::Deposit { vault, amount, lock_secs } => {
+            deposit(&program, &payer, vault, amount, lock_secs)
+        }
+        Command::Withdraw { vault, amount, claim } => {
+            withdraw(&program, &payer, vault, amount, claim)
+        }
+        Command::Claim { vault } => claim(&program, &payer, vault),
+        Command::RescheduleStart { vault, start_time } => {
+            reschedule_start(&program, &payer, vault, start_time)
+        }
+        Command::CancelVault { vault } => cancel_vault(&program, &payer, vault),
+        Command::ReclaimUnallocated { vault, stream } => {
+            reclaim_unallocated(&program, &payer, vault, stream)
+        }
+        Command::EmergencyWithdraw { vault } => emergency_withdraw(&program, &payer, vault),
+        Command::CloseStake { vault } => close_stake(&program, &payer, vault),
+        Command::Crank { vault, no_tip } => crank(&program, &payer, vault, no_tip),
+        Command::FundCrankPool { lamports } => fund_crank_pool(&program, &payer, lamports),
+        Command::CloseVault { vault } => close_vault(&program, &payer, vault),
+        Command::ReleasePosition { vault, owner } => {
+            release_position(&program, &payer, vault, owner)
+        }
+        Command::OwnerReclaim { vault } => owner_reclaim(&program, &payer, vault),
+        Command::AdminCloseStake { vault, owner } => {
+            admin_close_stake(&program, &payer, vault, owner)
+        }
+        Command::DisableNewVaults => set_new_vaults_disabled(&program, &payer, true),
+        Command::EnableNewVaults => set_new_vaults_disabled(&program, &payer, false),
+        Command::ListMint { mint, deny } => list_mint(&program, &payer, mint, deny),
+        Command::UnlistMint { mint } => unlist_mint(&program, &payer, mint),
+        Command::AllowlistOnly { enabled } => allowlist_only(&program, &payer, enabled),
+        Command::ProposeAdmin { new_admin } => propose_admin(&program, &payer, new_admin),
+        Command::AcceptAdmin => accept_admin(&program, &payer),
+        Command::UpdateFees {
+            fee_recipient,
+            creation_fee_lamports,
+            fee_bps,
+            cancel_fee_lamports,
+            cancel_refunds_fee,
+        } => {
+            update_fees(
+                &program,
+                &payer,
+                fee_recipient,
+                creation_fee_lamports,
+                fee_bps,
+                cancel_fee_lamports,
+                cancel_refunds_fee,
+            )
+        }
+        Command::RetireProgram { grace_secs } => retire_program(&program, &payer, grace_secs),
+        Command::RequestUnretire => request_unretire(&program, &payer),
+        Command::UnretireProgram => unretire_program(&program, &payer),
+        Command::MigrateGlobalState => migrate_global_state(&program, &payer),
+        Command::MigrateVault { vault, owner, sweep_principal } => {
+            let principal = if sweep_principal {
+                PrincipalPolicy::SweepToTreasury
+            } else {
+                PrincipalPolicy::HoldForOwners
+            };
+            migrate_vault(&program, &payer, vault, owner, principal)
+        }
+        Command::SetStartBounds { min_lead_secs, max_horizon_secs, max_backdate_secs } => {
+            set_start_bounds(&program, &payer, min_lead_secs, max_horizon_secs, max_backdate_secs)
+        }
+        Command::Pause => set_global_pause(&program, &payer, true),
+        Command::Unpause => set_global_pause(&program, &payer, false),
+        Command::PauseVault { vault } => pause_vault(&program, &payer, vault),
+        Command::UnpauseVault { vault } => unpause_vault(&program, &payer, vault),
+        Command::SetCrankTip { tip_lamports, interval_secs, max_tips_per_vault } => {
+            set_crank_tip(&program, &payer, tip_lamports, interval_secs, max_tips_per_vault)
+        }
+        Command::WithdrawCrankPool { lamports } => withdraw_crank_pool(&program, &payer, lamports),
+        Command::ListVaults { mint, creator } => list_vaults(&program, mint, creator),
+        Command::ShowVault { vault, at } => show_vault(&program, vault, at),
+    }
+}
+
+fn create_vault(program: &DripletProgram, payer: &Keypair, args: CreateVaultArgs) -> Result<()> {
+    let CreateVaultArgs {
+        mint,
+        reward_mint,
+        reward_amount,
+        vault_id,
+        duration_secs,
+        start_time,
+        half_life_secs,
+        cliff_secs,
+        early_exit_penalty_bps,
+        min_stake_secs,
+        sweep_principal,
+        backlog_to_funder,
+    } = args;
+    let reward_mint = reward_mint.unwrap_or(mint);
+    let schedule = match (half_life_secs, cliff_secs) {
+        (Some(half_life_secs), _) => EmissionSchedule::ExponentialDecay { half_life_secs },
+        (_, Some(cliff_secs)) => EmissionSchedule::CliffLinear { cliff_secs },
+        _ => EmissionSchedule::Linear,
+    };
+    let policy = VaultPolicy {
+        early_exit_penalty_bps,
+        min_stake_secs,
+        principal: if sweep_principal {
+            PrincipalPolicy::SweepToTreasury
+        } else {
+            PrincipalPolicy::HoldForOwners
+        },
+        backlog: if backlog_to_funder {
+            BacklogPolicy::ReturnToFunder
+        } else {
+            BacklogPolicy::ToFirstStaker
+        },
+    };
+    let token_program = token_program_of(program, &mint)?;
+    let reward_token_program = token_program_of(program, &reward_mint)?;
+
+    let state: GlobalState = program.account(pda::state().0)?;
+    let treasury = state.fee_recipient;
+    let creator = payer.pubkey();
+    let (vault, _) = pda::vault(&mint, &creator, vault_id);
+    let (escrow_authority, _) = pda::vault_escrow(&vault);
+    let (reward_authority, _) = pda::vault_reward(&vault);
+
+    let sig = program
+        .request()
+        // CreateVault takes these ATAs as pre-existing unchecked accounts
+        .instruction(create_ata(&creator, &escrow_authority, &mint, &token_program))
+        .instruction(create_ata(&creator, &reward_authority, &reward_mint, &reward_token_program))
+        .instruction(create_ata(&creator, &treasury, &reward_mint, &reward_token_program))
+        .accounts(accounts::CreateVault {
+            global_state: pda::state().0,
+            creator,
+            dev_treasury: treasury,
+            mint,
+            reward_mint,
+            vault,
+            vault_escrow_authority: escrow_authority,
+            vault_reward_authority: reward_authority,
+            vault_escrow_ata: ata(&escrow_authority, &mint, &token_program),
+            reward_vault_ata: ata(&reward_authority, &reward_mint, &reward_token_program),
+            creator_token_ata: ata(&creator, &reward_mint, &reward_token_program),
+            dev_token_ata: ata(&treasury, &reward_mint, &reward_token_program),
+            token_program,
+            reward_token_program,
+            system_program: system_program::ID,
+            mint_rule: pda::mint_rule(&mint).0,
+            reward_mint_rule: pda::mint_rule(&reward_mint).0,
+        })
+        .args(instruction::CreateVault {
+            reward_net: reward_amount,
+            maybe_start_time: start_time,
+            vault_id,
+            duration_secs,
+            schedule,
+            policy,
+        })
+        .send()?;
+    println!("vault     {vault}");
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn deposit(
+    program: &DripletProgram,
+    payer: &Keypair,
+    vault: Pubkey,
+    amount: u64,
+    lock_secs: Option<i64>,
+) -> Result<()> {
+    let v: Vault = program.account(vault)?;
+    let user = payer.pubkey();
+    let token_program = token_program_of(program, &v.mint)?;
+    let (escrow_authority, _) = pda::vault_escrow(&vault);
+
+    let request = program.request().accounts(accounts::Deposit {
+        user,
+        global_state: pda::state().0,
+        vault,
+        mint: v.mint,
+        vault_escrow_authority: escrow_authority,
+        vault_escrow_ata: ata(&escrow_authority, &v.mint, &token_program),
+        user_stake: pda::user(&vault, &user).0,
+        user_token_ata: ata(&user, &v.mint, &token_program),
+        token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+    });
+    let sig = match lock_secs {
+        Some(lock_secs) => request
+            .args(instruction::DepositLocked { amount, lock_secs })
+            .send()?,
+        None => request.args(instruction::Deposit { amount }).send()?,
+    };
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn withdraw(
+    program: &DripletProgram,
+    payer: &Keypair,
+    vault: Pubkey,
+    amount: u64,
+    claim: bool,
+) -> Result<()> {
+    let v: Vault = program.account(vault)?;
+    let user = payer.pubkey();
+    let token_program = token_program_of(program, &v.mint)?;
+    let reward_mint = v.streams[0].mint;
+    let reward_token_program = token_program_of(program, &reward_mint)?;
+    let (escrow_authority, _) = pda::vault_escrow(&vault);
+    let (reward_authority, _) = pda::vault_reward(&vault);
+
+    let (metas, setup) = if claim {
+        extra_streams(program, &vault, &v, |_| user, &user, false)?
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    let mut request = program.request();
+    for ix in setup {
+        request = request.instruction(ix);
+    }
+    let sig = request
+        .accounts(accounts::Withdraw {
+            user,
+            global_state: pda::state().0,
+            vault,
+            mint: v.mint,
+            reward_mint,
+            user_stake: pda::user(&vault, &user).0,
+            vault_escrow_authority: escrow_authority,
+            vault_escrow_ata: ata(&escrow_authority, &v.mint, &token_program),
+            vault_reward_authority: reward_authority,
+            reward_vault_ata: ata(&reward_authority, &reward_mint, &reward_token_program),
+            user_token_ata: ata(&user, &v.mint, &token_program),
+            user_reward_ata: ata(&user, &reward_mint, &reward_token_program),
+            token_program,
+            reward_token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        })
+        .accounts(metas)
+        .args(instruction::Withdraw { amount, claim_all: claim })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn claim(program: &DripletProgram, payer: &Keypair, vault: Pubkey) -> Result<()> {
+    let v: Vault = program.account(vault)?;
+    let user = payer.pubkey();
+    let reward_mint = v.streams[0].mint;
+    let reward_token_program = token_program_of(program, &reward_mint)?;
+    let (reward_authority, _) = pda::vault_reward(&vault);
+
+    let (metas, setup) = extra_streams(program, &vault, &v, |_| user, &user, false)?;
+    let mut request = program.request();
+    for ix in setup {
+        request = request.instruction(ix);
+    }
+    let sig = request
+        .accounts(accounts::Claim {
+            user,
+            global_state: pda::state().0,
+            vault,
+            reward_mint,
+            user_stake: pda::user(&vault, &user).0,
+            vault_reward_authority: reward_authority,
+            reward_vault_ata: ata(&reward_authority, &reward_mint, &reward_token_program),
+            user_reward_ata: ata(&user, &reward_mint, &reward_token_program),
+            reward_token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        })
+        .accounts(metas)
+        .args(instruction::Claim {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn reschedule_start(
+    program: &DripletProgram,
+    payer: &Keypair,
+    vault: Pubkey,
+    start_time: i64,
+) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::RescheduleStart {
+            creator: payer.pubkey(),
+            global_state: pda::state().0,
+            vault,
+        })
+        .args(instruction::RescheduleStart { new_start_time: start_time })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn cancel_vault(program: &DripletProgram, payer: &Keypair, vault: Pubkey) -> Result<()> {
+    let v: Vault = program.account(vault)?;
+    let state: GlobalState = program.account(pda::state().0)?;
+    let creator = payer.pubkey();
+    let token_program = token_program_of(program, &v.mint)?;
+    let reward_mint = v.streams[0].mint;
+    let reward_token_program = token_program_of(program, &reward_mint)?;
+    let (escrow_authority, _) = pda::vault_escrow(&vault);
+    let (reward_authority, _) = pda::vault_reward(&vault);
+
+    // Extra streams are refunded to whoever funded them
+    let (metas, setup) = extra_streams(program, &vault, &v, |s| s.funder, &creator, true)?;
+    let mut request = program.request();
+    for ix in setup {
+        request = request.instruction(ix);
+    }
+    let sig = request
+        .accounts(accounts::CancelVault {
+            creator,
+            global_state: pda::state().0,
+            dev_treasury: state.fee_recipient,
+            vault,
+            mint: v.mint,
+            reward_mint,
+            vault_reward_authority: reward_authority,
+            vault_escrow_authority: escrow_authority,
+            reward_vault_ata: ata(&reward_authority, &reward_mint, &reward_token_program),
+            vault_escrow_ata: ata(&escrow_authority, &v.mint, &token_program),
+            creator_token_ata: ata(&creator, &reward_mint, &reward_token_program),
+            creator_stake_ata: ata(&creator, &v.mint, &token_program),
+            token_program,
+            reward_token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        })
+        .accounts(metas)
+        .args(instruction::CancelVault {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn reclaim_unallocated(
+    program: &DripletProgram,
+    payer: &Keypair,
+    vault: Pubkey,
+    stream: u8,
+) -> Result<()> {
+    let v: Vault = program.account(vault)?;
+    let s = v
+        .streams
+        .get(stream as usize)
+        .ok_or_else(|| anyhow!("vault has no stream {stream}"))?;
+    let reward_token_program = token_program_of(program, &s.mint)?;
+    let (reward_authority, _) = pda::vault_reward(&vault);
+    let setup = create_ata(&payer.pubkey(), &s.funder, &s.mint, &reward_token_program);
+
+    let sig = program
+        .request()
+        .instruction(setup)
+        .accounts(accounts::ReclaimUnallocated {
+            creator: payer.pubkey(),
+            global_state: pda::state().0,
+            vault,
+            reward_mint: s.mint,
+            vault_reward_authority: reward_authority,
+            reward_vault_ata: ata(&reward_authority, &s.mint, &reward_token_program),
+            funder_token_ata: ata(&s.funder, &s.mint, &reward_token_program),
+            reward_token_program,
+        })
+        .args(instruction::ReclaimUnallocated { stream_index: stream })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn emergency_withdraw(program: &DripletProgram, payer: &Keypair, vault: Pubkey) -> Result<()> {
+    let v: Vault = program.account(vault)?;
+    let user = payer.pubkey();
+    let token_program = token_program_of(program, &v.mint)?;
+    let (escrow_authority, _) = pda::vault_escrow(&vault);
+
+    let sig = program
+        .request()
+        .accounts(accounts::EmergencyWithdraw {
+            user,
+            global_state: pda::state().0,
+            vault,
+            mint: v.mint,
+            user_stake: pda::user(&vault, &user).0,
+            vault_escrow_authority: escrow_authority,
+            vault_escrow_ata: ata(&escrow_authority, &v.mint, &token_program),
+            user_token_ata: ata(&user, &v.mint, &token_program),
+            token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        })
+        .args(instruction::EmergencyWithdraw {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn close_stake(program: &DripletProgram, payer: &Keypair, vault: Pubkey) -> Result<()> {
+    let user = payer.pubkey();
+    let sig = program
+        .request()
+        .accounts(accounts::CloseUserStake {
+            user,
+            vault,
+            user_stake: pda::user(&vault, &user).0,
+        })
+        .args(instruction::CloseUserStake {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn crank(
+    program: &DripletProgram,
+    payer: &Keypair,
+    vaults: Vec<Pubkey>,
+    no_tip: bool,
+) -> Result<()> {
+    let cranker = payer.pubkey();
+    let crank_pool = (!no_tip).then(|| pda::crank_pool().0);
+    let request = if let &[vault] = vaults.as_slice() {
+        program
+            .request()
+            .accounts(accounts::Crank {
+                cranker,
+                global_state: pda::state().0,
+                vault,
+                crank_pool,
+            })
+            .args(instruction::Crank {})
+    } else {
+        let metas: Vec<_> = vaults.iter().map(|v| AccountMeta::new(*v, false)).collect();
+        program
+            .request()
+            .accounts(accounts::CrankMany {
+                cranker,
+                global_state: pda::state().0,
+                crank_pool,
+            })
+            .accounts(metas)
+            .args(instruction::CrankMany {})
+    };
+    let sig = request.send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn fund_crank_pool(program: &DripletProgram, payer: &Keypair, lamports: u64) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::FundCrankPool {
+            funder: payer.pubkey(),
+            crank_pool: pda::crank_pool().0,
+            system_program: system_program::ID,
+        })
+        .args(instruction::FundCrankPool { lamports })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn close_vault(program: &DripletProgram, payer: &Keypair, vault: Pubkey) -> Result<()> {
+    let v: Vault = program.account(vault)?;
+    let state: GlobalState = program.account(pda::state().0)?;
+    let admin = payer.pubkey();
+    let treasury = state.fee_recipient;
+    let token_program = token_program_of(program, &v.mint)?;
+    let reward_mint = v.streams[0].mint;
+    let reward_token_program = token_program_of(program, &reward_mint)?;
+    let (escrow_authority, _) = pda::vault_escrow(&vault);
+    let (reward_authority, _) = pda::vault_reward(&vault);
+
+    // Mints are writable so withheld Token-2022 fees can be harvested
+    let (metas, setup) = extra_streams(program, &vault, &v, |_| treasury, &admin, true)?;
+    let mut request = program.request();
+    for ix in setup {
+        request = request.instruction(ix);
+    }
+    let sig = request
+        .accounts(accounts::CloseVaultTreasuryOnly {
+            admin,
+            global_state: pda::state().0,
+            dev_treasury: treasury,
+            vault,
+            mint: v.mint,
+            reward_mint,
+            vault_reward_authority: reward_authority,
+            vault_escrow_authority: escrow_authority,
+            reward_vault_ata: ata(&reward_authority, &reward_mint, &reward_token_program),
+            vault_escrow_ata: ata(&escrow_authority, &v.mint, &token_program),
+            dev_token_ata: ata(&treasury, &reward_mint, &reward_token_program),
+            dev_stake_ata: ata(&treasury, &v.mint, &token_program),
+            token_program,
+            reward_token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        })
+        .accounts(metas)
+        .args(instruction::CloseVault {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn release_position(
+    program: &DripletProgram,
+    payer: &Keypair,
+    vault: Pubkey,
+    owner: Pubkey,
+) -> Result<()> {
+    let v: Vault = program.account(vault)?;
+    let token_program = token_program_of(program, &v.mint)?;
+    let reward_mint = v.streams[0].mint;
+    let reward_token_program = token_program_of(program, &reward_mint)?;
+    let (escrow_authority, _) = pda::vault_escrow(&vault);
+    let (reward_authority, _) = pda::vault_reward(&vault);
+    let (unclaimed, _) = pda::unclaimed(&vault, &owner);
+
+    let (metas, setup) = extra_streams(program, &vault, &v, |_| owner, &payer.pubkey(), false)?;
+    let mut request = program.request();
+    for ix in setup {
+        request = request.instruction(ix);
+    }
+    let sig = request
+        .accounts(accounts::ReleasePosition {
+            payer: payer.pubkey(),
+            global_state: pda::state().0,
+            vault,
+            user_stake: pda::user(&vault, &owner).0,
+            owner,
+            mint: v.mint,
+            reward_mint,
+            vault_reward_authority: reward_authority,
+            reward_vault_ata: ata(&reward_authority, &reward_mint, &reward_token_program),
+            owner_reward_ata: ata(&owner, &reward_mint, &reward_token_program),
+            vault_escrow_authority: escrow_authority,
+            vault_escrow_ata: ata(&escrow_authority, &v.mint, &token_program),
+            unclaimed_principal: unclaimed,
+            unclaimed_principal_ata: ata(&unclaimed, &v.mint, &token_program),
+            token_program,
+            reward_token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        })
+        .accounts(metas)
+        .args(instruction::ReleasePosition {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn owner_reclaim(program: &DripletProgram, payer: &Keypair, vault: Pubkey) -> Result<()> {
+    let owner = payer.pubkey();
+    let (unclaimed, _) = pda::unclaimed(&vault, &owner);
+    let held: UnclaimedPrincipal = program.account(unclaimed)?;
+    let token_program = token_program_of(program, &held.mint)?;
+    let sig = program
+        .request()
+        .accounts(accounts::OwnerReclaim {
+            owner,
+            unclaimed_principal: unclaimed,
+            mint: held.mint,
+            unclaimed_principal_ata: ata(&unclaimed, &held.mint, &token_program),
+            user_token_ata: ata(&owner, &held.mint, &token_program),
+            token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        })
+        .args(instruction::OwnerReclaim {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn admin_close_stake(
+    program: &DripletProgram,
+    payer: &Keypair,
+    vault: Pubkey,
+    owner: Pubkey,
+) -> Result<()> {
+    let state: GlobalState = program.account(pda::state().0)?;
+    let sig = program
+        .request()
+        .accounts(accounts::AdminCloseExpiredStake {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+            dev_treasury: state.fee_recipient,
+            user_stake: pda::user(&vault, &owner).0,
+            vault,
+        })
+        .args(instruction::AdminCloseExpiredStake {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn set_new_vaults_disabled(
+    program: &DripletProgram,
+    payer: &Keypair,
+    disabled: bool,
+) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::SetNewVaultsDisabled {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::SetNewVaultsDisabled { disabled })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn list_mint(program: &DripletProgram, payer: &Keypair, mint: Pubkey, deny: bool) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::SetMintListing {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+            mint,
+            mint_rule: pda::mint_rule(&mint).0,
+            system_program: system_program::ID,
+        })
+        .args(instruction::SetMintListing {
+            listing: if deny { MintListing::Denied } else { MintListing::Allowed },
+        })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn unlist_mint(program: &DripletProgram, payer: &Keypair, mint: Pubkey) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::ClearMintListing {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+            mint_rule: pda::mint_rule(&mint).0,
+        })
+        .args(instruction::ClearMintListing {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn allowlist_only(program: &DripletProgram, payer: &Keypair, enabled: bool) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::SetMintAllowlistOnly {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::SetMintAllowlistOnly { allowlist_only: enabled })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn propose_admin(program: &DripletProgram, payer: &Keypair, new_admin: Pubkey) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::ProposeAdmin {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::ProposeAdmin { new_admin })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn accept_admin(program: &DripletProgram, payer: &Keypair) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::AcceptAdmin {
+            pending_admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::AcceptAdmin {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn update_fees(
+    program: &DripletProgram,
+    payer: &Keypair,
+    fee_recipient: Option<Pubkey>,
+    creation_fee_lamports: Option<u64>,
+    fee_bps: Option<u16>,
+    cancel_fee_lamports: Option<u64>,
+    cancel_refunds_fee: Option<bool>,
+) -> Result<()> {
+    let state: GlobalState = program.account(pda::state().0)?;
+    let sig = program
+        .request()
+        .accounts(accounts::UpdateFees {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::UpdateFees {
+            fee_recipient: fee_recipient.unwrap_or(state.fee_recipient),
+            creation_fee_lamports: creation_fee_lamports
+                .unwrap_or(state.creation_fee_lamports),
+            fee_bps: fee_bps.unwrap_or(state.fee_bps),
+            cancel_fee_lamports: cancel_fee_lamports.unwrap_or(state.cancel_fee_lamports),
+            cancel_refunds_fee: cancel_refunds_fee.unwrap_or(state.cancel_refunds_fee),
+        })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn retire_program(
+    program: &DripletProgram,
+    payer: &Keypair,
+    grace_secs: Option<i64>,
+) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::RetireProgram {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::RetireProgram { grace_secs })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn request_unretire(program: &DripletProgram, payer: &Keypair) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::RequestUnretire {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::RequestUnretire {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn unretire_program(program: &DripletProgram, payer: &Keypair) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::UnretireProgram {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::UnretireProgram {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn migrate_global_state(program: &DripletProgram, payer: &Keypair) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::MigrateGlobalState {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+            system_program: system_program::ID,
+        })
+        .args(instruction::MigrateGlobalState {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn migrate_vault(
+    program: &DripletProgram,
+    payer: &Keypair,
+    vault: Pubkey,
+    owners: Vec<Pubkey>,
+    principal: PrincipalPolicy,
+) -> Result<()> {
+    let rpc = program.rpc();
+    let is_v1 = |key: &Pubkey, len: usize| -> Result<bool> {
+        Ok(rpc.get_account(key).with_context(|| format!("fetching {key}"))?.data.len() == len)
+    };
+    let mut request = program.request();
+    if is_v1(&vault, VaultV1::LEN)? {
+        request = request.instruction(Instruction {
+            program_id: driplet_vaults::ID,
+            accounts: accounts::MigrateVault {
+                admin: payer.pubkey(),
+                global_state: pda::state().0,
+                vault,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::MigrateVault { principal }.data(),
+        });
+    }
+    for owner in &owners {
+        let user_stake = pda::user(&vault, owner).0;
+        if is_v1(&user_stake, UserStakeV1::LEN)? {
+            request = request.instruction(Instruction {
+                program_id: driplet_vaults::ID,
+                accounts: accounts::MigrateUserStake {
+                    payer: payer.pubkey(),
+                    user_stake,
+                    system_program: system_program::ID,
+                }
+                .to_account_metas(None),
+                data: instruction::MigrateUserStake {}.data(),
+            });
+        }
+    }
+    let sig = request.send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn set_start_bounds(
+    program: &DripletProgram,
+    payer: &Keypair,
+    min_lead_secs: i64,
+    max_horizon_secs: i64,
+    max_backdate_secs: i64,
+) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::SetStartBounds {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::SetStartBounds {
+            min_lead_secs,
+            max_horizon_secs,
+            max_backdate_secs,
+        })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn set_global_pause(program: &DripletProgram, payer: &Keypair, paused: bool) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::SetGlobalPause {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::SetGlobalPause { paused })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn pause_vault(program: &DripletProgram, payer: &Keypair, vault: Pubkey) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::PauseVault {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+            vault,
+        })
+        .args(instruction::PauseVault {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn unpause_vault(program: &DripletProgram, payer: &Keypair, vault: Pubkey) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::UnpauseVault {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+            vault,
+        })
+        .args(instruction::UnpauseVault {})
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn set_crank_tip(
+    program: &DripletProgram,
+    payer: &Keypair,
+    tip_lamports: u64,
+    interval_secs: i64,
+    max_tips_per_vault: u64,
+) -> Result<()> {
+    let sig = program
+        .request()
+        .accounts(accounts::SetCrankTip {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+        })
+        .args(instruction::SetCrankTip {
+            tip_lamports,
+            interval_secs,
+            max_tips_per_vault,
+        })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn withdraw_crank_pool(program: &DripletProgram, payer: &Keypair, lamports: u64) -> Result<()> {
+    let state: GlobalState = program.account(pda::state().0)?;
+    let sig = program
+        .request()
+        .accounts(accounts::WithdrawCrankPool {
+            admin: payer.pubkey(),
+            global_state: pda::state().0,
+            dev_treasury: state.fee_recipient,
+            crank_pool: pda::crank_pool().0,
+        })
+        .args(instruction::WithdrawCrankPool { lamports })
+        .send()?;
+    println!("signature {sig}");
+    Ok(())
+}
+
+fn list_vaults(
+    program: &DripletProgram,
+    mint: Option<Pubkey>,
+    creator: Option<Pubkey>,
+) -> Result<()> {
+    let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+        0,
+        Vault::DISCRIMINATOR,
+    ))];
+    if let Some(mint) = mint {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            VAULT_MINT_OFFSET,
+            mint.as_ref(),
+        )));
+    }
+    if let Some(creator) = creator {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            VAULT_CREATOR_OFFSET,
+            creator.as_ref(),
+        )));
+    }
+    let config = RpcProgramAccountsConfig {
+        filters: Some(filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    let accounts = program.rpc().get_program_accounts_with_config(&driplet_vaults::ID, config)?;
+
+    // One account that does not decode must not hide every other vault
+    let mut vaults = Vec::new();
+    let mut skipped = 0;
+    for (key, account) in accounts {
+        match decode_vault(&account.data) {
+            Ok(v) => vaults.push((key, v)),
+            Err(e) => {
+                eprintln!("skipping {key}: {e}");
+                skipped += 1;
+            }
+        }
+    }
+    vaults.sort_by_key(|(_, v)| v.start_time);
+    for (key, v) in vaults {
+        println!(
+            "{key}  id={} mint={} creator={} start={} end={} staked={} streams={}{}",
+            v.id,
+            v.mint,
+            v.creator,
+            v.start_time,
+            v.end_time,
+            v.total_staked,
+            v.streams.len(),
+            if v.paused_at != 0 { " paused" } else { "" },
+        );
+    }
+    if skipped > 0 {
+        eprintln!("{skipped} account(s) skipped as undecodable");
+    }
+    Ok(())
+}
+
+fn show_vault(program: &DripletProgram, vault: Pubkey, at: Option<i64>) -> Result<()> {
+    // Read-only, so a vault still in the v1 layout is shown as it would migrate
+    let account = program
+        .rpc()
+        .get_account(&vault)
+        .with_context(|| format!("fetching vault {vault}"))?;
+    let v = decode_vault(&account.data).with_context(|| format!("decoding vault {vault}"))?;
+    let state: GlobalState = program.account(pda::state().0)?;
+    let now = match at {
+        Some(at) => at,
+        None => cluster_time(program)?,
+    };
+    let sim = simulate(&v, &state, now)?;
+
+    println!("vault            {vault}");
+    println!("id               {}", v.id);
+    println!("creator          {}", v.creator);
+    println!("mint             {}", v.mint);
+    println!("term             {} .. {} ({}s)", v.start_time, v.end_time, v.duration_secs);
+    println!("total staked     {}", sim.total_staked);
+    println!("weighted stake   {}", sim.total_weighted_stake);
+    println!(
+        "policy           penalty {} bps before {}s, principal {}, backlog {}",
+        v.policy.early_exit_penalty_bps,
+        v.policy.min_stake_secs,
+        match v.policy.principal {
+            PrincipalPolicy::HoldForOwners => "held for owners",
+            PrincipalPolicy::SweepToTreasury => "swept to treasury",
+        },
+        match v.policy.backlog {
+            BacklogPolicy::ToFirstStaker => "to first staker",
+            BacklogPolicy::ReturnToFunder => "returned to funder",
+        },
+    );
+    println!("penalty pool     {}", sim.penalty_pool);
+    if v.paused_at != 0 {
+        println!("paused at        {} (emissions frozen)", v.paused_at);
+    }
+    if state.paused_at != 0 {
+        println!("program paused   {} (emissions frozen)", state.paused_at);
+    }
+    if v.crank_tips_paid != 0 {
+        println!(
+            "crank tips       {} (last {})",
+            v.crank_tips_paid, v.last_cranked_at
+        );
+    }
+    println!("as of            {now}");
+
+    for (i, s) in sim.streams.iter().enumerate() {
+        println!();
+        println!("stream {i}         {}", s.mint);
+        println!("  funder         {}", s.funder);
+        println!("  window         {} .. {}", s.start_time, s.end_time);
+        println!("  schedule       {}", describe_schedule(&s.schedule));
+        println!("  reward net     {} (fee {})", s.reward_net, s.reward_fee);
+        println!("  emitted        {}", s.emitted);
+        println!("  unallocated    {} (reclaimed {})", s.unallocated, s.reclaimed);
+        println!("  owed / paid    {} / {}", s.total_owed, s.paid);
+        let frozen = (v.paused_at != 0 && now >= v.paused_at)
+            || (state.paused_at != 0 && now >= state.paused_at);
+        let rate_fp = if frozen {
+            0
+        } else {
+            driplet_vaults::math::current_rate_fp(s, now)
+        };
+        println!("  rate           {:.6} tokens/s", rate_fp as f64 / RATE_SCALE as f64);
+
+        // Emitted totals at the quarter points of the stream's window
+        let span = s.end_time - s.start_time;
+        for q in 1..=4 {
+            let at = s.start_time + span * q / 4;
+            println!("  by {at:<11} {}", projected_emissions(&v, &state, at)?[i]);
+        }
+    }
+    Ok(())
+}
+
+fn ws_url(http: &str) -> String {
+    http.replacen("http", "ws", 1).replace(":8899", ":8900")
+}
+
+/// The cluster's `Clock::unix_timestamp`, as the program reads it; block
+/// times can be missing for recent slots.
+fn cluster_time(program: &DripletProgram) -> Result<i64> {
+    let account = program
+        .rpc()
+        .get_account(&sysvar::clock::ID)
+        .context("fetching the clock sysvar")?;
+    let clock: Clock = from_account(&account).ok_or_else(|| anyhow!("decoding the clock sysvar"))?;
+    Ok(clock.unix_timestamp)
+}
+
+fn token_program_of(program: &DripletProgram, mint: &Pubkey) -> Result<Pubkey> {
+    let account = program
+        .rpc()
+        .get_account(mint)
+        .with_context(|| format!("fetching mint {mint}"))?;
+    Ok(account.owner)
+}
+
+fn ata(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, token_program)
+}
+
+fn create_ata(payer: &Pubkey, owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Instruction {
+    create_associated_token_account_idempotent(payer, owner, mint, token_program)
+}
+
+/// `remaining_accounts` for every stream after the primary one:
+/// `[mint, reward_vault_ata, counterparty_ata, token_program]` each. Also
+/// returns instructions creating the counterparty ATAs if they are missing.
+fn extra_streams(
+    program: &DripletProgram,
+    vault: &Pubkey,
+    v: &Vault,
+    counterparty: impl Fn(&RewardStream) -> Pubkey,
+    payer: &Pubkey,
+    writable_mints: bool,
+) -> Result<(Vec<AccountMeta>, Vec<Instruction>)> {
+    let (reward_authority, _) = pda::vault_reward(vault);
+    let mut metas = Vec::new();
+    let mut setup = Vec::new();
+    for s in v.streams.iter().skip(1) {
+        let token_program = token_program_of(program, &s.mint)?;
+        let counterparty = counterparty(s);
+        let counterparty_ata = ata(&counterparty, &s.mint, &token_program);
+        setup.push(create_ata(payer, &counterparty, &s.mint, &token_program));
+        metas.push(if writable_mints {
+            AccountMeta::new(s.mint, false)
+        } else {
+            AccountMeta::new_readonly(s.mint, false)
+        });
+        metas.push(AccountMeta::new(ata(&reward_authority, &s.mint, &token_program), false));
+        metas.push(AccountMeta::new(counterparty_ata, false));
+        metas.push(AccountMeta::new_readonly(token_program, false));
+    }
+    Ok((metas, setup))
+}
+
+fn describe_schedule(schedule: &EmissionSchedule) -> String {
+    match schedule {
+        EmissionSchedule::Linear => "linear".to_string(),
+        EmissionSchedule::ExponentialDecay { half_life_secs } => {
+            format!("exponential decay, half-life {half_life_secs}s")
+        }
+        EmissionSchedule::Stepped { steps } => {
+            let steps: Vec<String> = steps
+                .iter()
+                .map(|s| format!("+{}s x{}", s.offset_secs, s.weight))
+                .collect();
+            format!("stepped [{}]", steps.join(", "))
+        }
+        EmissionSchedule::CliffLinear { cliff_secs } => format!("linear after a {cliff_secs}s cliff"),
+    }
+}
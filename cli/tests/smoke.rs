@@ -0,0 +1,76 @@
+//! Runs the built `driplet` binary without a cluster: argument parsing,
+//! help for every command, and failures that happen before any RPC.
+
+use std::process::{Command, Output};
+
+fn driplet(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_driplet"))
+        .args(args)
+        .env_remove("DRIPLET_URL")
+        .env_remove("DRIPLET_KEYPAIR")
+        .output()
+        .expect("running driplet")
+}
+
+/// Subcommand names listed under "Commands:" in `driplet --help`.
+fn subcommands() -> Vec<String> {
+    let out = driplet(&["--help"]);
+    assert!(out.status.success());
+    String::from_utf8(out.stdout)
+        .unwrap()
+        .lines()
+        .skip_while(|l| !l.starts_with("Commands:"))
+        .skip(1)
+        .take_while(|l| !l.is_empty())
+        .filter_map(|l| l.split_whitespace().next().map(str::to_string))
+        .filter(|name| name != "help")
+        .collect()
+}
+
+#[test]
+fn every_command_has_help() {
+    let commands = subcommands();
+    for expected in [
+        "create-vault",
+        "crank",
+        "list-vaults",
+        "show-vault",
+        "set-crank-tip",
+    ] {
+        assert!(commands.iter().any(|c| c == expected), "{expected} missing");
+    }
+    for command in &commands {
+        let out = driplet(&[command, "--help"]);
+        assert!(out.status.success(), "{command} --help failed");
+    }
+}
+
+#[test]
+fn bad_arguments_are_usage_errors() {
+    // Missing a required flag, an unknown command, and a malformed pubkey
+    for args in [
+        &["create-vault", "--reward-amount", "1"][..],
+        &["no-such-command"],
+        &["show-vault", "--vault", "not-a-pubkey"],
+    ] {
+        let out = driplet(args);
+        assert_eq!(out.status.code(), Some(2), "{args:?}");
+    }
+}
+
+#[test]
+fn an_unreadable_keypair_fails_before_any_rpc() {
+    let out = driplet(&[
+        "--keypair",
+        "/nonexistent/id.json",
+        "--url",
+        "http://127.0.0.1:1",
+        "list-vaults",
+    ]);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(
+        stderr.contains("reading keypair /nonexistent/id.json"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,290 @@
+//! Runs the built `driplet` binary against a local `solana-test-validator`
+//! with the program loaded. The validator starts from preloaded accounts (a
+//! funded payer that is also the admin, a mint and the payer's token
+//! account), since `init_global_state` can only be signed by DEV_TREASURY.
+//!
+//! Needs `solana-test-validator` on PATH and `anchor build` at the workspace
+//! root (or DRIPLET_VAULTS_SO); run with `cargo test -p driplet-cli --test
+//! validator -- --ignored`.
+
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anchor_client::anchor_lang::{AccountSerialize, AnchorSerialize, Discriminator};
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::{
+    account::from_account,
+    clock::Clock,
+    pubkey::Pubkey,
+    signature::{write_keypair_file, Keypair, Signer},
+    system_program, sysvar,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token::{
+    self,
+    solana_program::{program_option::COption, program_pack::Pack},
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use driplet_vaults_sdk::{pda, GlobalState, Vault, VaultV1, ID};
+
+const RPC_PORT: u16 = 18_899;
+const SOL: u64 = 1_000_000_000;
+const SUPPLY: u64 = 10_000_000;
+
+/// A running validator; killed when dropped.
+struct Validator {
+    child: Child,
+    dir: PathBuf,
+    keypair: PathBuf,
+    payer: Pubkey,
+    mint: Pubkey,
+    rpc: RpcClient,
+}
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+impl Validator {
+    fn start(extra: &[(Pubkey, Pubkey, Vec<u8>)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("driplet-cli-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let payer = Keypair::new();
+        let keypair = dir.join("payer.json");
+        write_keypair_file(&payer, &keypair).unwrap();
+        let mint = Pubkey::new_unique();
+
+        let mut accounts = vec![
+            (payer.pubkey(), system_program::ID, Vec::new()),
+            (pda::state().0, ID, global_state(&payer.pubkey())),
+            (mint, spl_token::ID, mint_data(&payer.pubkey())),
+            (
+                get_associated_token_address(&payer.pubkey(), &mint),
+                spl_token::ID,
+                token_account_data(&mint, &payer.pubkey(), SUPPLY),
+            ),
+        ];
+        accounts.extend_from_slice(extra);
+
+        let mut cmd = Command::new("solana-test-validator");
+        cmd.arg("--reset")
+            .arg("--quiet")
+            .arg("--ledger")
+            .arg(dir.join("ledger"))
+            .args(["--rpc-port", &RPC_PORT.to_string()])
+            .args(["--faucet-port", &(RPC_PORT + 1_001).to_string()])
+            .arg("--bpf-program")
+            .arg(ID.to_string())
+            .arg(program_so());
+        for (i, (key, owner, data)) in accounts.iter().enumerate() {
+            let file = dir.join(format!("account-{i}.json"));
+            std::fs::write(&file, account_json(key, owner, data)).unwrap();
+            cmd.arg("--account").arg(key.to_string()).arg(file);
+        }
+        let child = cmd
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("solana-test-validator not found on PATH");
+
+        let rpc = RpcClient::new(format!("http://127.0.0.1:{RPC_PORT}"));
+        let validator = Self { child, dir, keypair, payer: payer.pubkey(), mint, rpc };
+        let deadline = Instant::now() + Duration::from_secs(60);
+        while validator.rpc.get_health().is_err() || validator.rpc.get_slot().unwrap_or(0) < 2 {
+            assert!(Instant::now() < deadline, "validator did not come up");
+            sleep(Duration::from_millis(250));
+        }
+        validator
+    }
+
+    fn driplet(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_driplet"))
+            .args(["--url", &format!("http://127.0.0.1:{RPC_PORT}")])
+            .arg("--keypair")
+            .arg(&self.keypair)
+            .args(args)
+            .output()
+            .expect("running driplet")
+    }
+
+    /// Runs a command that must succeed and returns its stdout.
+    fn ok(&self, args: &[&str]) -> String {
+        let out = self.driplet(args);
+        assert!(
+            out.status.success(),
+            "{args:?} failed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        String::from_utf8(out.stdout).unwrap()
+    }
+
+    fn now(&self) -> i64 {
+        let account = self.rpc.get_account(&sysvar::clock::ID).unwrap();
+        from_account::<Clock, _>(&account).unwrap().unix_timestamp
+    }
+
+    fn token_balance(&self) -> u64 {
+        let ata = get_associated_token_address(&self.payer, &self.mint);
+        let data = self.rpc.get_account(&ata).unwrap().data;
+        TokenAccount::unpack(&data).unwrap().amount
+    }
+}
+
+fn program_so() -> PathBuf {
+    if let Ok(path) = std::env::var("DRIPLET_VAULTS_SO") {
+        return path.into();
+    }
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/deploy/driplet_vaults.so");
+    assert!(path.exists(), "driplet_vaults.so not found; run `anchor build` or set DRIPLET_VAULTS_SO");
+    path
+}
+
+/// `solana account --output json` format, as `--account` reads it.
+fn account_json(key: &Pubkey, owner: &Pubkey, data: &[u8]) -> String {
+    format!(
+        r#"{{"pubkey":"{key}","account":{{"lamports":{},"data":["{}","base64"],"owner":"{owner}","executable":false,"rentEpoch":0,"space":{}}}}}"#,
+        100 * SOL,
+        STANDARD.encode(data),
+        data.len(),
+    )
+}
+
+fn global_state(admin: &Pubkey) -> Vec<u8> {
+    let mut data = Vec::new();
+    GlobalState::new(*admin).try_serialize(&mut data).unwrap();
+    data.resize(8 + GlobalState::SPACE, 0);
+    data
+}
+
+fn mint_data(authority: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0; Mint::LEN];
+    let mint = Mint {
+        mint_authority: COption::Some(*authority),
+        supply: SUPPLY,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    Mint::pack(mint, &mut data).unwrap();
+    data
+}
+
+fn token_account_data(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0; TokenAccount::LEN];
+    let account = TokenAccount {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+    TokenAccount::pack(account, &mut data).unwrap();
+    data
+}
+
+/// A vault still in the v1 layout, paying 1_000_000 over a day.
+fn v1_vault(mint: &Pubkey) -> Vec<u8> {
+    let old = VaultV1 {
+        id: 9,
+        creator: Pubkey::new_unique(),
+        mint: *mint,
+        start_time: 1_000,
+        end_time: 1_000 + 86_400,
+        reward_net: 1_000_000,
+        reward_fee: 30_000,
+        reward_gross: 1_030_000,
+        rate_fp: 0,
+        emission_acc_fp: 0,
+        emitted: 0,
+        last_update_time: 1_000,
+        acc_reward_per_token: 0,
+        unallocated: 0,
+        total_staked: 0,
+        bump: 0,
+        vault_escrow_bump: 0,
+        vault_reward_bump: 0,
+        version: 1,
+    };
+    let mut data = Vault::DISCRIMINATOR.to_vec();
+    old.serialize(&mut data).unwrap();
+    data.resize(VaultV1::LEN, 0);
+    data
+}
+
+#[test]
+#[ignore = "needs solana-test-validator and `anchor build`"]
+fn vault_lifecycle_against_a_local_validator() {
+    let old_vault = Pubkey::new_unique();
+    let old_mint = Pubkey::new_unique();
+    let v = Validator::start(&[(old_vault, ID, v1_vault(&old_mint))]);
+    let payer = v.payer.to_string();
+    let mint = v.mint.to_string();
+
+    let now = v.now().to_string();
+    let out = v.ok(&[
+        "create-vault",
+        "--mint",
+        &mint,
+        "--reward-amount",
+        "1000000",
+        "--duration-secs",
+        "86400",
+        "--start-time",
+        &now,
+    ]);
+    let vault = pda::vault(&v.mint, &v.payer, 0).0.to_string();
+    assert!(out.contains(&format!("vault     {vault}")), "{out}");
+    // 1_000_000 plus the default 3% fee
+    assert_eq!(v.token_balance(), SUPPLY - 1_030_000);
+
+    v.ok(&["deposit", "--vault", &vault, "--amount", "1000"]);
+    let out = v.ok(&["list-vaults", "--creator", &payer]);
+    assert!(out.contains(&vault), "{out}");
+    // The v1 vault is listed alongside, under its own mint
+    let out = v.ok(&["list-vaults", "--mint", &old_mint.to_string()]);
+    assert!(out.contains(&old_vault.to_string()) && !out.contains(&vault), "{out}");
+    let out = v.ok(&["show-vault", "--vault", &vault]);
+    assert!(out.contains("total staked     1000"), "{out}");
+
+    // Let some rewards accrue, then claim and leave
+    sleep(Duration::from_secs(3));
+    let before = v.token_balance();
+    v.ok(&["claim", "--vault", &vault]);
+    assert!(v.token_balance() > before);
+    v.ok(&["withdraw", "--vault", &vault, "--amount", "1000"]);
+    let out = v.ok(&["show-vault", "--vault", &vault]);
+    assert!(out.contains("total staked     0"), "{out}");
+
+    // A vault nobody has migrated yet still shows, as it would migrate
+    let out = v.ok(&["show-vault", "--vault", &old_vault.to_string(), "--at", "44200"]);
+    assert!(out.contains("id               9"), "{out}");
+    assert!(out.contains("emitted        500000"), "{out}");
+
+    // Admin commands, signed by the preloaded admin
+    v.ok(&["disable-new-vaults"]);
+    let out = v.driplet(&[
+        "create-vault",
+        "--mint",
+        &mint,
+        "--reward-amount",
+        "1000",
+        "--vault-id",
+        "1",
+        "--duration-secs",
+        "86400",
+    ]);
+    assert!(!out.status.success());
+    v.ok(&["enable-new-vaults"]);
+}
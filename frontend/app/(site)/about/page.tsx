@@ -11,17 +11,17 @@ export default function AboutPage() {
           <Card className="p-6 sm:p-8">
             <div className="text-sm text-cyan-300/90 font-medium tracking-wide">HOW IT WORKS</div>
             <h1 className="mt-2 text-3xl sm:text-4xl font-semibold text-white">
-              6 month staking vaults for creator tokens
+              Staking vaults for creator tokens, on your timeline
             </h1>
             <p className="mt-3 text-[#DDA0DD]">
-              Create a vault, prefund rewards, and let emissions stream per second. No edits. No pause. No top-ups.
+              Create a vault, prefund rewards, and let emissions stream per second. No edits, and a pause only stops the clock. Top-ups welcome.
             </p>
 
             {/* 3 quick facts */}
             <div className="mt-6 grid gap-3 sm:grid-cols-3">
-              <Fact label="Term length" value="182 days from creation" />
+              <Fact label="Term length" value="set by you at creation" />
               <Fact label="Emissions" value="per-second stream" />
-              <Fact label="Creation fee" value="0.1 SOL + 3% of rewards token" />
+              <Fact label="Creation fee" value="SOL fee + % of rewards token" />
             </div>
           </Card>
 
@@ -46,7 +46,7 @@ export default function AboutPage() {
       <ol className="mt-4 space-y-4">
         <Step n={1} title="Click Create & Paste Mint Address" desc="This is the SPL mint address for your token." />
         <Step n={2} title="Enter Amount of Rewards" desc="Enter the total tokens to distribute over the term." />
-        <Step n={3} title="Go Live by Clicking Create Vault" desc="0.1 SOL + 3% token fee. Vault starts streaming instantly." />
+        <Step n={3} title="Go Live by Clicking Create Vault" desc="Pay the SOL and token fees shown at checkout. Vault starts streaming instantly." />
       </ol>
     </div>
   </div>
@@ -61,10 +61,10 @@ export default function AboutPage() {
             <h3 className="text-cyan-300/90 font-semibold">For Creators</h3>
             <div className="mt-3">
               <UL>
-                <LI>Fixed, immutable term of <b>6 months</b>.</LI>
+                <LI>Pick the term when you create the vault, within the platform’s current minimum and maximum; it is fixed after launch.</LI>
                 <LI>Rewards are <b>prefunded</b> and streamed per second.</LI>
-                <LI>No admin after launch: no pause, no edits, no top-ups.</LI>
-                <LI>Create a new vault if you want more rewards later.</LI>
+                <LI>No edits after launch. The admin can pause, which freezes emissions and pushes the end back by the time paused.</LI>
+                <LI>Top up a live vault anytime; the extra rewards stream over the time left.</LI>
               </UL>
             </div>
           </Card>
@@ -73,10 +73,12 @@ export default function AboutPage() {
             <h3 className="text-cyan-300/90 font-semibold">For Stakers</h3>
             <div className="mt-3">
               <UL>
-                <LI>Stake the same token as the vault’s mint; unstake anytime.</LI>
+                <LI>Stake the same token as the vault’s mint; unstake anytime unless locked.</LI>
+                <LI>Lock your stake for up to a year to earn up to <b>2x</b> reward weight.</LI>
+                <LI>Some vaults charge an early-exit penalty before a minimum stay; it goes to the stakers who remain.</LI>
                 <LI>Rewards accrue continuously and can be claimed anytime.</LI>
                 <LI>APR varies with <i>rewards per second ÷ total staked</i>.</LI>
-                <LI>After 6 months, emissions stop; claim remaining rewards.</LI>
+                <LI>When the vault’s term ends, emissions stop; claim remaining rewards.</LI>
               </UL>
             </div>
           </Card>
@@ -122,8 +124,8 @@ export default function AboutPage() {
           <h3 className="text-cyan-300/90 font-semibold">Fees</h3>
           <div className="mt-3">
             <UL>
-              <LI>Creation: <b>0.1 SOL</b> (one-time).</LI>
-              <LI>Token fee: <b>3%</b> of the reward deposit (taken at creation).</LI>
+              <LI>Creation: a one-time <b>SOL</b> fee, set by the platform and shown before you sign.</LI>
+              <LI>Token fee: a <b>percentage</b> of the reward deposit (taken at creation), capped at 10%.</LI>
               <LI>No ongoing protocol fees or rent to worry about.</LI>
             </UL>
           </div>
@@ -133,9 +135,10 @@ export default function AboutPage() {
           <h3 className="text-cyan-300/90 font-semibold">Immutable Rules</h3>
           <div className="mt-3">
             <UL>
-              <LI>Fixed term of <b>182 days</b> from creation.</LI>
+              <LI>Term chosen at creation, within the platform’s minimum and maximum, then <b>fixed</b>.</LI>
               <LI>Rewards are <b>prefunded</b> and streamed per second.</LI>
-              <LI><b>No admin knobs</b>: no pause, no edits, no top-ups.</LI>
+              <LI><b>No edits</b>; an admin pause freezes emissions and extends the term by its length.</LI>
+              <LI>Top-ups pay the same <b>token fee</b> as creation.</LI>
             </UL>
           </div>
         </Card>
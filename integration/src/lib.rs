@@ -0,0 +1,1363 @@
+//! LiteSVM harness for the `driplet_vaults` integration tests.
+//!
+//! Signature verification is disabled so `DEV_TREASURY`, which bootstraps
+//! `GlobalState`, can act without its keypair; every other wallet is a bare
+//! pubkey too. The runtime still enforces the signer flags set in each
+//! instruction's metas, so Anchor's `Signer` checks behave exactly as on
+//! chain.
+
+use std::path::PathBuf;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::{AnchorDeserialize, Discriminator, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{
+    get_associated_token_address_with_program_id,
+    spl_associated_token_account::instruction::create_associated_token_account_idempotent,
+};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use driplet_vaults::{
+    accounts, instruction, PendingPreview, RewardReconciliation, VaultStatePreview, DEV_TREASURY,
+};
+use driplet_vaults_sdk::{
+    pda, CrankPool, EmissionSchedule, GlobalState, MintListing, PrincipalPolicy, RewardStream,
+    UnclaimedPrincipal, UserStake, Vault, VaultError, VaultPolicy, ID,
+};
+use litesvm::types::{FailedTransactionMetadata, TransactionMetadata, TransactionResult};
+use litesvm::LiteSVM;
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    message::Message,
+    transaction::{Transaction, TransactionError},
+};
+
+pub use anchor_spl::{token::ID as TOKEN, token_2022::ID as TOKEN_2022};
+
+/// Cluster time the harness starts at.
+pub const GENESIS_TS: i64 = 1_700_000_000;
+pub const DAY: i64 = 86_400;
+pub const YEAR: i64 = 365 * DAY;
+/// `THREE_YEARS_SECS` in the program.
+pub const GRACE_SECS: i64 = 3 * YEAR;
+pub const CREATION_FEE_LAMPORTS: u64 = 100_000_000;
+const SOL: u64 = 1_000_000_000;
+
+pub struct Env {
+    pub svm: LiteSVM,
+    pub payer: Pubkey,
+}
+
+/// Arguments and signers of `create_vault`, with test defaults.
+#[derive(Clone)]
+pub struct VaultParams {
+    pub creator: Pubkey,
+    pub mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub vault_id: u64,
+    pub reward_net: u64,
+    pub start_time: Option<i64>,
+    pub duration_secs: i64,
+    pub schedule: EmissionSchedule,
+    pub policy: VaultPolicy,
+}
+
+/// The token accounts `create_vault` expects to exist already. Tests swap
+/// individual fields to exercise the instruction's sanity checks.
+#[derive(Clone, Copy)]
+pub struct VaultAccounts {
+    pub vault: Pubkey,
+    pub escrow_ata: Pubkey,
+    pub reward_ata: Pubkey,
+    pub creator_ata: Pubkey,
+    pub dev_ata: Pubkey,
+}
+
+impl Env {
+    /// Fresh chain with the program deployed and `GlobalState` initialised.
+    pub fn new() -> Self {
+        let mut svm = LiteSVM::new()
+            .with_sigverify(false)
+            .with_blockhash_check(false)
+            .with_transaction_history(0);
+        svm.add_program_from_file(ID, program_so())
+            .expect("driplet_vaults.so not found; run `anchor build` or set DRIPLET_VAULTS_SO");
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp = GENESIS_TS;
+        svm.set_sysvar(&clock);
+
+        let payer = Pubkey::new_unique();
+        svm.airdrop(&payer, 1_000 * SOL).unwrap();
+        svm.airdrop(&DEV_TREASURY, 100 * SOL).unwrap();
+
+        let mut env = Self { svm, payer };
+        let ix = env.init_global_state_ix();
+        env.ok(&[ix]);
+        env
+    }
+
+    // ── clock ───────────────────────────────────────────────────────────────
+
+    pub fn now(&self) -> i64 {
+        self.svm.get_sysvar::<Clock>().unix_timestamp
+    }
+
+    pub fn warp_to(&mut self, ts: i64) {
+        let mut clock = self.svm.get_sysvar::<Clock>();
+        assert!(ts >= clock.unix_timestamp, "cannot warp backwards");
+        clock.slot += ((ts - clock.unix_timestamp) as u64 * 5 / 2).max(1);
+        clock.unix_timestamp = ts;
+        self.svm.set_sysvar(&clock);
+    }
+
+    pub fn warp(&mut self, secs: i64) {
+        self.warp_to(self.now() + secs);
+    }
+
+    // ── transactions ────────────────────────────────────────────────────────
+
+    // litesvm's error type, not ours to box
+    #[allow(clippy::result_large_err)]
+    pub fn send(&mut self, ixs: &[Instruction]) -> TransactionResult {
+        let mut all = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
+        all.extend_from_slice(ixs);
+        let msg =
+            Message::new_with_blockhash(&all, Some(&self.payer), &self.svm.latest_blockhash());
+        self.svm.send_transaction(Transaction::new_unsigned(msg))
+    }
+
+    /// Sends and panics with the program logs on failure.
+    pub fn ok(&mut self, ixs: &[Instruction]) -> TransactionMetadata {
+        self.send(ixs).unwrap_or_else(|e| failed(&e))
+    }
+
+    /// Simulates a view instruction and decodes its Anchor return data.
+    pub fn view<T: AnchorDeserialize>(&mut self, ix: Instruction) -> T {
+        let msg =
+            Message::new_with_blockhash(&[ix], Some(&self.payer), &self.svm.latest_blockhash());
+        let sim = self
+            .svm
+            .simulate_transaction(Transaction::new_unsigned(msg))
+            .unwrap_or_else(|e| failed(&e));
+        T::deserialize(&mut &sim.meta.return_data.data[..]).expect("bad return data")
+    }
+
+    // ── wallets, mints and token accounts ───────────────────────────────────
+
+    /// A new wallet holding 100 SOL.
+    pub fn wallet(&mut self) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.svm.airdrop(&key, 100 * SOL).unwrap();
+        key
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.svm.get_account(key).map_or(0, |a| a.lamports)
+    }
+
+    pub fn is_closed(&self, key: &Pubkey) -> bool {
+        !matches!(self.svm.get_account(key), Some(a) if a.lamports > 0)
+    }
+
+    /// Mint (6 decimals) under `token_program`, with the payer as authority.
+    pub fn create_mint(&mut self, token_program: Pubkey) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        self.alloc(mint, spl_token_2022::state::Mint::LEN, token_program);
+        let ix = spl_token_2022::instruction::initialize_mint2(
+            &token_program,
+            &mint,
+            &self.payer,
+            None,
+            6,
+        )
+        .unwrap();
+        self.ok(&[ix]);
+        mint
+    }
+
+    /// Token-2022 mint charging `fee_bps` on every transfer.
+    pub fn create_fee_mint(&mut self, fee_bps: u16) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+            ExtensionType::TransferFeeConfig,
+        ])
+        .unwrap();
+        self.alloc(mint, len, TOKEN_2022);
+        let fee_ix = transfer_fee::instruction::initialize_transfer_fee_config(
+            &TOKEN_2022,
+            &mint,
+            Some(&self.payer),
+            Some(&self.payer),
+            fee_bps,
+            u64::MAX,
+        )
+        .unwrap();
+        let mint_ix =
+            spl_token_2022::instruction::initialize_mint2(&TOKEN_2022, &mint, &self.payer, None, 6)
+                .unwrap();
+        self.ok(&[fee_ix, mint_ix]);
+        mint
+    }
+
+    fn alloc(&mut self, key: Pubkey, len: usize, owner: Pubkey) {
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(len),
+            data: vec![0; len],
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.svm.set_account(key, account).unwrap();
+    }
+
+    pub fn token_program_of(&self, mint: &Pubkey) -> Pubkey {
+        self.svm.get_account(mint).expect("mint missing").owner
+    }
+
+    pub fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program_of(mint))
+    }
+
+    pub fn create_ata(&mut self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let program = self.token_program_of(mint);
+        let ix = create_associated_token_account_idempotent(&self.payer, owner, mint, &program);
+        self.ok(&[ix]);
+        get_associated_token_address_with_program_id(owner, mint, &program)
+    }
+
+    /// Mints `amount` into `owner`'s ATA, creating it if needed.
+    pub fn fund(&mut self, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Pubkey {
+        let ata = self.create_ata(owner, mint);
+        let program = self.token_program_of(mint);
+        let ix =
+            spl_token_2022::instruction::mint_to(&program, mint, &ata, &self.payer, &[], amount)
+                .unwrap();
+        self.ok(&[ix]);
+        ata
+    }
+
+    /// Token balance of `account`; 0 if it does not exist.
+    pub fn balance(&self, account: &Pubkey) -> u64 {
+        self.svm.get_account(account).map_or(0, |a| {
+            StateWithExtensions::<spl_token_2022::state::Account>::unpack(&a.data)
+                .map_or(0, |s| s.base.amount)
+        })
+    }
+
+    pub fn balance_of(&self, owner: &Pubkey, mint: &Pubkey) -> u64 {
+        self.balance(&self.ata(owner, mint))
+    }
+
+    /// Withheld Token-2022 transfer fees sitting on `account`.
+    pub fn withheld(&self, account: &Pubkey) -> u64 {
+        let a = self.svm.get_account(account).expect("account missing");
+        let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&a.data).unwrap();
+        state
+            .get_extension::<transfer_fee::TransferFeeAmount>()
+            .map_or(0, |f| u64::from(f.withheld_amount))
+    }
+
+    // ── program accounts ────────────────────────────────────────────────────
+
+    pub fn vault(&self, vault: &Pubkey) -> Vault {
+        let a = self.svm.get_account(vault).expect("vault missing");
+        driplet_vaults_sdk::decode_vault(&a.data).unwrap()
+    }
+
+    pub fn user_stake(&self, vault: &Pubkey, owner: &Pubkey) -> UserStake {
+        let a = self
+            .svm
+            .get_account(&pda::user(vault, owner).0)
+            .expect("user stake missing");
+        driplet_vaults_sdk::decode_user_stake(&a.data).unwrap()
+    }
+
+    pub fn global_state(&self) -> GlobalState {
+        use anchor_lang::AccountDeserialize;
+        let a = self
+            .svm
+            .get_account(&pda::state().0)
+            .expect("state missing");
+        GlobalState::try_deserialize(&mut &a.data[..]).unwrap()
+    }
+
+    pub fn crank_pool(&self) -> CrankPool {
+        use anchor_lang::AccountDeserialize;
+        let a = self
+            .svm
+            .get_account(&pda::crank_pool().0)
+            .expect("crank pool missing");
+        CrankPool::try_deserialize(&mut &a.data[..]).unwrap()
+    }
+
+    pub fn unclaimed_principal(&self, vault: &Pubkey, owner: &Pubkey) -> UnclaimedPrincipal {
+        use anchor_lang::AccountDeserialize;
+        let a = self
+            .svm
+            .get_account(&pda::unclaimed(vault, owner).0)
+            .expect("unclaimed principal missing");
+        UnclaimedPrincipal::try_deserialize(&mut &a.data[..]).unwrap()
+    }
+
+    /// Current `GlobalState.admin`.
+    pub fn admin(&self) -> Pubkey {
+        self.global_state().admin
+    }
+
+    /// Current `GlobalState.fee_recipient`; the `dev_treasury` account.
+    pub fn treasury(&self) -> Pubkey {
+        self.global_state().fee_recipient
+    }
+
+    // ── vault setup ─────────────────────────────────────────────────────────
+
+    /// A 30-day linear vault starting now, over two fresh SPL mints.
+    pub fn vault_params(&mut self) -> VaultParams {
+        let mint = self.create_mint(TOKEN);
+        let reward_mint = self.create_mint(TOKEN);
+        VaultParams {
+            creator: self.wallet(),
+            mint,
+            reward_mint,
+            vault_id: 0,
+            reward_net: 1_000_000,
+            start_time: Some(self.now()),
+            duration_secs: 30 * DAY,
+            schedule: EmissionSchedule::Linear,
+            policy: VaultPolicy::default(),
+        }
+    }
+
+    pub fn vault_accounts(&self, p: &VaultParams) -> VaultAccounts {
+        let vault = pda::vault(&p.mint, &p.creator, p.vault_id).0;
+        VaultAccounts {
+            vault,
+            escrow_ata: self.ata(&pda::vault_escrow(&vault).0, &p.mint),
+            reward_ata: self.ata(&pda::vault_reward(&vault).0, &p.reward_mint),
+            creator_ata: self.ata(&p.creator, &p.reward_mint),
+            dev_ata: self.ata(&self.treasury(), &p.reward_mint),
+        }
+    }
+
+    /// Pre-creates the vault's token accounts and funds the creator with the
+    /// gross reward (net plus the 3% fee).
+    pub fn prepare_vault(&mut self, p: &VaultParams) -> VaultAccounts {
+        let vault = pda::vault(&p.mint, &p.creator, p.vault_id).0;
+        self.create_ata(&pda::vault_escrow(&vault).0, &p.mint);
+        self.create_ata(&pda::vault_reward(&vault).0, &p.reward_mint);
+        let treasury = self.treasury();
+        self.create_ata(&treasury, &p.reward_mint);
+        self.fund(&p.creator, &p.reward_mint, gross(p.reward_net));
+        self.vault_accounts(p)
+    }
+
+    pub fn create_vault_ix(&self, p: &VaultParams, a: &VaultAccounts) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::CreateVault {
+                global_state: pda::state().0,
+                creator: p.creator,
+                dev_treasury: self.treasury(),
+                mint: p.mint,
+                reward_mint: p.reward_mint,
+                vault: a.vault,
+                vault_escrow_authority: pda::vault_escrow(&a.vault).0,
+                vault_reward_authority: pda::vault_reward(&a.vault).0,
+                vault_escrow_ata: a.escrow_ata,
+                reward_vault_ata: a.reward_ata,
+                creator_token_ata: a.creator_ata,
+                dev_token_ata: a.dev_ata,
+                token_program: self.token_program_of(&p.mint),
+                reward_token_program: self.token_program_of(&p.reward_mint),
+                system_program: solana_sdk::system_program::ID,
+                mint_rule: pda::mint_rule(&p.mint).0,
+                reward_mint_rule: pda::mint_rule(&p.reward_mint).0,
+            }
+            .to_account_metas(None),
+            data: instruction::CreateVault {
+                reward_net: p.reward_net,
+                maybe_start_time: p.start_time,
+                vault_id: p.vault_id,
+                duration_secs: p.duration_secs,
+                schedule: p.schedule.clone(),
+                policy: p.policy,
+            }
+            .data(),
+        }
+    }
+
+    pub fn create_vault(&mut self, p: &VaultParams) -> Pubkey {
+        let a = self.prepare_vault(p);
+        let ix = self.create_vault_ix(p, &a);
+        self.ok(&[ix]);
+        a.vault
+    }
+
+    // ── instruction builders ────────────────────────────────────────────────
+
+    pub fn init_global_state_ix(&self) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::InitGlobalState {
+                global_state: pda::state().0,
+                dev_treasury: DEV_TREASURY,
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::InitGlobalState {}.data(),
+        }
+    }
+
+    /// Writes a rent-exempt account owned by the program, e.g. one in an
+    /// older layout.
+    pub fn set_program_account(&mut self, key: Pubkey, data: Vec<u8>) {
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.svm.set_account(key, account).unwrap();
+    }
+
+    pub fn migrate_vault_ix(&self, vault: &Pubkey, principal: PrincipalPolicy) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::MigrateVault {
+                admin: self.admin(),
+                global_state: pda::state().0,
+                vault: *vault,
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::MigrateVault { principal }.data(),
+        }
+    }
+
+    pub fn migrate_user_stake_ix(&self, user_stake: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::MigrateUserStake {
+                payer: self.payer,
+                user_stake: *user_stake,
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::MigrateUserStake {}.data(),
+        }
+    }
+
+    pub fn set_new_vaults_disabled_ix(&self, signer: Pubkey, disabled: bool) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::SetNewVaultsDisabled {
+                admin: signer,
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::SetNewVaultsDisabled { disabled }.data(),
+        }
+    }
+
+    pub fn disable_new_vaults_ix(&self, signer: Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::DisableNewVaults {
+                dev_treasury: signer,
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::DisableNewVaults {}.data(),
+        }
+    }
+
+    pub fn set_mint_allowlist_only_ix(&self, allowlist_only: bool) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::SetMintAllowlistOnly {
+                admin: self.admin(),
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::SetMintAllowlistOnly { allowlist_only }.data(),
+        }
+    }
+
+    pub fn set_mint_listing_ix(&self, mint: &Pubkey, listing: MintListing) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::SetMintListing {
+                admin: self.admin(),
+                global_state: pda::state().0,
+                mint: *mint,
+                mint_rule: pda::mint_rule(mint).0,
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::SetMintListing { listing }.data(),
+        }
+    }
+
+    pub fn clear_mint_listing_ix(&self, mint: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::ClearMintListing {
+                admin: self.admin(),
+                global_state: pda::state().0,
+                mint_rule: pda::mint_rule(mint).0,
+            }
+            .to_account_metas(None),
+            data: instruction::ClearMintListing {}.data(),
+        }
+    }
+
+    pub fn migrate_global_state_ix(&self, signer: Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::MigrateGlobalState {
+                admin: signer,
+                global_state: pda::state().0,
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::MigrateGlobalState {}.data(),
+        }
+    }
+
+    /// Replaces `GlobalState` with the original 8+40-byte layout, as the
+    /// first deployment left it.
+    pub fn set_legacy_global_state(&mut self, new_vaults_disabled: bool, admin: Pubkey) {
+        let mut data = vec![0; 8 + 40];
+        data[..8].copy_from_slice(GlobalState::DISCRIMINATOR);
+        data[8] = new_vaults_disabled as u8;
+        data[9..41].copy_from_slice(admin.as_ref());
+        self.set_program_account(pda::state().0, data);
+    }
+
+    pub fn set_duration_bounds_ix(
+        &self,
+        min_duration_secs: i64,
+        max_duration_secs: i64,
+    ) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::SetDurationBounds {
+                admin: self.admin(),
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::SetDurationBounds {
+                min_duration_secs,
+                max_duration_secs,
+            }
+            .data(),
+        }
+    }
+
+    pub fn set_start_bounds_ix(
+        &self,
+        min_lead_secs: i64,
+        max_horizon_secs: i64,
+        max_backdate_secs: i64,
+    ) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::SetStartBounds {
+                admin: self.admin(),
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::SetStartBounds {
+                min_lead_secs,
+                max_horizon_secs,
+                max_backdate_secs,
+            }
+            .data(),
+        }
+    }
+
+    pub fn propose_admin_ix(&self, signer: Pubkey, new_admin: Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::ProposeAdmin {
+                admin: signer,
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::ProposeAdmin { new_admin }.data(),
+        }
+    }
+
+    pub fn accept_admin_ix(&self, signer: Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::AcceptAdmin {
+                pending_admin: signer,
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::AcceptAdmin {}.data(),
+        }
+    }
+
+    pub fn retire_program_ix(&self, grace_secs: Option<i64>) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::RetireProgram {
+                admin: self.admin(),
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::RetireProgram { grace_secs }.data(),
+        }
+    }
+
+    pub fn request_unretire_ix(&self) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::RequestUnretire {
+                admin: self.admin(),
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::RequestUnretire {}.data(),
+        }
+    }
+
+    pub fn unretire_program_ix(&self) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::UnretireProgram {
+                admin: self.admin(),
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::UnretireProgram {}.data(),
+        }
+    }
+
+    pub fn set_global_pause_ix(&self, paused: bool) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::SetGlobalPause {
+                admin: self.admin(),
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::SetGlobalPause { paused }.data(),
+        }
+    }
+
+    pub fn set_crank_tip_ix(
+        &self,
+        tip_lamports: u64,
+        interval_secs: i64,
+        max_tips_per_vault: u64,
+    ) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::SetCrankTip {
+                admin: self.admin(),
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::SetCrankTip {
+                tip_lamports,
+                interval_secs,
+                max_tips_per_vault,
+            }
+            .data(),
+        }
+    }
+
+    pub fn fund_crank_pool_ix(&self, funder: &Pubkey, lamports: u64) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::FundCrankPool {
+                funder: *funder,
+                crank_pool: pda::crank_pool().0,
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::FundCrankPool { lamports }.data(),
+        }
+    }
+
+    pub fn withdraw_crank_pool_ix(&self, lamports: u64) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::WithdrawCrankPool {
+                admin: self.admin(),
+                global_state: pda::state().0,
+                dev_treasury: self.treasury(),
+                crank_pool: pda::crank_pool().0,
+            }
+            .to_account_metas(None),
+            data: instruction::WithdrawCrankPool { lamports }.data(),
+        }
+    }
+
+    /// Without `tipped` the crank pool is left out.
+    pub fn crank_ix(&self, vault: &Pubkey, cranker: &Pubkey, tipped: bool) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::Crank {
+                cranker: *cranker,
+                global_state: pda::state().0,
+                vault: *vault,
+                crank_pool: tipped.then(|| pda::crank_pool().0),
+            }
+            .to_account_metas(None),
+            data: instruction::Crank {}.data(),
+        }
+    }
+
+    pub fn crank_many_ix(&self, vaults: &[Pubkey], cranker: &Pubkey, tipped: bool) -> Instruction {
+        let mut metas = accounts::CrankMany {
+            cranker: *cranker,
+            global_state: pda::state().0,
+            crank_pool: tipped.then(|| pda::crank_pool().0),
+        }
+        .to_account_metas(None);
+        metas.extend(vaults.iter().map(|v| AccountMeta::new(*v, false)));
+        Instruction {
+            program_id: ID,
+            accounts: metas,
+            data: instruction::CrankMany {}.data(),
+        }
+    }
+
+    pub fn pause_vault_ix(&self, vault: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::PauseVault {
+                admin: self.admin(),
+                global_state: pda::state().0,
+                vault: *vault,
+            }
+            .to_account_metas(None),
+            data: instruction::PauseVault {}.data(),
+        }
+    }
+
+    pub fn unpause_vault_ix(&self, vault: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::UnpauseVault {
+                admin: self.admin(),
+                global_state: pda::state().0,
+                vault: *vault,
+            }
+            .to_account_metas(None),
+            data: instruction::UnpauseVault {}.data(),
+        }
+    }
+
+    pub fn update_fees_ix(
+        &self,
+        fee_recipient: Pubkey,
+        creation_fee_lamports: u64,
+        fee_bps: u16,
+        cancel_fee_lamports: u64,
+        cancel_refunds_fee: bool,
+    ) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::UpdateFees {
+                admin: self.admin(),
+                global_state: pda::state().0,
+            }
+            .to_account_metas(None),
+            data: instruction::UpdateFees {
+                fee_recipient,
+                creation_fee_lamports,
+                fee_bps,
+                cancel_fee_lamports,
+                cancel_refunds_fee,
+            }
+            .data(),
+        }
+    }
+
+    pub fn reschedule_start_ix(&self, vault: &Pubkey, new_start_time: i64) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::RescheduleStart {
+                creator: self.vault(vault).creator,
+                global_state: pda::state().0,
+                vault: *vault,
+            }
+            .to_account_metas(None),
+            data: instruction::RescheduleStart { new_start_time }.data(),
+        }
+    }
+
+    /// Refunds go to the creator and, for extra streams, each funder's ATA,
+    /// which must already exist.
+    pub fn cancel_vault_ix(&self, vault: &Pubkey) -> Instruction {
+        let v = self.vault(vault);
+        let reward_mint = v.streams[0].mint;
+        let escrow_authority = pda::vault_escrow(vault).0;
+        let reward_authority = pda::vault_reward(vault).0;
+        let mut metas = accounts::CancelVault {
+            creator: v.creator,
+            global_state: pda::state().0,
+            dev_treasury: self.treasury(),
+            vault: *vault,
+            mint: v.mint,
+            reward_mint,
+            vault_reward_authority: reward_authority,
+            vault_escrow_authority: escrow_authority,
+            reward_vault_ata: self.ata(&reward_authority, &reward_mint),
+            vault_escrow_ata: self.ata(&escrow_authority, &v.mint),
+            creator_token_ata: self.ata(&v.creator, &reward_mint),
+            creator_stake_ata: self.ata(&v.creator, &v.mint),
+            token_program: self.token_program_of(&v.mint),
+            reward_token_program: self.token_program_of(&reward_mint),
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: solana_sdk::system_program::ID,
+        }
+        .to_account_metas(None);
+        metas.extend(self.extra_stream_metas(vault, |s| s.funder));
+        Instruction {
+            program_id: ID,
+            accounts: metas,
+            data: instruction::CancelVault {}.data(),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_reward_stream_ix(
+        &self,
+        vault: &Pubkey,
+        funder: &Pubkey,
+        reward_mint: &Pubkey,
+        reward_net: u64,
+        maybe_start_time: Option<i64>,
+        maybe_end_time: Option<i64>,
+        schedule: EmissionSchedule,
+    ) -> Instruction {
+        let reward_authority = pda::vault_reward(vault).0;
+        Instruction {
+            program_id: ID,
+            accounts: accounts::AddRewardStream {
+                creator: self.vault(vault).creator,
+                funder: *funder,
+                global_state: pda::state().0,
+                dev_treasury: self.treasury(),
+                vault: *vault,
+                reward_mint: *reward_mint,
+                vault_reward_authority: reward_authority,
+                reward_vault_ata: self.ata(&reward_authority, reward_mint),
+                funder_token_ata: self.ata(funder, reward_mint),
+                dev_token_ata: self.ata(&self.treasury(), reward_mint),
+                reward_token_program: self.token_program_of(reward_mint),
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::AddRewardStream {
+                reward_net,
+                maybe_start_time,
+                maybe_end_time,
+                schedule,
+            }
+            .data(),
+        }
+    }
+
+    /// Funds `funder` with the gross amount and adds a stream over the rest
+    /// of the vault term.
+    pub fn add_reward_stream(
+        &mut self,
+        vault: &Pubkey,
+        funder: &Pubkey,
+        reward_mint: &Pubkey,
+        reward_net: u64,
+    ) {
+        self.fund(funder, reward_mint, gross(reward_net));
+        let ix = self.add_reward_stream_ix(
+            vault,
+            funder,
+            reward_mint,
+            reward_net,
+            None,
+            None,
+            EmissionSchedule::Linear,
+        );
+        self.ok(&[ix]);
+    }
+
+    /// Pays to the stream funder's ATA, which must exist.
+    pub fn reclaim_unallocated_ix(&self, vault: &Pubkey, stream_index: u8) -> Instruction {
+        let v = self.vault(vault);
+        let s = &v.streams[stream_index as usize];
+        let reward_authority = pda::vault_reward(vault).0;
+        Instruction {
+            program_id: ID,
+            accounts: accounts::ReclaimUnallocated {
+                creator: v.creator,
+                global_state: pda::state().0,
+                vault: *vault,
+                reward_mint: s.mint,
+                vault_reward_authority: reward_authority,
+                reward_vault_ata: self.ata(&reward_authority, &s.mint),
+                funder_token_ata: self.ata(&s.funder, &s.mint),
+                reward_token_program: self.token_program_of(&s.mint),
+            }
+            .to_account_metas(None),
+            data: instruction::ReclaimUnallocated { stream_index }.data(),
+        }
+    }
+
+    pub fn top_up_ix(
+        &self,
+        vault: &Pubkey,
+        funder: &Pubkey,
+        stream_index: u8,
+        amount: u64,
+    ) -> Instruction {
+        let reward_mint = self.vault(vault).streams[stream_index as usize].mint;
+        let reward_authority = pda::vault_reward(vault).0;
+        Instruction {
+            program_id: ID,
+            accounts: accounts::TopUpRewards {
+                funder: *funder,
+                global_state: pda::state().0,
+                dev_treasury: self.treasury(),
+                vault: *vault,
+                reward_mint,
+                vault_reward_authority: reward_authority,
+                reward_vault_ata: self.ata(&reward_authority, &reward_mint),
+                funder_token_ata: self.ata(funder, &reward_mint),
+                dev_token_ata: self.ata(&self.treasury(), &reward_mint),
+                reward_token_program: self.token_program_of(&reward_mint),
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::TopUpRewards {
+                stream_index,
+                amount,
+            }
+            .data(),
+        }
+    }
+
+    /// `deposit`, or `deposit_locked` when `lock_secs` is set.
+    pub fn deposit_ix(
+        &self,
+        vault: &Pubkey,
+        user: &Pubkey,
+        amount: u64,
+        lock_secs: Option<i64>,
+    ) -> Instruction {
+        let mint = self.vault(vault).mint;
+        let escrow_authority = pda::vault_escrow(vault).0;
+        Instruction {
+            program_id: ID,
+            accounts: accounts::Deposit {
+                user: *user,
+                global_state: pda::state().0,
+                vault: *vault,
+                mint,
+                vault_escrow_authority: escrow_authority,
+                vault_escrow_ata: self.ata(&escrow_authority, &mint),
+                user_stake: pda::user(vault, user).0,
+                user_token_ata: self.ata(user, &mint),
+                token_program: self.token_program_of(&mint),
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: solana_sdk::system_program::ID,
+                rent: solana_sdk::sysvar::rent::ID,
+            }
+            .to_account_metas(None),
+            data: match lock_secs {
+                None => instruction::Deposit { amount }.data(),
+                Some(lock_secs) => instruction::DepositLocked { amount, lock_secs }.data(),
+            },
+        }
+    }
+
+    /// Mints `amount` of the stake mint to `user` and deposits it.
+    pub fn stake(&mut self, vault: &Pubkey, user: &Pubkey, amount: u64) {
+        let mint = self.vault(vault).mint;
+        self.fund(user, &mint, amount);
+        let ix = self.deposit_ix(vault, user, amount, None);
+        self.ok(&[ix]);
+    }
+
+    pub fn expire_lock_ix(&self, vault: &Pubkey, owner: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::ExpireLock {
+                global_state: pda::state().0,
+                vault: *vault,
+                user_stake: pda::user(vault, owner).0,
+            }
+            .to_account_metas(None),
+            data: instruction::ExpireLock {}.data(),
+        }
+    }
+
+    /// `[mint, reward_vault_ata, counterparty_ata, token_program]` for every
+    /// extra stream, as `claim`, `withdraw`, `close_vault` and `release_position`
+    /// expect.
+    pub fn extra_stream_metas(
+        &self,
+        vault: &Pubkey,
+        counterparty: impl Fn(&RewardStream) -> Pubkey,
+    ) -> Vec<AccountMeta> {
+        let reward_authority = pda::vault_reward(vault).0;
+        self.vault(vault).streams[1..]
+            .iter()
+            .flat_map(|s| {
+                let program = self.token_program_of(&s.mint);
+                [
+                    AccountMeta::new_readonly(s.mint, false),
+                    AccountMeta::new(self.ata(&reward_authority, &s.mint), false),
+                    AccountMeta::new(self.ata(&counterparty(s), &s.mint), false),
+                    AccountMeta::new_readonly(program, false),
+                ]
+            })
+            .collect()
+    }
+
+    /// Creates `owner`'s ATA for every extra stream mint.
+    pub fn create_stream_atas(&mut self, vault: &Pubkey, owner: &Pubkey) {
+        let mints: Vec<Pubkey> = self.vault(vault).streams[1..]
+            .iter()
+            .map(|s| s.mint)
+            .collect();
+        for mint in mints {
+            self.create_ata(owner, &mint);
+        }
+    }
+
+    pub fn claim_ix(&self, vault: &Pubkey, user: &Pubkey) -> Instruction {
+        let reward_mint = self.vault(vault).streams[0].mint;
+        let reward_authority = pda::vault_reward(vault).0;
+        let mut metas = accounts::Claim {
+            user: *user,
+            global_state: pda::state().0,
+            vault: *vault,
+            reward_mint,
+            user_stake: pda::user(vault, user).0,
+            vault_reward_authority: reward_authority,
+            reward_vault_ata: self.ata(&reward_authority, &reward_mint),
+            user_reward_ata: self.ata(user, &reward_mint),
+            reward_token_program: self.token_program_of(&reward_mint),
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: solana_sdk::system_program::ID,
+            rent: solana_sdk::sysvar::rent::ID,
+        }
+        .to_account_metas(None);
+        metas.extend(self.extra_stream_metas(vault, |_| *user));
+        Instruction {
+            program_id: ID,
+            accounts: metas,
+            data: instruction::Claim {}.data(),
+        }
+    }
+
+    pub fn claim(&mut self, vault: &Pubkey, user: &Pubkey) -> TransactionMetadata {
+        self.create_stream_atas(vault, user);
+        let ix = self.claim_ix(vault, user);
+        self.ok(&[ix])
+    }
+
+    pub fn withdraw_ix(
+        &self,
+        vault: &Pubkey,
+        user: &Pubkey,
+        amount: u64,
+        claim_all: bool,
+    ) -> Instruction {
+        let v = self.vault(vault);
+        let reward_mint = v.streams[0].mint;
+        let escrow_authority = pda::vault_escrow(vault).0;
+        let reward_authority = pda::vault_reward(vault).0;
+        let mut metas = accounts::Withdraw {
+            user: *user,
+            global_state: pda::state().0,
+            vault: *vault,
+            mint: v.mint,
+            reward_mint,
+            user_stake: pda::user(vault, user).0,
+            vault_escrow_authority: escrow_authority,
+            vault_escrow_ata: self.ata(&escrow_authority, &v.mint),
+            vault_reward_authority: reward_authority,
+            reward_vault_ata: self.ata(&reward_authority, &reward_mint),
+            user_token_ata: self.ata(user, &v.mint),
+            user_reward_ata: self.ata(user, &reward_mint),
+            token_program: self.token_program_of(&v.mint),
+            reward_token_program: self.token_program_of(&reward_mint),
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: solana_sdk::system_program::ID,
+            rent: solana_sdk::sysvar::rent::ID,
+        }
+        .to_account_metas(None);
+        if claim_all {
+            metas.extend(self.extra_stream_metas(vault, |_| *user));
+        }
+        Instruction {
+            program_id: ID,
+            accounts: metas,
+            data: instruction::Withdraw { amount, claim_all }.data(),
+        }
+    }
+
+    pub fn reconcile(&mut self, vault: &Pubkey, stream_index: u8) -> RewardReconciliation {
+        let reward_mint = self.vault(vault).streams[stream_index as usize].mint;
+        let reward_authority = pda::vault_reward(vault).0;
+        let ix = Instruction {
+            program_id: ID,
+            accounts: accounts::ReconcileRewards {
+                global_state: pda::state().0,
+                vault: *vault,
+                vault_reward_authority: reward_authority,
+                reward_vault_ata: self.ata(&reward_authority, &reward_mint),
+            }
+            .to_account_metas(None),
+            data: instruction::ReconcileRewards { stream_index }.data(),
+        };
+        self.view(ix)
+    }
+
+    pub fn preview_pending(&mut self, vault: &Pubkey, owner: &Pubkey) -> PendingPreview {
+        let ix = Instruction {
+            program_id: ID,
+            accounts: accounts::PreviewPending {
+                global_state: pda::state().0,
+                vault: *vault,
+                user_stake: pda::user(vault, owner).0,
+            }
+            .to_account_metas(None),
+            data: instruction::PreviewPending {}.data(),
+        };
+        self.view(ix)
+    }
+
+    pub fn preview_vault_state(&mut self, vault: &Pubkey, at_ts: i64) -> VaultStatePreview {
+        let ix = Instruction {
+            program_id: ID,
+            accounts: accounts::PreviewVaultState {
+                global_state: pda::state().0,
+                vault: *vault,
+            }
+            .to_account_metas(None),
+            data: instruction::PreviewVaultState { at_ts }.data(),
+        };
+        self.view(ix)
+    }
+
+    /// Expects the treasury's ATAs for every extra stream mint to exist.
+    pub fn close_vault_ix(&self, vault: &Pubkey) -> Instruction {
+        let v = self.vault(vault);
+        let reward_mint = v.streams[0].mint;
+        let escrow_authority = pda::vault_escrow(vault).0;
+        let reward_authority = pda::vault_reward(vault).0;
+        let treasury = self.treasury();
+        let mut metas = accounts::CloseVaultTreasuryOnly {
+            admin: self.admin(),
+            global_state: pda::state().0,
+            dev_treasury: treasury,
+            vault: *vault,
+            mint: v.mint,
+            reward_mint,
+            vault_reward_authority: reward_authority,
+            vault_escrow_authority: escrow_authority,
+            reward_vault_ata: self.ata(&reward_authority, &reward_mint),
+            vault_escrow_ata: self.ata(&escrow_authority, &v.mint),
+            dev_token_ata: self.ata(&treasury, &reward_mint),
+            dev_stake_ata: self.ata(&treasury, &v.mint),
+            token_program: self.token_program_of(&v.mint),
+            reward_token_program: self.token_program_of(&reward_mint),
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: solana_sdk::system_program::ID,
+            rent: solana_sdk::sysvar::rent::ID,
+        }
+        .to_account_metas(None);
+        metas.extend(self.extra_stream_metas(vault, |_| treasury));
+        Instruction {
+            program_id: ID,
+            accounts: metas,
+            data: instruction::CloseVault {}.data(),
+        }
+    }
+
+    /// Expects `owner`'s ATAs for every extra stream mint to exist.
+    pub fn release_position_ix(&self, vault: &Pubkey, owner: &Pubkey) -> Instruction {
+        let v = self.vault(vault);
+        let reward_mint = v.streams[0].mint;
+        let escrow_authority = pda::vault_escrow(vault).0;
+        let reward_authority = pda::vault_reward(vault).0;
+        let unclaimed = pda::unclaimed(vault, owner).0;
+        let mut metas = accounts::ReleasePosition {
+            payer: self.payer,
+            global_state: pda::state().0,
+            vault: *vault,
+            user_stake: pda::user(vault, owner).0,
+            owner: *owner,
+            mint: v.mint,
+            reward_mint,
+            vault_reward_authority: reward_authority,
+            reward_vault_ata: self.ata(&reward_authority, &reward_mint),
+            owner_reward_ata: self.ata(owner, &reward_mint),
+            vault_escrow_authority: escrow_authority,
+            vault_escrow_ata: self.ata(&escrow_authority, &v.mint),
+            unclaimed_principal: unclaimed,
+            unclaimed_principal_ata: self.ata(&unclaimed, &v.mint),
+            token_program: self.token_program_of(&v.mint),
+            reward_token_program: self.token_program_of(&reward_mint),
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: solana_sdk::system_program::ID,
+        }
+        .to_account_metas(None);
+        metas.extend(self.extra_stream_metas(vault, |_| *owner));
+        Instruction {
+            program_id: ID,
+            accounts: metas,
+            data: instruction::ReleasePosition {}.data(),
+        }
+    }
+
+    pub fn owner_reclaim_ix(&self, vault: &Pubkey, owner: &Pubkey, mint: &Pubkey) -> Instruction {
+        let unclaimed = pda::unclaimed(vault, owner).0;
+        Instruction {
+            program_id: ID,
+            accounts: accounts::OwnerReclaim {
+                owner: *owner,
+                unclaimed_principal: unclaimed,
+                mint: *mint,
+                unclaimed_principal_ata: self.ata(&unclaimed, mint),
+                user_token_ata: self.ata(owner, mint),
+                token_program: self.token_program_of(mint),
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::OwnerReclaim {}.data(),
+        }
+    }
+
+    pub fn emergency_withdraw_ix(&self, vault: &Pubkey, user: &Pubkey) -> Instruction {
+        let mint = self.vault(vault).mint;
+        let escrow_authority = pda::vault_escrow(vault).0;
+        Instruction {
+            program_id: ID,
+            accounts: accounts::EmergencyWithdraw {
+                user: *user,
+                global_state: pda::state().0,
+                vault: *vault,
+                mint,
+                user_stake: pda::user(vault, user).0,
+                vault_escrow_authority: escrow_authority,
+                vault_escrow_ata: self.ata(&escrow_authority, &mint),
+                user_token_ata: self.ata(user, &mint),
+                token_program: self.token_program_of(&mint),
+                associated_token_program: anchor_spl::associated_token::ID,
+                system_program: solana_sdk::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::EmergencyWithdraw {}.data(),
+        }
+    }
+
+    pub fn close_user_stake_ix(&self, vault: &Pubkey, user: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::CloseUserStake {
+                user: *user,
+                vault: *vault,
+                user_stake: pda::user(vault, user).0,
+            }
+            .to_account_metas(None),
+            data: instruction::CloseUserStake {}.data(),
+        }
+    }
+
+    pub fn admin_close_stake_ix(&self, vault: &Pubkey, owner: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: ID,
+            accounts: accounts::AdminCloseExpiredStake {
+                admin: self.admin(),
+                global_state: pda::state().0,
+                dev_treasury: self.treasury(),
+                user_stake: pda::user(vault, owner).0,
+                vault: *vault,
+            }
+            .to_account_metas(None),
+            data: instruction::AdminCloseExpiredStake {}.data(),
+        }
+    }
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the creator must send for `reward_net` to arrive after the 3% fee.
+pub fn gross(reward_net: u64) -> u64 {
+    reward_net + reward_net * 300 / 10_000
+}
+
+/// Anchor events emitted by a transaction, in log order.
+pub fn events<T: AnchorDeserialize + Discriminator>(meta: &TransactionMetadata) -> Vec<T> {
+    meta.logs
+        .iter()
+        .filter_map(|l| l.strip_prefix("Program data: "))
+        .filter_map(|b64| STANDARD.decode(b64).ok())
+        .filter(|data| data.starts_with(T::DISCRIMINATOR))
+        .map(|data| T::deserialize(&mut &data[T::DISCRIMINATOR.len()..]).expect("bad event"))
+        .collect()
+}
+
+/// Asserts the transaction failed with `code` (a `VaultError` or Anchor
+/// `ErrorCode`, via `u32::from`).
+pub fn assert_err(res: TransactionResult, code: u32) {
+    match res {
+        Ok(meta) => panic!(
+            "expected error {code}, got success:\n{}",
+            meta.logs.join("\n")
+        ),
+        Err(e) => match e.err {
+            TransactionError::InstructionError(_, InstructionError::Custom(c)) if c == code => {}
+            _ => panic!(
+                "expected error {code}, got {:?}:\n{}",
+                e.err,
+                e.meta.logs.join("\n")
+            ),
+        },
+    }
+}
+
+pub fn assert_vault_err(res: TransactionResult, err: VaultError) {
+    assert_err(res, u32::from(err));
+}
+
+fn failed(e: &FailedTransactionMetadata) -> ! {
+    panic!(
+        "transaction failed: {:?}\n{}",
+        e.err,
+        e.meta.logs.join("\n")
+    )
+}
+
+fn program_so() -> PathBuf {
+    if let Ok(path) = std::env::var("DRIPLET_VAULTS_SO") {
+        return path.into();
+    }
+    // `anchor build` at the workspace root
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../target/deploy/driplet_vaults.so")
+}
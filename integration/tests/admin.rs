@@ -0,0 +1,516 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorSerialize, Discriminator};
+use driplet_vaults::{
+    GlobalStateChanged, MintListing, MintListingChanged, PrincipalPolicy, RewardStreamAdded,
+    StakeClosed, UserStake, UserStakeV1, Vault, VaultError, VaultV1, DEV_TREASURY, VAULT_VERSION,
+};
+use driplet_vaults_integration::*;
+
+#[test]
+fn global_state_defaults_and_single_init() {
+    let mut env = Env::new();
+    let state = env.global_state();
+    assert!(!state.new_vaults_disabled);
+    assert_eq!(state.admin, DEV_TREASURY);
+    assert_eq!(
+        (state.min_duration_secs, state.max_duration_secs),
+        (DAY, 2 * YEAR)
+    );
+    assert_eq!(state.fee_recipient, DEV_TREASURY);
+    assert_eq!(
+        (state.creation_fee_lamports, state.fee_bps),
+        (CREATION_FEE_LAMPORTS, 300)
+    );
+    assert_eq!(state.pending_admin, Pubkey::default());
+    assert!(!state.paused);
+    assert_eq!(
+        (
+            state.min_start_lead_secs,
+            state.max_start_horizon_secs,
+            state.max_start_backdate_secs
+        ),
+        (0, YEAR, 60)
+    );
+
+    let ix = env.init_global_state_ix();
+    assert!(env.send(&[ix]).is_err());
+}
+
+#[test]
+fn migrate_global_state_upgrades_the_original_layout_once() {
+    let mut env = Env::new();
+    env.set_legacy_global_state(true, DEV_TREASURY);
+
+    let stranger = env.wallet();
+    let ix = env.migrate_global_state_ix(stranger);
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+
+    let ix = env.migrate_global_state_ix(DEV_TREASURY);
+    let meta = env.ok(&[ix]);
+    assert!(events::<GlobalStateChanged>(&meta)[0].new_vaults_disabled);
+    let state = env.global_state();
+    assert!(state.new_vaults_disabled);
+    assert_eq!((state.admin, state.fee_recipient), (DEV_TREASURY, DEV_TREASURY));
+    assert_eq!(
+        (state.creation_fee_lamports, state.fee_bps),
+        (CREATION_FEE_LAMPORTS, 300)
+    );
+    assert_eq!(
+        (state.min_duration_secs, state.max_duration_secs),
+        (DAY, 2 * YEAR)
+    );
+
+    // The migrated state works with the admin instructions
+    let ix = env.set_duration_bounds_ix(7 * DAY, 14 * DAY);
+    env.ok(&[ix]);
+    assert_eq!(env.global_state().max_duration_secs, 14 * DAY);
+
+    let ix = env.migrate_global_state_ix(DEV_TREASURY);
+    assert_vault_err(env.send(&[ix]), VaultError::AlreadyMigrated);
+}
+
+#[test]
+fn v1_vaults_and_stakes_migrate_in_place_once() {
+    let mut env = Env::new();
+    let (vault, stake) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let old_vault = VaultV1 {
+        id: 0,
+        creator: env.wallet(),
+        mint: Pubkey::new_unique(),
+        start_time: GENESIS_TS,
+        end_time: GENESIS_TS + 182 * DAY,
+        reward_net: 1_000_000,
+        reward_fee: 30_000,
+        reward_gross: 1_030_000,
+        rate_fp: 0,
+        emission_acc_fp: 0,
+        emitted: 0,
+        last_update_time: GENESIS_TS,
+        acc_reward_per_token: 0,
+        unallocated: 0,
+        total_staked: 100,
+        bump: 255,
+        vault_escrow_bump: 255,
+        vault_reward_bump: 255,
+        version: 1,
+    };
+    let mut data = Vault::DISCRIMINATOR.to_vec();
+    old_vault.serialize(&mut data).unwrap();
+    data.resize(VaultV1::LEN, 0);
+    env.set_program_account(vault, data);
+    let old_stake = UserStakeV1 {
+        owner: env.wallet(),
+        vault,
+        amount: 100,
+        reward_debt: 0,
+        initialized: 1,
+    };
+    let mut data = UserStake::DISCRIMINATOR.to_vec();
+    old_stake.serialize(&mut data).unwrap();
+    data.resize(UserStakeV1::LEN, 0);
+    env.set_program_account(stake, data);
+
+    // The admin picks what close_vault does with unwithdrawn principal
+    let mut ix = env.migrate_vault_ix(&vault, PrincipalPolicy::SweepToTreasury);
+    ix.accounts[0].pubkey = env.wallet();
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+
+    let ix = env.migrate_vault_ix(&vault, PrincipalPolicy::SweepToTreasury);
+    let ix2 = env.migrate_user_stake_ix(&stake);
+    env.ok(&[ix, ix2]);
+    assert_eq!(
+        env.svm.get_account(&vault).unwrap().data.len(),
+        8 + Vault::SPACE
+    );
+    assert_eq!(
+        env.svm.get_account(&stake).unwrap().data.len(),
+        8 + UserStake::SPACE
+    );
+    let v = env.vault(&vault);
+    assert_eq!((v.version, v.total_weighted_stake), (VAULT_VERSION, 100));
+    assert_eq!(v.streams[0].reward_net, 1_000_000);
+    assert!(v.policy.principal == PrincipalPolicy::SweepToTreasury);
+
+    let ix = env.migrate_vault_ix(&vault, PrincipalPolicy::SweepToTreasury);
+    assert_vault_err(env.send(&[ix]), VaultError::AlreadyMigrated);
+    let ix = env.migrate_user_stake_ix(&stake);
+    assert_vault_err(env.send(&[ix]), VaultError::AlreadyMigrated);
+}
+
+#[test]
+fn disabling_new_vaults_blocks_creation_until_reenabled() {
+    let mut env = Env::new();
+    let stranger = env.wallet();
+    let ix = env.set_new_vaults_disabled_ix(stranger, true);
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+
+    let ix = env.set_new_vaults_disabled_ix(DEV_TREASURY, true);
+    let meta = env.ok(&[ix]);
+    assert!(events::<GlobalStateChanged>(&meta)[0].new_vaults_disabled);
+    assert!(env.global_state().new_vaults_disabled);
+
+    let p = env.vault_params();
+    let a = env.prepare_vault(&p);
+    let ix = env.create_vault_ix(&p, &a);
+    assert_vault_err(
+        env.send(std::slice::from_ref(&ix)),
+        VaultError::VaultCreationDisabled,
+    );
+
+    let ix2 = env.set_new_vaults_disabled_ix(DEV_TREASURY, false);
+    env.ok(&[ix2]);
+    assert!(!env.global_state().new_vaults_disabled);
+    env.ok(&[ix]);
+}
+
+#[test]
+fn disable_new_vaults_still_works_for_the_current_admin() {
+    let mut env = Env::new();
+    let stranger = env.wallet();
+    let ix = env.disable_new_vaults_ix(stranger);
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+
+    let ix = env.disable_new_vaults_ix(DEV_TREASURY);
+    let meta = env.ok(&[ix]);
+    assert!(events::<GlobalStateChanged>(&meta)[0].new_vaults_disabled);
+    assert!(env.global_state().new_vaults_disabled);
+
+    // Reversible through the newer instruction
+    let ix = env.set_new_vaults_disabled_ix(DEV_TREASURY, false);
+    env.ok(&[ix]);
+    assert!(!env.global_state().new_vaults_disabled);
+}
+
+#[test]
+fn mint_listings_gate_create_vault() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+
+    // Denying either mint blocks the vault, without affecting other mints
+    let ix = env.set_mint_listing_ix(&p.reward_mint, MintListing::Denied);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<MintListingChanged>(&meta)[0];
+    assert_eq!(
+        (ev.mint, ev.listing),
+        (p.reward_mint, Some(MintListing::Denied))
+    );
+    let a = env.prepare_vault(&p);
+    let ix = env.create_vault_ix(&p, &a);
+    assert_vault_err(env.send(&[ix]), VaultError::MintNotAllowed);
+    let other = env.vault_params();
+    env.create_vault(&other);
+
+    // Clearing the listing returns the mint to the default
+    let ix = env.clear_mint_listing_ix(&p.reward_mint);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<MintListingChanged>(&meta)[0].listing, None);
+    assert!(env.is_closed(&driplet_vaults_sdk::pda::mint_rule(&p.reward_mint).0));
+
+    // Under the allowlist only Allowed mints pass, for staking and rewards
+    let ix = env.set_mint_allowlist_only_ix(true);
+    env.ok(&[ix]);
+    let ix = env.set_mint_listing_ix(&p.mint, MintListing::Allowed);
+    env.ok(&[ix]);
+    let ix = env.create_vault_ix(&p, &a);
+    assert_vault_err(
+        env.send(std::slice::from_ref(&ix)),
+        VaultError::MintNotAllowed,
+    );
+    let listing = env.set_mint_listing_ix(&p.reward_mint, MintListing::Allowed);
+    env.ok(&[listing]);
+    env.ok(&[ix]);
+
+    let stranger = env.wallet();
+    let mut ix = env.set_mint_listing_ix(&p.mint, MintListing::Denied);
+    ix.accounts[0].pubkey = stranger;
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+}
+
+#[test]
+fn duration_bounds_gate_create_vault() {
+    let mut env = Env::new();
+    let ix = env.set_duration_bounds_ix(0, DAY);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidDuration);
+    let ix = env.set_duration_bounds_ix(2 * DAY, DAY);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidDuration);
+
+    let ix = env.set_duration_bounds_ix(7 * DAY, 14 * DAY);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<GlobalStateChanged>(&meta)[0];
+    assert_eq!(
+        (ev.min_duration_secs, ev.max_duration_secs),
+        (7 * DAY, 14 * DAY)
+    );
+
+    for duration in [7 * DAY - 1, 14 * DAY + 1] {
+        let mut p = env.vault_params();
+        p.duration_secs = duration;
+        let a = env.prepare_vault(&p);
+        let ix = env.create_vault_ix(&p, &a);
+        assert_vault_err(env.send(&[ix]), VaultError::InvalidDuration);
+    }
+    let mut p = env.vault_params();
+    p.duration_secs = 14 * DAY;
+    env.create_vault(&p);
+}
+
+#[test]
+fn close_vault_waits_three_years_after_end() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let end = env.vault(&vault).end_time;
+
+    env.warp_to(end + GRACE_SECS - 1);
+    let ix = env.close_vault_ix(&vault);
+    assert_vault_err(
+        env.send(std::slice::from_ref(&ix)),
+        VaultError::GraceNotElapsed,
+    );
+
+    env.warp(1);
+    env.ok(&[ix]);
+}
+
+#[test]
+fn close_vault_is_admin_only() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    env.warp(30 * DAY + GRACE_SECS);
+
+    let stranger = env.wallet();
+    let mut ix = env.close_vault_ix(&vault);
+    ix.accounts[0].pubkey = stranger;
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+}
+
+#[test]
+fn admin_transfer_takes_two_steps() {
+    let mut env = Env::new();
+    let new_admin = env.wallet();
+    let stranger = env.wallet();
+
+    let ix = env.propose_admin_ix(stranger, stranger);
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+
+    let ix = env.propose_admin_ix(DEV_TREASURY, new_admin);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<GlobalStateChanged>(&meta)[0];
+    assert_eq!((ev.admin, ev.pending_admin), (DEV_TREASURY, new_admin));
+
+    // Nothing changes hands until the proposed key accepts
+    let ix = env.accept_admin_ix(stranger);
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+    assert_eq!(env.admin(), DEV_TREASURY);
+
+    let ix = env.accept_admin_ix(new_admin);
+    env.ok(&[ix]);
+    let state = env.global_state();
+    assert_eq!(
+        (state.admin, state.pending_admin),
+        (new_admin, Pubkey::default())
+    );
+    // Fees keep flowing to the treasury until update_fees says otherwise
+    assert_eq!(state.fee_recipient, DEV_TREASURY);
+
+    let ix = env.set_new_vaults_disabled_ix(DEV_TREASURY, true);
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+    let ix = env.set_duration_bounds_ix(7 * DAY, 14 * DAY);
+    env.ok(&[ix]);
+
+    // A proposal can be withdrawn by proposing the default key
+    let ix = env.propose_admin_ix(new_admin, stranger);
+    env.ok(&[ix]);
+    let ix = env.propose_admin_ix(new_admin, Pubkey::default());
+    env.ok(&[ix]);
+    let ix = env.accept_admin_ix(stranger);
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+}
+
+#[test]
+fn update_fees_redirects_and_reprices_fees() {
+    let mut env = Env::new();
+    let recipient = env.wallet();
+
+    let ix = env.update_fees_ix(recipient, 0, 1_001, 0, false);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidFee);
+    let ix = env.update_fees_ix(Pubkey::default(), 0, 500, 0, false);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidFee);
+
+    let ix = env.update_fees_ix(recipient, 0, 500, 0, false);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<GlobalStateChanged>(&meta)[0];
+    assert_eq!(
+        (ev.fee_recipient, ev.creation_fee_lamports, ev.fee_bps),
+        (recipient, 0, 500)
+    );
+
+    // The old treasury is no longer accepted as the fee account
+    let p = env.vault_params();
+    let a = env.prepare_vault(&p);
+    env.fund(&p.creator, &p.reward_mint, 20_000);
+    let mut ix = env.create_vault_ix(&p, &a);
+    ix.accounts[2].pubkey = DEV_TREASURY;
+    assert_vault_err(env.send(&[ix]), VaultError::NotTreasury);
+
+    let creator_lamports = env.lamports(&p.creator);
+    let recipient_lamports = env.lamports(&recipient);
+    let ix = env.create_vault_ix(&p, &a);
+    env.ok(&[ix]);
+    assert_eq!(env.lamports(&recipient), recipient_lamports);
+    assert!(creator_lamports - env.lamports(&p.creator) < CREATION_FEE_LAMPORTS);
+    assert_eq!(env.balance_of(&recipient, &p.reward_mint), 50_000);
+    assert_eq!(env.balance_of(&DEV_TREASURY, &p.reward_mint), 0);
+
+    // Streams added later use the new rate too
+    let mint = env.create_mint(TOKEN);
+    env.fund(&p.creator, &mint, 105_000);
+    let ix = env.add_reward_stream_ix(
+        &a.vault,
+        &p.creator,
+        &mint,
+        100_000,
+        None,
+        None,
+        driplet_vaults::EmissionSchedule::Linear,
+    );
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<RewardStreamAdded>(&meta)[0].fee, 5_000);
+    assert_eq!(env.balance_of(&recipient, &mint), 5_000);
+}
+
+#[test]
+fn retirement_blocks_creation_and_shortens_grace() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let end = env.vault(&vault).end_time;
+
+    let ix = env.request_unretire_ix();
+    assert_vault_err(env.send(&[ix]), VaultError::NotRetired);
+    let ix = env.retire_program_ix(Some(DAY));
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidDuration);
+    let ix = env.retire_program_ix(Some(GRACE_SECS + 1));
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidDuration);
+
+    let ix = env.retire_program_ix(Some(90 * DAY));
+    let meta = env.ok(&[ix]);
+    let ev = &events::<GlobalStateChanged>(&meta)[0];
+    assert_eq!(
+        (ev.retired_at, ev.retired_grace_secs),
+        (env.now(), 90 * DAY)
+    );
+    let ix = env.retire_program_ix(None);
+    assert_vault_err(env.send(&[ix]), VaultError::AlreadyRetired);
+
+    let p2 = env.vault_params();
+    let a = env.prepare_vault(&p2);
+    let ix = env.create_vault_ix(&p2, &a);
+    assert_vault_err(env.send(&[ix]), VaultError::ProgramRetired);
+
+    // Live vaults still run their term, then wait the shortened grace
+    env.warp_to(end + 90 * DAY - 1);
+    let close = env.close_vault_ix(&vault);
+    assert_vault_err(
+        env.send(std::slice::from_ref(&close)),
+        VaultError::GraceNotElapsed,
+    );
+
+    // Reversal is timelocked
+    let ix = env.unretire_program_ix();
+    assert_vault_err(env.send(&[ix]), VaultError::TimelockNotElapsed);
+    let ix = env.request_unretire_ix();
+    env.ok(&[ix]);
+    env.warp(7 * DAY - 1);
+    let ix = env.unretire_program_ix();
+    assert_vault_err(
+        env.send(std::slice::from_ref(&ix)),
+        VaultError::TimelockNotElapsed,
+    );
+
+    // Still retired meanwhile, so the shortened grace applies
+    env.ok(&[close]);
+
+    env.warp(1);
+    env.ok(&[ix]);
+    let state = env.global_state();
+    assert_eq!((state.retired_at, state.unretire_requested_at), (0, 0));
+    let p3 = env.vault_params();
+    env.create_vault(&p3);
+}
+
+#[test]
+fn admin_close_expired_stake_waits_for_grace_and_release() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let end = env.vault(&vault).end_time;
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+
+    env.warp_to(end + GRACE_SECS - 1);
+    let ix = env.admin_close_stake_ix(&vault, &alice);
+    assert_vault_err(
+        env.send(std::slice::from_ref(&ix)),
+        VaultError::GraceNotElapsed,
+    );
+
+    // Held principal belongs to the owner
+    env.warp(1);
+    assert_vault_err(env.send(std::slice::from_ref(&ix)), VaultError::StillStaked);
+
+    let release = env.release_position_ix(&vault, &alice);
+    env.ok(&[release]);
+    let user_stake = driplet_vaults_sdk::pda::user(&vault, &alice).0;
+    let rent = env.lamports(&user_stake);
+    let treasury_before = env.lamports(&DEV_TREASURY);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<StakeClosed>(&meta)[0].amount, 0);
+    assert!(env.is_closed(&user_stake));
+    assert_eq!(env.lamports(&DEV_TREASURY), treasury_before + rent);
+}
+
+#[test]
+fn admin_close_expired_stake_keeps_owed_rewards() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let end = env.vault(&vault).end_time;
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+
+    env.warp_to(end + GRACE_SECS);
+    let ix = env.withdraw_ix(&vault, &alice, 1_000, false);
+    env.ok(&[ix]);
+    assert_eq!(env.user_stake(&vault, &alice).owed[0], 1_000_000);
+
+    // The record is empty but still carries Alice's rewards
+    let ix = env.admin_close_stake_ix(&vault, &alice);
+    assert_vault_err(env.send(std::slice::from_ref(&ix)), VaultError::RewardsOwed);
+    assert_eq!(env.vault(&vault).streams[0].total_owed, 1_000_000);
+
+    let release = env.release_position_ix(&vault, &alice);
+    env.ok(&[release]);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 1_000_000);
+    env.ok(&[ix]);
+    let ix = env.close_vault_ix(&vault);
+    env.ok(&[ix]);
+    assert!(env.is_closed(&vault));
+}
+
+#[test]
+fn admin_close_expired_stake_drops_swept_positions() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.policy.principal = PrincipalPolicy::SweepToTreasury;
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+
+    env.warp(30 * DAY + GRACE_SECS);
+    let ix = env.admin_close_stake_ix(&vault, &alice);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<StakeClosed>(&meta)[0];
+    assert_eq!((ev.amount, ev.total_staked), (1_000, 1_000));
+}
@@ -0,0 +1,226 @@
+use anchor_lang::error::ErrorCode;
+use driplet_vaults::{
+    CrankPoolChanged, GlobalStateChanged, VaultCranked, VaultError, DEV_TREASURY,
+};
+use driplet_vaults_integration::*;
+use driplet_vaults_sdk::pda;
+
+const TIP: u64 = 5_000;
+const POOL: u64 = 1_000_000_000;
+const MAX_TIPS: u64 = 100;
+
+/// Tips of TIP per hour (MAX_TIPS per vault), with POOL lamports to pay
+/// them from.
+fn tipping_env() -> Env {
+    let mut env = Env::new();
+    let ix = env.set_crank_tip_ix(TIP, 3_600, MAX_TIPS);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<GlobalStateChanged>(&meta)[0];
+    assert_eq!(
+        (
+            ev.crank_tip_lamports,
+            ev.crank_interval_secs,
+            ev.max_crank_tips_per_vault
+        ),
+        (TIP, 3_600, MAX_TIPS)
+    );
+    let ix = env.fund_crank_pool_ix(&DEV_TREASURY, POOL);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<CrankPoolChanged>(&meta)[0].available, POOL);
+    env
+}
+
+#[test]
+fn anyone_can_crank_emissions_forward() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    env.warp(10 * DAY);
+    assert_eq!(env.vault(&vault).streams[0].emitted, 0);
+
+    // No crank pool needed, and nothing is paid without one
+    let stranger = env.wallet();
+    let before = env.lamports(&stranger);
+    let ix = env.crank_ix(&vault, &stranger, false);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<VaultCranked>(&meta)[0];
+    assert_eq!((ev.emitted.clone(), ev.tip), (vec![333_333], 0));
+    let v = env.vault(&vault);
+    assert_eq!(v.streams[0].emitted, 333_333);
+    // Only tipped cranks are recorded
+    assert_eq!((v.last_cranked_at, v.crank_tips_paid), (0, 0));
+    assert_eq!(
+        ev.acc_reward_per_token,
+        vec![v.streams[0].acc_reward_per_token]
+    );
+    assert_eq!(env.lamports(&stranger), before);
+
+    // Cranking changes nothing about what alice is owed
+    env.claim(&vault, &alice);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 333_333);
+}
+
+#[test]
+fn crank_tip_is_paid_once_per_interval_while_running() {
+    let mut env = tipping_env();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    let cranker = env.wallet();
+    let before = env.lamports(&cranker);
+
+    env.warp(DAY);
+    let ix = env.crank_ix(&vault, &cranker, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<VaultCranked>(&meta)[0].tip, TIP);
+    assert_eq!(env.lamports(&cranker), before + TIP);
+    let tipped_at = env.now();
+    assert_eq!(env.vault(&vault).last_cranked_at, tipped_at);
+
+    // Again within the hour: the crank goes through, the tip does not, and
+    // it leaves the last tipped crank alone
+    env.warp(3_599);
+    let ix = env.crank_ix(&vault, &cranker, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<VaultCranked>(&meta)[0].tip, 0);
+    assert_eq!(env.vault(&vault).last_cranked_at, tipped_at);
+
+    // The hour runs from the last accrual, which that crank did make
+    env.warp(3_599);
+    let ix = env.crank_ix(&vault, &cranker, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<VaultCranked>(&meta)[0].tip, 0);
+    env.warp(3_600);
+    let ix = env.crank_ix(&vault, &cranker, true);
+    env.ok(&[ix]);
+    assert_eq!(env.lamports(&cranker), before + 2 * TIP);
+
+    // Paused vaults emit nothing, so cranking them earns nothing
+    let ix = env.pause_vault_ix(&vault);
+    env.ok(&[ix]);
+    env.warp(DAY);
+    let ix = env.crank_ix(&vault, &cranker, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<VaultCranked>(&meta)[0].tip, 0);
+    let ix = env.unpause_vault_ix(&vault);
+    env.ok(&[ix]);
+
+    // One last tip for the crank that carries it past its end, then none
+    env.warp(60 * DAY);
+    let ix = env.crank_ix(&vault, &cranker, true);
+    env.ok(&[ix]);
+    env.warp(DAY);
+    let ix = env.crank_ix(&vault, &cranker, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<VaultCranked>(&meta)[0].tip, 0);
+    assert_eq!(env.lamports(&cranker), before + 3 * TIP);
+    assert_eq!(env.crank_pool().total_tipped, 3 * TIP);
+    assert_eq!(env.vault(&vault).crank_tips_paid, 3);
+}
+
+#[test]
+fn crank_tips_need_stakers_and_stop_at_the_per_vault_cap() {
+    let mut env = tipping_env();
+    let ix = env.set_crank_tip_ix(TIP, 3_600, 2);
+    env.ok(&[ix]);
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let cranker = env.wallet();
+    let before = env.lamports(&cranker);
+
+    // Emissions with nobody staked are only parked; no tip for that
+    env.warp(DAY);
+    let ix = env.crank_ix(&vault, &cranker, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<VaultCranked>(&meta)[0].tip, 0);
+
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    let mut tips = Vec::new();
+    for _ in 0..3 {
+        env.warp(DAY);
+        let ix = env.crank_ix(&vault, &cranker, true);
+        let meta = env.ok(&[ix]);
+        tips.push(events::<VaultCranked>(&meta)[0].tip);
+    }
+    assert_eq!(tips, vec![TIP, TIP, 0]);
+    assert_eq!(env.lamports(&cranker), before + 2 * TIP);
+    let v = env.vault(&vault);
+    assert_eq!(v.crank_tips_paid, 2);
+    assert_eq!(v.last_cranked_at, env.now() - DAY);
+}
+
+#[test]
+fn crank_many_tips_each_vault_and_rejects_non_vaults() {
+    let mut env = tipping_env();
+    let p1 = env.vault_params();
+    let v1 = env.create_vault(&p1);
+    let p2 = env.vault_params();
+    let v2 = env.create_vault(&p2);
+    let alice = env.wallet();
+    env.stake(&v1, &alice, 1_000);
+    env.stake(&v2, &alice, 1_000);
+    let cranker = env.wallet();
+    let before = env.lamports(&cranker);
+    env.warp(DAY);
+
+    // A repeated vault is cranked twice but tipped once
+    let ix = env.crank_many_ix(&[v1, v2, v1], &cranker, true);
+    let meta = env.ok(&[ix]);
+    let tips: Vec<u64> = events::<VaultCranked>(&meta)
+        .iter()
+        .map(|e| e.tip)
+        .collect();
+    assert_eq!(tips, vec![TIP, TIP, 0]);
+    assert_eq!(env.lamports(&cranker), before + 2 * TIP);
+    assert_eq!(env.vault(&v2).streams[0].emitted, 1_000_000 / 30);
+
+    let ix = env.crank_many_ix(&[v1, pda::state().0], &cranker, true);
+    assert_err(
+        env.send(&[ix]),
+        u32::from(ErrorCode::AccountDiscriminatorMismatch),
+    );
+    let ix = env.crank_many_ix(&[], &cranker, true);
+    assert_vault_err(env.send(&[ix]), VaultError::ZeroAmount);
+}
+
+#[test]
+fn admin_withdraws_unspent_tips_and_an_empty_pool_pays_nothing() {
+    let mut env = tipping_env();
+    let stranger = env.wallet();
+    let mut ix = env.withdraw_crank_pool_ix(POOL);
+    ix.accounts[0].pubkey = stranger;
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+    let ix = env.withdraw_crank_pool_ix(POOL + 1);
+    assert_vault_err(env.send(&[ix]), VaultError::InsufficientCrankPool);
+
+    let treasury_before = env.lamports(&DEV_TREASURY);
+    let ix = env.withdraw_crank_pool_ix(POOL - TIP / 2);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<CrankPoolChanged>(&meta)[0];
+    assert_eq!((ev.withdrawn, ev.available), (POOL - TIP / 2, TIP / 2));
+    assert_eq!(
+        env.lamports(&DEV_TREASURY),
+        treasury_before + POOL - TIP / 2
+    );
+
+    // What is left is paid out, never the pool's rent
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    let cranker = env.wallet();
+    let before = env.lamports(&cranker);
+    env.warp(DAY);
+    let ix = env.crank_ix(&vault, &cranker, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<VaultCranked>(&meta)[0].tip, TIP / 2);
+    env.warp(DAY);
+    let ix = env.crank_ix(&vault, &cranker, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<VaultCranked>(&meta)[0].tip, 0);
+    assert_eq!(env.lamports(&cranker), before + TIP / 2);
+}
@@ -0,0 +1,247 @@
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use driplet_vaults::{VaultError, VaultRescheduled, DEV_TREASURY};
+use driplet_vaults_integration::*;
+use driplet_vaults_sdk::pda;
+
+/// Prepares a valid vault, lets `tamper` swap one of its token accounts, and
+/// expects `create_vault` to reject it.
+fn expect_bad_mint(tamper: impl FnOnce(&mut Env, &VaultParams, &mut VaultAccounts)) {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let mut a = env.prepare_vault(&p);
+    tamper(&mut env, &p, &mut a);
+    let ix = env.create_vault_ix(&p, &a);
+    assert_vault_err(env.send(&[ix]), VaultError::BadMint);
+}
+
+#[test]
+fn creator_ata_in_wrong_mint() {
+    expect_bad_mint(|env, p, a| {
+        a.creator_ata = env.fund(&p.creator, &p.mint, gross(p.reward_net));
+    });
+}
+
+#[test]
+fn creator_ata_of_someone_else() {
+    expect_bad_mint(|env, p, a| {
+        let other = env.wallet();
+        a.creator_ata = env.fund(&other, &p.reward_mint, gross(p.reward_net));
+    });
+}
+
+#[test]
+fn reward_ata_not_owned_by_reward_pda() {
+    expect_bad_mint(|env, p, a| {
+        let escrow_authority = pda::vault_escrow(&a.vault).0;
+        a.reward_ata = env.create_ata(&escrow_authority, &p.reward_mint);
+    });
+}
+
+#[test]
+fn reward_ata_in_wrong_mint() {
+    expect_bad_mint(|env, p, a| {
+        let reward_authority = pda::vault_reward(&a.vault).0;
+        a.reward_ata = env.create_ata(&reward_authority, &p.mint);
+    });
+}
+
+#[test]
+fn escrow_ata_in_wrong_mint() {
+    expect_bad_mint(|env, p, a| {
+        let escrow_authority = pda::vault_escrow(&a.vault).0;
+        a.escrow_ata = env.create_ata(&escrow_authority, &p.reward_mint);
+    });
+}
+
+#[test]
+fn escrow_ata_not_owned_by_escrow_pda() {
+    expect_bad_mint(|env, p, a| {
+        let reward_authority = pda::vault_reward(&a.vault).0;
+        a.escrow_ata = env.create_ata(&reward_authority, &p.mint);
+    });
+}
+
+#[test]
+fn dev_ata_not_the_treasury() {
+    expect_bad_mint(|env, p, a| {
+        let other = env.wallet();
+        a.dev_ata = env.create_ata(&other, &p.reward_mint);
+    });
+}
+
+#[test]
+fn dev_ata_in_wrong_mint() {
+    expect_bad_mint(|env, p, a| {
+        a.dev_ata = env.create_ata(&DEV_TREASURY, &p.mint);
+    });
+}
+
+#[test]
+fn mint_must_match_token_program() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.mint = env.create_mint(TOKEN_2022);
+    let a = env.prepare_vault(&p);
+    let mut ix = env.create_vault_ix(&p, &a);
+    // token_program sits after the four pre-created token accounts
+    ix.accounts[12].pubkey = TOKEN;
+    assert_err(
+        env.send(&[ix]),
+        u32::from(ErrorCode::ConstraintMintTokenProgram),
+    );
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    let mut env = Env::new();
+
+    let mut p = env.vault_params();
+    p.reward_net = 0;
+    let a = env.prepare_vault(&p);
+    let ix = env.create_vault_ix(&p, &a);
+    assert_vault_err(env.send(&[ix]), VaultError::ZeroAmount);
+
+    let mut p = env.vault_params();
+    p.policy.early_exit_penalty_bps = 5_001;
+    let a = env.prepare_vault(&p);
+    let ix = env.create_vault_ix(&p, &a);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidPolicy);
+
+    let mut p = env.vault_params();
+    p.policy.min_stake_secs = p.duration_secs + 1;
+    let a = env.prepare_vault(&p);
+    let ix = env.create_vault_ix(&p, &a);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidPolicy);
+
+    let mut p = env.vault_params();
+    p.policy.min_stake_secs = -1;
+    let a = env.prepare_vault(&p);
+    let ix = env.create_vault_ix(&p, &a);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidPolicy);
+}
+
+#[test]
+fn vault_ids_allow_several_vaults_per_mint() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    let first = env.create_vault(&p);
+    p.vault_id = 1;
+    let second = env.create_vault(&p);
+    assert_ne!(first, second);
+    assert_eq!(env.vault(&second).id, 1);
+
+    // Same id twice is the same PDA
+    let a = env.prepare_vault(&p);
+    let ix = env.create_vault_ix(&p, &a);
+    assert!(env.send(&[ix]).is_err());
+}
+
+#[test]
+fn transfer_fee_reward_mint_books_what_arrived() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.reward_mint = env.create_fee_mint(100);
+    let vault = env.create_vault(&p);
+
+    // 1% is withheld on the way in (of 1_030_000) and on the fee skim (of 30_000)
+    let s = &env.vault(&vault).streams[0];
+    assert_eq!(s.reward_net, 1_030_000 - 10_300 - 30_000);
+    assert_eq!(s.reward_fee, 30_000);
+    assert_eq!(
+        env.balance_of(&pda::vault_reward(&vault).0, &p.reward_mint),
+        s.reward_net
+    );
+    assert_eq!(env.balance_of(&DEV_TREASURY, &p.reward_mint), 29_700);
+}
+
+/// A fresh vault asking to start at `start_time`, and its create_vault.
+fn create_at_ix(env: &mut Env, start_time: Option<i64>) -> (Pubkey, Instruction) {
+    let mut p = env.vault_params();
+    p.start_time = start_time;
+    let a = env.prepare_vault(&p);
+    (a.vault, env.create_vault_ix(&p, &a))
+}
+
+#[test]
+fn start_time_follows_global_bounds() {
+    let mut env = Env::new();
+    let now = env.now();
+
+    // Defaults: up to a minute of backdating is absorbed, a year of horizon
+    let (_, ix) = create_at_ix(&mut env, Some(now - 61));
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidStartTime);
+    let (vault, ix) = create_at_ix(&mut env, Some(now - 60));
+    env.ok(&[ix]);
+    assert_eq!(env.vault(&vault).start_time, now);
+    let (_, ix) = create_at_ix(&mut env, Some(now + YEAR + 1));
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidStartTime);
+    let (_, ix) = create_at_ix(&mut env, Some(now + YEAR));
+    env.ok(&[ix]);
+
+    let ix = env.set_start_bounds_ix(31 * DAY, 30 * DAY, 0);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidStartTime);
+    let ix = env.set_start_bounds_ix(DAY, 30 * DAY, 0);
+    env.ok(&[ix]);
+    let state = env.global_state();
+    assert_eq!(
+        (
+            state.min_start_lead_secs,
+            state.max_start_horizon_secs,
+            state.max_start_backdate_secs
+        ),
+        (DAY, 30 * DAY, 0)
+    );
+
+    for start in [now - 1, now, now + DAY - 1, now + 30 * DAY + 1] {
+        let (_, ix) = create_at_ix(&mut env, Some(start));
+        assert_vault_err(env.send(&[ix]), VaultError::InvalidStartTime);
+    }
+    // Without a start, the vault waits out the lead time
+    let (vault, ix) = create_at_ix(&mut env, None);
+    env.ok(&[ix]);
+    assert_eq!(env.vault(&vault).start_time, now + DAY);
+}
+
+#[test]
+fn reschedule_start_moves_unstarted_vault_and_streams() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.start_time = Some(env.now() + DAY);
+    let vault = env.create_vault(&p);
+    let extra_mint = env.create_mint(TOKEN);
+    env.add_reward_stream(&vault, &p.creator, &extra_mint, 1_000);
+    let before = env.vault(&vault);
+
+    let ix = env.reschedule_start_ix(&vault, env.now() + 3 * DAY);
+    let meta = env.ok(&[ix]);
+    let v = env.vault(&vault);
+    assert_eq!(
+        (v.start_time, v.end_time),
+        (before.start_time + 2 * DAY, before.end_time + 2 * DAY)
+    );
+    for (s, old) in v.streams.iter().zip(before.streams.iter()) {
+        assert_eq!(
+            (s.start_time, s.end_time, s.last_update_time),
+            (
+                old.start_time + 2 * DAY,
+                old.end_time + 2 * DAY,
+                old.last_update_time + 2 * DAY
+            )
+        );
+    }
+    let ev = &events::<VaultRescheduled>(&meta)[0];
+    assert_eq!((ev.start_time, ev.end_time), (v.start_time, v.end_time));
+
+    // Earlier works too, within the same bounds as create_vault
+    let ix = env.reschedule_start_ix(&vault, env.now() + YEAR + 1);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidStartTime);
+    let ix = env.reschedule_start_ix(&vault, env.now() + 3_600);
+    env.ok(&[ix]);
+    assert_eq!(env.vault(&vault).start_time, env.now() + 3_600);
+
+    env.warp(3_600);
+    let ix = env.reschedule_start_ix(&vault, env.now() + DAY);
+    assert_vault_err(env.send(&[ix]), VaultError::AlreadyStarted);
+}
@@ -0,0 +1,339 @@
+use driplet_vaults::{
+    BacklogPolicy, EmissionSchedule, RewardsToppedUp, ScheduleStep, UnallocatedReclaimed,
+    VaultError, RATE_SCALE,
+};
+use driplet_vaults_integration::*;
+
+#[test]
+fn empty_periods_park_emissions_in_unallocated() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+
+    env.warp(10 * DAY);
+    let preview = env.preview_vault_state(&vault, env.now());
+    assert_eq!(preview.streams[0].emitted, 333_333);
+    assert_eq!(preview.streams[0].unallocated, 333_333);
+    assert_eq!(preview.streams[0].acc_reward_per_token, 0);
+    assert_eq!(preview.streams[0].rate_per_weight_fp, 0);
+
+    // The first deposit accrues with nobody staked, so the backlog is parked
+    let bob = env.wallet();
+    env.stake(&vault, &bob, 500);
+    let s = &env.vault(&vault).streams[0];
+    assert_eq!((s.emitted, s.unallocated), (333_333, 333_333));
+
+    // ...and handed to whoever is staked at the next accrual
+    env.warp(20 * DAY);
+    env.claim(&vault, &bob);
+    assert_eq!(env.balance_of(&bob, &p.reward_mint), 1_000_000);
+    let s = &env.vault(&vault).streams[0];
+    assert_eq!(
+        (s.emitted, s.unallocated, s.paid),
+        (1_000_000, 0, 1_000_000)
+    );
+}
+
+#[test]
+fn emissions_during_gaps_between_stakers_are_not_lost() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    let bob = env.wallet();
+
+    env.stake(&vault, &alice, 1_000);
+    env.warp(10 * DAY);
+    let ix = env.withdraw_ix(&vault, &alice, 1_000, true);
+    env.ok(&[ix]);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 333_333);
+
+    // Vault sits empty for ten days
+    env.warp(10 * DAY);
+    env.stake(&vault, &bob, 1_000);
+    assert_eq!(env.vault(&vault).streams[0].unallocated, 333_333);
+
+    env.warp(10 * DAY);
+    env.claim(&vault, &bob);
+    assert_eq!(env.balance_of(&bob, &p.reward_mint), 1_000_000 - 333_333);
+}
+
+#[test]
+fn backlog_flushes_to_staker_arriving_at_end_time() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let end = env.vault(&vault).end_time;
+
+    // Deposits are still accepted at exactly end_time; everything emitted
+    // so far is unallocated
+    env.warp_to(end);
+    let bob = env.wallet();
+    env.stake(&vault, &bob, 700);
+    let s = &env.vault(&vault).streams[0];
+    assert_eq!(
+        (s.emitted, s.unallocated, s.last_update_time),
+        (1_000_000, 1_000_000, end)
+    );
+
+    // The clock never moves past end_time for the stream, but the backlog
+    // still goes out once someone is staked
+    env.warp(DAY);
+    let preview = env.preview_pending(&vault, &bob);
+    env.claim(&vault, &bob);
+    let paid = env.balance_of(&bob, &p.reward_mint);
+    assert_eq!(preview.owed, vec![paid]);
+    assert!(1_000_000 - paid <= 1, "lost more than rounding: {paid}");
+    assert_eq!(env.vault(&vault).streams[0].unallocated, 0);
+
+    let r = env.reconcile(&vault, 0);
+    assert_eq!((r.unemitted, r.total_owed, r.shortfall), (0, 0, 0));
+    assert_eq!(r.unsettled, 1_000_000 - paid);
+}
+
+#[test]
+fn awkward_amounts_emit_exactly_reward_net() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.reward_net = 1_000_003;
+    p.duration_secs = 7 * DAY + 13;
+    let vault = env.create_vault(&p);
+    let stakers = [(env.wallet(), 3), (env.wallet(), 7), (env.wallet(), 11)];
+    for (user, amount) in stakers {
+        env.stake(&vault, &user, amount);
+    }
+
+    // Claims at odd times leave floor() dust at every accrual
+    for (i, step) in [12_345, 99_991, 7, 250_001, 31_337].into_iter().enumerate() {
+        env.warp(step);
+        let (user, _) = stakers[i % stakers.len()];
+        env.claim(&vault, &user);
+
+        let s = env.vault(&vault).streams[0].clone();
+        let expected = driplet_vaults_sdk::math::emitted_at(&s, env.now()).unwrap();
+        assert_eq!(s.emitted, expected);
+        assert!(s.paid + s.total_owed <= s.emitted);
+    }
+
+    env.warp(7 * DAY);
+    for (user, amount) in stakers {
+        let ix = env.withdraw_ix(&vault, &user, amount, true);
+        env.ok(&[ix]);
+    }
+
+    let s = env.vault(&vault).streams[0].clone();
+    assert_eq!(s.emitted, 1_000_003);
+    let paid: u64 = stakers
+        .iter()
+        .map(|(u, _)| env.balance_of(u, &p.reward_mint))
+        .sum();
+    assert_eq!(paid, s.paid);
+    let dust = 1_000_003 - paid;
+    assert!(dust <= 16, "too much rounding dust: {dust}");
+
+    // The dust is visible as emitted-but-unsettled and still in the account
+    let r = env.reconcile(&vault, 0);
+    assert_eq!((r.unemitted, r.total_owed, r.unsettled), (0, 0, dust));
+    assert_eq!((r.balance, r.shortfall, r.surplus), (dust, 0, 0));
+}
+
+#[test]
+fn cliff_linear_emits_nothing_before_the_cliff() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.schedule = EmissionSchedule::CliffLinear {
+        cliff_secs: 10 * DAY,
+    };
+    let vault = env.create_vault(&p);
+    let start = env.now();
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+
+    let at = |env: &mut Env, t: i64| env.preview_vault_state(&vault, start + t).streams[0].clone();
+    assert_eq!(at(&mut env, 10 * DAY).emitted, 0);
+    assert_eq!(at(&mut env, 10 * DAY - 1).rate_fp, 0);
+    assert_eq!(at(&mut env, 20 * DAY).emitted, 500_000);
+    assert_eq!(at(&mut env, 30 * DAY).emitted, 1_000_000);
+
+    env.warp(10 * DAY);
+    let ix = env.claim_ix(&vault, &alice);
+    env.ok(&[ix]);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 0);
+    env.warp(5 * DAY);
+    env.claim(&vault, &alice);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 250_000);
+}
+
+#[test]
+fn decay_and_stepped_schedules_follow_their_curves() {
+    let mut env = Env::new();
+
+    // Two halvings over 30 days: 2/3 of the rewards in the first half
+    let mut p = env.vault_params();
+    p.schedule = EmissionSchedule::ExponentialDecay {
+        half_life_secs: 15 * DAY,
+    };
+    let decay = env.create_vault(&p);
+    let start = env.now();
+    let s = env.preview_vault_state(&decay, start + 15 * DAY).streams[0].clone();
+    assert_eq!(s.emitted, 666_666);
+
+    // Weight 3 for ten days, then weight 1
+    let mut p = env.vault_params();
+    p.schedule = EmissionSchedule::Stepped {
+        steps: vec![
+            ScheduleStep {
+                offset_secs: 0,
+                weight: 3,
+            },
+            ScheduleStep {
+                offset_secs: 10 * DAY,
+                weight: 1,
+            },
+        ],
+    };
+    let stepped = env.create_vault(&p);
+    let s = env.preview_vault_state(&stepped, start + 10 * DAY).streams[0].clone();
+    assert_eq!(s.emitted, 600_000);
+    assert_eq!(s.rate_fp, 400_000 * RATE_SCALE / (20 * DAY) as u128);
+
+    let mut p = env.vault_params();
+    p.schedule = EmissionSchedule::Stepped {
+        steps: vec![ScheduleStep {
+            offset_secs: DAY,
+            weight: 1,
+        }],
+    };
+    let a = env.prepare_vault(&p);
+    let ix = env.create_vault_ix(&p, &a);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidSchedule);
+}
+
+#[test]
+fn top_up_respreads_over_time_left() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    let partner = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+
+    env.warp(15 * DAY);
+    env.fund(&partner, &p.reward_mint, gross(300_000));
+    let ix = env.top_up_ix(&vault, &partner, 0, 300_000);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<RewardsToppedUp>(&meta)[0];
+    assert_eq!(
+        (ev.amount_net, ev.fee, ev.reward_net),
+        (300_000, 9_000, 1_300_000)
+    );
+    assert_eq!(ev.rate_fp, 800_000 * RATE_SCALE / (15 * DAY) as u128);
+
+    let s = &env.vault(&vault).streams[0];
+    assert_eq!(
+        (s.emitted, s.base_emitted, s.reward_net),
+        (500_000, 500_000, 1_300_000)
+    );
+
+    env.warp(15 * DAY);
+    env.claim(&vault, &alice);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 1_300_000);
+
+    env.fund(&partner, &p.reward_mint, gross(1_000));
+    let ix = env.top_up_ix(&vault, &partner, 0, 1_000);
+    assert_vault_err(env.send(&[ix]), VaultError::StreamEnded);
+    let ix = env.top_up_ix(&vault, &partner, 0, 0);
+    assert_vault_err(env.send(&[ix]), VaultError::ZeroAmount);
+}
+
+#[test]
+fn top_up_is_refused_once_only_zero_weight_steps_remain() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    // Everything in the first twenty days, nothing after
+    p.schedule = EmissionSchedule::Stepped {
+        steps: vec![
+            ScheduleStep {
+                offset_secs: 0,
+                weight: 1,
+            },
+            ScheduleStep {
+                offset_secs: 20 * DAY,
+                weight: 0,
+            },
+        ],
+    };
+    let vault = env.create_vault(&p);
+    let partner = env.wallet();
+    env.fund(&partner, &p.reward_mint, gross(2_000));
+
+    env.warp(20 * DAY - 1);
+    let ix = env.top_up_ix(&vault, &partner, 0, 1_000);
+    env.ok(&[ix]);
+
+    env.warp(1);
+    let ix = env.top_up_ix(&vault, &partner, 0, 1_000);
+    assert_vault_err(env.send(&[ix]), VaultError::NoEmissionsLeft);
+    assert_eq!(env.vault(&vault).streams[0].reward_net, 1_001_000);
+}
+
+#[test]
+fn creator_reclaims_backlog_once_an_empty_term_ends() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let partner = env.wallet();
+    let extra_mint = env.create_mint(TOKEN);
+    env.add_reward_stream(&vault, &partner, &extra_mint, 1_000);
+    let end = env.vault(&vault).end_time;
+
+    // A deposit at end_time could still take the backlog
+    env.warp_to(end);
+    let ix = env.reclaim_unallocated_ix(&vault, 0);
+    assert_vault_err(env.send(&[ix]), VaultError::NotEnded);
+
+    env.warp(1);
+    let ix = env.reclaim_unallocated_ix(&vault, 0);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<UnallocatedReclaimed>(&meta)[0];
+    assert_eq!((ev.funder, ev.amount), (p.creator, 1_000_000));
+    assert_eq!(env.balance_of(&p.creator, &p.reward_mint), 1_000_000);
+    let ix = env.reclaim_unallocated_ix(&vault, 0);
+    assert_vault_err(env.send(&[ix]), VaultError::ZeroAmount);
+
+    // A partner stream goes back to the partner
+    let ix = env.reclaim_unallocated_ix(&vault, 1);
+    env.ok(&[ix]);
+    assert_eq!(env.balance_of(&partner, &extra_mint), 1_000);
+
+    let s = &env.vault(&vault).streams[0];
+    assert_eq!((s.unallocated, s.reclaimed), (0, 1_000_000));
+    let r = env.reconcile(&vault, 0);
+    assert_eq!((r.unsettled, r.shortfall, r.surplus), (0, 0, 0));
+}
+
+#[test]
+fn return_to_funder_keeps_backlog_from_late_stakers() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.policy.backlog = BacklogPolicy::ReturnToFunder;
+    let vault = env.create_vault(&p);
+
+    env.warp(10 * DAY);
+    let bob = env.wallet();
+    env.stake(&vault, &bob, 500);
+    env.warp(10 * DAY);
+
+    // Mid-term reclaim of the first ten days; bob only earns his own time
+    let ix = env.reclaim_unallocated_ix(&vault, 0);
+    env.ok(&[ix]);
+    assert_eq!(env.balance_of(&p.creator, &p.reward_mint), 333_333);
+    env.warp(10 * DAY);
+    env.claim(&vault, &bob);
+    assert_eq!(env.balance_of(&bob, &p.reward_mint), 1_000_000 - 333_333);
+    let s = &env.vault(&vault).streams[0];
+    assert_eq!(
+        (s.unallocated, s.paid, s.reclaimed),
+        (0, 1_000_000 - 333_333, 333_333)
+    );
+}
@@ -0,0 +1,147 @@
+//! Random deposit/withdraw/claim/warp sequences against the real program,
+//! checking the accounting invariants on actual token balances after every
+//! step. `sdk/tests/accounting.rs` runs the same invariants through the
+//! math alone, with far more cases.
+
+use anchor_lang::prelude::Pubkey;
+use driplet_vaults_integration::*;
+use driplet_vaults_sdk::pda;
+use proptest::prelude::*;
+
+const USERS: usize = 3;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Deposit {
+        user: usize,
+        amount: u64,
+        lock_days: i64,
+    },
+    Withdraw {
+        user: usize,
+        bps: u64,
+    },
+    Claim {
+        user: usize,
+    },
+    Warp {
+        secs: i64,
+    },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (0..USERS, 1u64..1_000_000, prop_oneof![Just(0i64), 1i64..30])
+            .prop_map(|(user, amount, lock_days)| Op::Deposit { user, amount, lock_days }),
+        3 => (0..USERS, 1u64..=10_000).prop_map(|(user, bps)| Op::Withdraw { user, bps }),
+        3 => (0..USERS).prop_map(|user| Op::Claim { user }),
+        4 => (0i64..5 * DAY).prop_map(|secs| Op::Warp { secs }),
+    ]
+}
+
+fn has_stake(env: &Env, vault: &Pubkey, owner: &Pubkey) -> bool {
+    !env.is_closed(&pda::user(vault, owner).0)
+}
+
+fn check(env: &mut Env, vault: &Pubkey, users: &[Pubkey]) -> Result<(), TestCaseError> {
+    let v = env.vault(vault);
+    let escrow = env.balance_of(&pda::vault_escrow(vault).0, &v.mint);
+    prop_assert_eq!(escrow, v.total_staked + v.penalty_pool);
+
+    let state = env.global_state();
+    let now = env.now();
+    let stakes: Vec<_> = users
+        .iter()
+        .filter(|u| has_stake(env, vault, u))
+        .map(|u| env.user_stake(vault, u))
+        .collect();
+    let sim = driplet_vaults_sdk::simulate(&v, &state, now).unwrap();
+    for (idx, s) in sim.streams.iter().enumerate() {
+        prop_assert!(s.emitted <= s.reward_net);
+        let owed: u64 = stakes
+            .iter()
+            .map(|u| driplet_vaults_sdk::pending(&v, &state, u, now).unwrap()[idx])
+            .sum();
+        prop_assert!(
+            s.paid + owed <= s.emitted,
+            "paid {} + owed {} > emitted {}",
+            s.paid,
+            owed,
+            s.emitted
+        );
+
+        let balance = env.balance_of(&pda::vault_reward(vault).0, &s.mint);
+        prop_assert!(balance >= owed + (s.reward_net - s.emitted));
+        prop_assert_eq!(balance, s.reward_net - s.paid);
+    }
+    Ok(())
+}
+
+fn run(penalty_bps: u16, ops: Vec<Op>) -> Result<(), TestCaseError> {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.policy.early_exit_penalty_bps = penalty_bps;
+    p.policy.min_stake_secs = 7 * DAY;
+    let vault = env.create_vault(&p);
+    let users: Vec<Pubkey> = (0..USERS).map(|_| env.wallet()).collect();
+    for u in &users {
+        env.fund(u, &p.mint, 1_000_000_000);
+    }
+
+    for op in ops {
+        let v = env.vault(&vault);
+        match op {
+            Op::Deposit {
+                user,
+                amount,
+                lock_days,
+            } => {
+                let ix = env.deposit_ix(
+                    &vault,
+                    &users[user],
+                    amount,
+                    (lock_days > 0).then_some(lock_days * DAY),
+                );
+                let fits = lock_days == 0 || env.now() + lock_days * DAY <= v.end_time;
+                if env.now() <= v.end_time && fits {
+                    env.ok(&[ix]);
+                } else {
+                    prop_assert!(env.send(&[ix]).is_err());
+                }
+            }
+            Op::Withdraw { user, bps } => {
+                if !has_stake(&env, &vault, &users[user]) {
+                    continue;
+                }
+                let stake = env.user_stake(&vault, &users[user]);
+                // The handler settles first, so size off the settled principal
+                let amount = env.preview_pending(&vault, &users[user]).amount * bps / 10_000;
+                if amount == 0 || env.now() < stake.lock_until {
+                    continue;
+                }
+                let ix = env.withdraw_ix(&vault, &users[user], amount, false);
+                env.ok(&[ix]);
+            }
+            Op::Claim { user } => {
+                if has_stake(&env, &vault, &users[user]) {
+                    env.claim(&vault, &users[user]);
+                }
+            }
+            Op::Warp { secs } => env.warp(secs),
+        }
+        check(&mut env, &vault, &users)?;
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn accounting_invariants_hold_on_chain(
+        penalty_bps in prop_oneof![Just(0u16), 1u16..=5_000],
+        ops in prop::collection::vec(op(), 1..40),
+    ) {
+        run(penalty_bps, ops)?;
+    }
+}
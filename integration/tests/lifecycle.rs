@@ -0,0 +1,398 @@
+use driplet_vaults::{
+    Claimed, Deposited, GlobalStateChanged, PrincipalPolicy, PrincipalReclaimed,
+    PrincipalReleased, StakeClosed, VaultCancelled, VaultClosed, VaultCreated, VaultError,
+    Withdrawn, DEV_TREASURY,
+};
+use driplet_vaults_integration::*;
+use driplet_vaults_sdk::pda;
+
+#[test]
+fn create_vault_books_fees_and_stream() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let a = env.prepare_vault(&p);
+    let treasury_before = env.lamports(&DEV_TREASURY);
+
+    let ix = env.create_vault_ix(&p, &a);
+    let meta = env.ok(&[ix]);
+
+    let v = env.vault(&a.vault);
+    assert_eq!(v.creator, p.creator);
+    assert_eq!(v.mint, p.mint);
+    assert_eq!(v.start_time, env.now());
+    assert_eq!(v.end_time, env.now() + p.duration_secs);
+    assert_eq!(v.streams.len(), 1);
+    assert_eq!(v.streams[0].reward_net, 1_000_000);
+    assert_eq!(v.streams[0].reward_fee, 30_000);
+    assert_eq!(env.balance(&a.reward_ata), 1_000_000);
+    assert_eq!(env.balance(&a.dev_ata), 30_000);
+    assert_eq!(env.balance(&a.creator_ata), 0);
+    assert_eq!(
+        env.lamports(&DEV_TREASURY),
+        treasury_before + CREATION_FEE_LAMPORTS
+    );
+
+    let created = events::<VaultCreated>(&meta);
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0].reward_net, 1_000_000);
+    assert_eq!(created[0].fee, 30_000);
+}
+
+#[test]
+fn default_start_is_five_minutes_out() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.start_time = None;
+    let vault = env.create_vault(&p);
+    assert_eq!(env.vault(&vault).start_time, env.now() + 300);
+
+    // Deposits wait for the start
+    let mint = p.mint;
+    let user = env.wallet();
+    env.fund(&user, &mint, 100);
+    let ix = env.deposit_ix(&vault, &user, 100, None);
+    assert_vault_err(env.send(std::slice::from_ref(&ix)), VaultError::VaultEnded);
+    env.warp(300);
+    env.ok(&[ix]);
+}
+
+#[test]
+fn stake_claim_withdraw_round_trip() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+
+    env.fund(&alice, &p.mint, 1_000);
+    let ix = env.deposit_ix(&vault, &alice, 1_000, None);
+    let meta = env.ok(&[ix]);
+    let dep = &events::<Deposited>(&meta)[0];
+    assert_eq!(
+        (dep.amount, dep.user_amount, dep.total_staked),
+        (1_000, 1_000, 1_000)
+    );
+    assert_eq!(env.balance_of(&pda::vault_escrow(&vault).0, &p.mint), 1_000);
+
+    env.warp(15 * DAY);
+    let meta = env.claim(&vault, &alice);
+    assert_eq!(events::<Claimed>(&meta)[0].paid, vec![500_000]);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 500_000);
+
+    // Nothing new accrues between two claims in the same second
+    env.claim(&vault, &alice);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 500_000);
+
+    env.warp(30 * DAY);
+    let ix = env.withdraw_ix(&vault, &alice, 1_000, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<Claimed>(&meta)[0].paid, vec![500_000]);
+    let w = &events::<Withdrawn>(&meta)[0];
+    assert_eq!((w.amount, w.penalty, w.total_staked), (1_000, 0, 0));
+
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 1_000_000);
+    assert_eq!(env.balance_of(&alice, &p.mint), 1_000);
+    assert_eq!(env.balance_of(&pda::vault_escrow(&vault).0, &p.mint), 0);
+    assert_eq!(
+        env.balance_of(&pda::vault_reward(&vault).0, &p.reward_mint),
+        0
+    );
+
+    let s = &env.vault(&vault).streams[0];
+    assert_eq!((s.emitted, s.paid, s.total_owed), (1_000_000, 1_000_000, 0));
+}
+
+#[test]
+fn withdraw_rejects_zero_and_overdraw() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 500);
+
+    let ix = env.withdraw_ix(&vault, &alice, 0, false);
+    assert_vault_err(env.send(&[ix]), VaultError::ZeroAmount);
+    let ix = env.withdraw_ix(&vault, &alice, 501, false);
+    assert_vault_err(env.send(&[ix]), VaultError::InsufficientStake);
+
+    // Partial withdraw without claiming keeps rewards owed
+    env.warp(DAY);
+    let ix = env.withdraw_ix(&vault, &alice, 200, false);
+    env.ok(&[ix]);
+    assert_eq!(env.balance_of(&alice, &p.mint), 200);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 0);
+    let u = env.user_stake(&vault, &alice);
+    assert_eq!((u.amount, u.weight), (300, 300));
+    assert_eq!(u.owed[0], 1_000_000 / 30);
+}
+
+#[test]
+fn deposits_close_after_end_time() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.fund(&alice, &p.mint, 100);
+
+    env.warp(30 * DAY + 1);
+    let ix = env.deposit_ix(&vault, &alice, 100, None);
+    assert_vault_err(env.send(&[ix]), VaultError::VaultEnded);
+    let ix = env.deposit_ix(&vault, &alice, 0, None);
+    assert_vault_err(env.send(&[ix]), VaultError::ZeroAmount);
+}
+
+#[test]
+fn hold_for_owners_release_close_reclaim() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let end = env.vault(&vault).end_time;
+    let alice = env.wallet();
+    env.fund(&alice, &p.mint, 1_000);
+    let ix = env.deposit_ix(&vault, &alice, 1_000, Some(10 * DAY));
+    env.ok(&[ix]);
+
+    env.warp_to(end + GRACE_SECS - 1);
+    let ix = env.release_position_ix(&vault, &alice);
+    assert_vault_err(env.send(&[ix]), VaultError::GraceNotElapsed);
+
+    // Alice never came back: her position blocks the close until released
+    env.warp(1);
+    let ix = env.close_vault_ix(&vault);
+    assert_vault_err(env.send(&[ix]), VaultError::StillStaked);
+
+    let ix = env.release_position_ix(&vault, &alice);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<Claimed>(&meta)[0].paid, vec![1_000_000]);
+    let released = &events::<PrincipalReleased>(&meta)[0];
+    assert_eq!((released.owner, released.amount), (alice, 1_000));
+    assert_eq!(released.total_staked, 0);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 1_000_000);
+    let unclaimed = pda::unclaimed(&vault, &alice).0;
+    assert_eq!(env.balance_of(&unclaimed, &p.mint), 1_000);
+    let held = env.unclaimed_principal(&vault, &alice);
+    assert_eq!((held.vault, held.mint, held.amount), (vault, p.mint, 1_000));
+
+    let reward_ata = env.ata(&pda::vault_reward(&vault).0, &p.reward_mint);
+    let ix = env.close_vault_ix(&vault);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<VaultClosed>(&meta)[0].principal_swept, 0);
+    assert!(env.is_closed(&vault));
+    assert!(env.is_closed(&reward_ata));
+    assert!(env.is_closed(&env.ata(&pda::vault_escrow(&vault).0, &p.mint)));
+
+    // The principal waits for Alice with the vault gone
+    let bob = env.wallet();
+    let mut ix = env.owner_reclaim_ix(&vault, &alice, &p.mint);
+    ix.accounts[0].pubkey = bob;
+    assert!(env.send(&[ix]).is_err());
+
+    let rent = env.lamports(&unclaimed);
+    let alice_before = env.lamports(&alice);
+    let ix = env.owner_reclaim_ix(&vault, &alice, &p.mint);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<PrincipalReclaimed>(&meta)[0].amount, 1_000);
+    assert_eq!(env.balance_of(&alice, &p.mint), 1_000);
+    assert!(env.is_closed(&unclaimed));
+    assert!(env.is_closed(&env.ata(&unclaimed, &p.mint)));
+    assert!(env.lamports(&alice) > alice_before + rent);
+}
+
+#[test]
+fn release_position_only_for_hold_vaults() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.policy.principal = PrincipalPolicy::SweepToTreasury;
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    env.warp(30 * DAY + GRACE_SECS);
+    let ix = env.release_position_ix(&vault, &alice);
+    assert_vault_err(env.send(&[ix]), VaultError::InvalidPolicy);
+}
+
+#[test]
+fn sweep_policy_sweeps_principal_and_closes() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    // Token-2022 stake mint with a transfer fee, so the escrow carries
+    // withheld fees that have to be harvested before it can close
+    p.mint = env.create_fee_mint(100);
+    p.policy.principal = PrincipalPolicy::SweepToTreasury;
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 10_000);
+    let escrow = env.ata(&pda::vault_escrow(&vault).0, &p.mint);
+    assert_eq!(env.balance(&escrow), 9_900);
+    assert_eq!(env.withheld(&escrow), 100);
+    assert_eq!(env.user_stake(&vault, &alice).amount, 9_900);
+
+    env.create_ata(&DEV_TREASURY, &p.mint);
+    env.warp(30 * DAY + GRACE_SECS);
+    let ix = env.close_vault_ix(&vault);
+    let meta = env.ok(&[ix]);
+    let closed = &events::<VaultClosed>(&meta)[0];
+    assert_eq!(closed.principal_swept, 9_900);
+    assert!(env.is_closed(&vault));
+    assert!(env.is_closed(&escrow));
+    // 1% of the sweep is withheld on the treasury's side
+    assert_eq!(env.balance_of(&DEV_TREASURY, &p.mint), 9_801);
+}
+
+#[test]
+fn cancel_vault_refunds_funders_and_closes_everything() {
+    let mut env = Env::new();
+    let ix = env.update_fees_ix(DEV_TREASURY, CREATION_FEE_LAMPORTS, 300, 5_000, false);
+    env.ok(&[ix]);
+    let mut p = env.vault_params();
+    p.start_time = Some(env.now() + DAY);
+    let vault = env.create_vault(&p);
+    let partner = env.wallet();
+    let extra_mint = env.create_mint(TOKEN);
+    env.add_reward_stream(&vault, &partner, &extra_mint, 1_000);
+    let reward_authority = pda::vault_reward(&vault).0;
+    let treasury_before = env.lamports(&DEV_TREASURY);
+
+    let ix = env.cancel_vault_ix(&vault);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<VaultCancelled>(&meta)[0];
+    assert_eq!(ev.refunded, vec![1_000_000, 1_000]);
+    assert_eq!((ev.escrow_swept, ev.cancel_fee_lamports), (0, 5_000));
+    assert_eq!(env.balance_of(&p.creator, &p.reward_mint), 1_000_000);
+    assert_eq!(env.balance_of(&partner, &extra_mint), 1_000);
+    // Funding fees are kept; only the cancel fee is new
+    assert_eq!(env.balance_of(&DEV_TREASURY, &p.reward_mint), 30_000);
+    assert_eq!(env.lamports(&DEV_TREASURY), treasury_before + 5_000);
+
+    assert!(env.is_closed(&vault));
+    assert!(env.is_closed(&env.ata(&pda::vault_escrow(&vault).0, &p.mint)));
+    assert!(env.is_closed(&env.ata(&reward_authority, &p.reward_mint)));
+    assert!(env.is_closed(&env.ata(&reward_authority, &extra_mint)));
+}
+
+#[test]
+fn cancel_vault_only_while_nothing_staked_or_emitted() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.start_time = Some(env.now() + DAY);
+    let vault = env.create_vault(&p);
+
+    // Only the creator may cancel
+    let mut ix = env.cancel_vault_ix(&vault);
+    let stranger = env.wallet();
+    ix.accounts[0].pubkey = stranger;
+    assert_vault_err(env.send(&[ix]), VaultError::Unauthorized);
+
+    // Once it has emitted, even an empty vault stays
+    env.warp(DAY + 1);
+    let ix = env.cancel_vault_ix(&vault);
+    assert_vault_err(env.send(&[ix]), VaultError::CancelNotAllowed);
+
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    let ix = env.cancel_vault_ix(&vault);
+    assert_vault_err(env.send(&[ix]), VaultError::CancelNotAllowed);
+
+    // Someone has been in and out, but nothing is staked or emitted
+    let mut p = env.vault_params();
+    p.start_time = Some(env.now());
+    let vault = env.create_vault(&p);
+    env.stake(&vault, &alice, 1_000);
+    let ix = env.withdraw_ix(&vault, &alice, 1_000, false);
+    env.ok(&[ix]);
+    let v = env.vault(&vault);
+    assert_eq!((v.total_staked, v.streams[0].emitted), (0, 0));
+    let ix = env.cancel_vault_ix(&vault);
+    env.ok(&[ix]);
+    assert!(env.is_closed(&vault));
+}
+
+#[test]
+fn cancel_vault_refunds_held_token_fees() {
+    let mut env = Env::new();
+    let ix = env.update_fees_ix(DEV_TREASURY, CREATION_FEE_LAMPORTS, 300, 0, true);
+    let meta = env.ok(&[ix]);
+    assert!(events::<GlobalStateChanged>(&meta)[0].cancel_refunds_fee);
+    let mut p = env.vault_params();
+    p.start_time = Some(env.now() + DAY);
+    let vault = env.create_vault(&p);
+
+    // The fee is booked but stays next to the rewards
+    assert_eq!(env.vault(&vault).streams[0].reward_fee, 30_000);
+    let reward_ata = env.ata(&pda::vault_reward(&vault).0, &p.reward_mint);
+    assert_eq!(env.balance(&reward_ata), 1_030_000);
+    assert_eq!(env.balance_of(&DEV_TREASURY, &p.reward_mint), 0);
+
+    let ix = env.cancel_vault_ix(&vault);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<VaultCancelled>(&meta)[0].refunded, vec![1_030_000]);
+    assert_eq!(env.balance_of(&p.creator, &p.reward_mint), 1_030_000);
+    assert_eq!(env.balance_of(&DEV_TREASURY, &p.reward_mint), 0);
+}
+
+#[test]
+fn owner_closes_empty_stake_and_gets_rent_back() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    let user_stake = pda::user(&vault, &alice).0;
+
+    let ix = env.close_user_stake_ix(&vault, &alice);
+    assert_vault_err(env.send(&[ix]), VaultError::StillStaked);
+
+    // Withdrawn but not claimed: the owed rewards keep the record open
+    env.warp(10 * DAY);
+    let ix = env.withdraw_ix(&vault, &alice, 1_000, false);
+    env.ok(&[ix]);
+    let ix = env.close_user_stake_ix(&vault, &alice);
+    assert_vault_err(env.send(&[ix]), VaultError::RewardsOwed);
+
+    env.claim(&vault, &alice);
+    let rent = env.lamports(&user_stake);
+    let alice_before = env.lamports(&alice);
+    let ix = env.close_user_stake_ix(&vault, &alice);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<StakeClosed>(&meta)[0];
+    assert_eq!((ev.user, ev.amount), (alice, 0));
+    let s = &env.vault(&vault).streams[0];
+    assert_eq!(ev.acc_reward_per_token, vec![s.acc_reward_per_token]);
+    assert_eq!(ev.emitted, vec![s.emitted]);
+    assert!(env.is_closed(&user_stake));
+    assert_eq!(env.lamports(&alice), alice_before + rent);
+
+    // A new deposit starts a fresh record
+    env.stake(&vault, &alice, 500);
+    let u = env.user_stake(&vault, &alice);
+    assert_eq!((u.amount, u.owed[0]), (500, 0));
+}
+
+#[test]
+fn stake_record_closes_after_its_vault_is_gone() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.policy.principal = PrincipalPolicy::SweepToTreasury;
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    let user_stake = pda::user(&vault, &alice).0;
+
+    env.create_ata(&DEV_TREASURY, &p.mint);
+    env.warp(30 * DAY + GRACE_SECS);
+    let ix = env.close_vault_ix(&vault);
+    env.ok(&[ix]);
+    assert!(env.is_closed(&vault));
+
+    // The swept principal and unclaimed rewards can no longer be paid out
+    let rent = env.lamports(&user_stake);
+    let alice_before = env.lamports(&alice);
+    let ix = env.close_user_stake_ix(&vault, &alice);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<StakeClosed>(&meta)[0];
+    assert_eq!((ev.vault, ev.amount, ev.total_staked), (vault, 1_000, 0));
+    assert!(ev.acc_reward_per_token.is_empty() && ev.emitted.is_empty());
+    assert!(env.is_closed(&user_stake));
+    assert_eq!(env.lamports(&alice), alice_before + rent);
+}
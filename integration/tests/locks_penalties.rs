@@ -0,0 +1,184 @@
+use driplet_vaults::{LockExpired, VaultError, Withdrawn, MAX_LOCK_SECS};
+use driplet_vaults_integration::*;
+
+/// A year-plus vault so a full-length lock fits inside the term.
+fn long_vault(env: &mut Env) -> (anchor_lang::prelude::Pubkey, VaultParams) {
+    let mut p = env.vault_params();
+    p.duration_secs = 400 * DAY;
+    let vault = env.create_vault(&p);
+    (vault, p)
+}
+
+#[test]
+fn deposit_locked_validates_lock() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.fund(&alice, &p.mint, 1_000);
+
+    for lock in [0, -1, MAX_LOCK_SECS + 1, 31 * DAY] {
+        let ix = env.deposit_ix(&vault, &alice, 100, Some(lock));
+        assert_vault_err(env.send(&[ix]), VaultError::InvalidLock);
+    }
+    let ix = env.deposit_ix(&vault, &alice, 100, Some(30 * DAY));
+    env.ok(&[ix]);
+}
+
+#[test]
+fn locked_stake_earns_boosted_share_and_cannot_leave() {
+    let mut env = Env::new();
+    let (vault, p) = long_vault(&mut env);
+    let alice = env.wallet();
+    let bob = env.wallet();
+
+    env.fund(&alice, &p.mint, 1_000);
+    let ix = env.deposit_ix(&vault, &alice, 1_000, Some(MAX_LOCK_SECS));
+    env.ok(&[ix]);
+    env.stake(&vault, &bob, 1_000);
+    let u = env.user_stake(&vault, &alice);
+    assert_eq!(
+        (u.amount, u.weight, u.lock_until),
+        (1_000, 2_000, env.now() + MAX_LOCK_SECS)
+    );
+    assert_eq!(env.vault(&vault).total_weighted_stake, 3_000);
+
+    env.warp(100 * DAY);
+    env.claim(&vault, &alice);
+    env.claim(&vault, &bob);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 166_666);
+    assert_eq!(env.balance_of(&bob, &p.reward_mint), 83_333);
+
+    let ix = env.withdraw_ix(&vault, &alice, 1, false);
+    assert_vault_err(env.send(&[ix]), VaultError::StakeLocked);
+    let ix = env.expire_lock_ix(&vault, &alice);
+    assert_vault_err(env.send(&[ix]), VaultError::StakeLocked);
+}
+
+#[test]
+fn expire_lock_drops_the_boost() {
+    let mut env = Env::new();
+    let (vault, p) = long_vault(&mut env);
+    let alice = env.wallet();
+    let bob = env.wallet();
+    env.fund(&alice, &p.mint, 1_000);
+    let ix = env.deposit_ix(&vault, &alice, 1_000, Some(100 * DAY));
+    env.ok(&[ix]);
+    env.stake(&vault, &bob, 1_000);
+
+    env.warp(100 * DAY);
+    let preview = env.preview_pending(&vault, &alice);
+    assert_eq!(preview.weight, 1_000);
+
+    // Anyone can crank it; the boosted period is settled first
+    let owed_before = env.user_stake(&vault, &alice).owed[0];
+    let ix = env.expire_lock_ix(&vault, &alice);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<LockExpired>(&meta)[0];
+    assert_eq!((ev.weight, ev.total_weighted_stake), (1_000, 2_000));
+    let u = env.user_stake(&vault, &alice);
+    assert!(u.owed[0] > owed_before);
+    assert_eq!(u.owed[0], preview.owed[0]);
+
+    // Idempotent once expired
+    let ix = env.expire_lock_ix(&vault, &alice);
+    assert!(events::<LockExpired>(&env.ok(&[ix])).is_empty());
+
+    let ix = env.withdraw_ix(&vault, &alice, 1_000, true);
+    env.ok(&[ix]);
+    assert_eq!(env.balance_of(&alice, &p.mint), 1_000);
+}
+
+#[test]
+fn relocking_extends_and_reboosts() {
+    let mut env = Env::new();
+    let (vault, p) = long_vault(&mut env);
+    let alice = env.wallet();
+    env.fund(&alice, &p.mint, 2_000);
+
+    let ix = env.deposit_ix(&vault, &alice, 1_000, Some(MAX_LOCK_SECS / 2));
+    env.ok(&[ix]);
+    assert_eq!(env.user_stake(&vault, &alice).weight, 1_500);
+
+    // Extending to a full year re-boosts the existing stake too
+    let ix = env.deposit_ix(&vault, &alice, 1_000, Some(MAX_LOCK_SECS));
+    env.ok(&[ix]);
+    let u = env.user_stake(&vault, &alice);
+    assert_eq!(
+        (u.amount, u.weight, u.lock_until),
+        (2_000, 4_000, env.now() + MAX_LOCK_SECS)
+    );
+}
+
+#[test]
+fn early_exit_penalty_goes_to_remaining_stakers() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.policy.early_exit_penalty_bps = 1_000;
+    p.policy.min_stake_secs = 7 * DAY;
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    let bob = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    env.stake(&vault, &bob, 1_000);
+
+    env.warp(DAY);
+    let ix = env.withdraw_ix(&vault, &alice, 1_000, false);
+    let meta = env.ok(&[ix]);
+    let w = &events::<Withdrawn>(&meta)[0];
+    assert_eq!((w.amount, w.penalty, w.total_staked), (1_000, 100, 1_000));
+    assert_eq!(env.balance_of(&alice, &p.mint), 900);
+    assert_eq!(env.vault(&vault).penalty_pool, 100);
+
+    // Bob's principal compounds the penalty
+    assert_eq!(env.preview_pending(&vault, &bob).amount, 1_100);
+
+    env.warp(7 * DAY);
+    let ix = env.withdraw_ix(&vault, &bob, 1_100, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<Withdrawn>(&meta)[0].penalty, 0);
+    assert_eq!(env.balance_of(&bob, &p.mint), 1_100);
+    let v = env.vault(&vault);
+    assert_eq!((v.total_staked, v.penalty_pool), (0, 0));
+    assert_eq!(
+        env.balance_of(&driplet_vaults_sdk::pda::vault_escrow(&vault).0, &p.mint),
+        0
+    );
+}
+
+#[test]
+fn penalty_is_waived_once_the_term_is_over() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.policy.early_exit_penalty_bps = 1_000;
+    p.policy.min_stake_secs = 7 * DAY;
+    let vault = env.create_vault(&p);
+    let (alice, bob) = (env.wallet(), env.wallet());
+    env.stake(&vault, &alice, 1_000);
+
+    // Bob joins late; min_stake_secs would still run past end_time
+    env.warp(27 * DAY);
+    env.stake(&vault, &bob, 1_000);
+    env.warp_to(env.vault(&vault).end_time);
+    let ix = env.withdraw_ix(&vault, &bob, 1_000, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<Withdrawn>(&meta)[0].penalty, 0);
+    assert_eq!(env.balance_of(&bob, &p.mint), 1_000);
+}
+
+#[test]
+fn penalty_is_waived_for_the_last_staker() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.policy.early_exit_penalty_bps = 5_000;
+    p.policy.min_stake_secs = 7 * DAY;
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+
+    env.warp(DAY);
+    let ix = env.withdraw_ix(&vault, &alice, 1_000, true);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<Withdrawn>(&meta)[0].penalty, 0);
+    assert_eq!(env.balance_of(&alice, &p.mint), 1_000);
+}
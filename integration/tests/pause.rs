@@ -0,0 +1,250 @@
+use driplet_vaults::{
+    EmissionSchedule, GlobalStateChanged, VaultError, VaultPauseChanged, Withdrawn,
+};
+use driplet_vaults_integration::*;
+use driplet_vaults_sdk::pda;
+
+#[test]
+fn global_pause_blocks_entry_points_but_not_exits() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    env.fund(&alice, &p.mint, 100);
+    env.warp(DAY);
+
+    let ix = env.set_global_pause_ix(true);
+    let meta = env.ok(&[ix]);
+    assert!(events::<GlobalStateChanged>(&meta)[0].paused);
+    assert!(env.global_state().paused);
+
+    let p2 = env.vault_params();
+    let a = env.prepare_vault(&p2);
+    let ix = env.create_vault_ix(&p2, &a);
+    assert_vault_err(env.send(&[ix]), VaultError::Paused);
+    let deposit = env.deposit_ix(&vault, &alice, 100, None);
+    assert_vault_err(env.send(std::slice::from_ref(&deposit)), VaultError::Paused);
+    let ix = env.claim_ix(&vault, &alice);
+    assert_vault_err(env.send(&[ix]), VaultError::Paused);
+    let ix = env.withdraw_ix(&vault, &alice, 100, true);
+    assert_vault_err(env.send(&[ix]), VaultError::Paused);
+    let extra_mint = env.create_mint(TOKEN);
+    env.fund(&p.creator, &extra_mint, gross(1_000));
+    let ix = env.add_reward_stream_ix(
+        &vault,
+        &p.creator,
+        &extra_mint,
+        1_000,
+        None,
+        None,
+        EmissionSchedule::Linear,
+    );
+    assert_vault_err(env.send(&[ix]), VaultError::Paused);
+
+    // Principal can still leave, with or without reward accounts
+    let ix = env.withdraw_ix(&vault, &alice, 100, false);
+    env.ok(&[ix]);
+    let ix = env.emergency_withdraw_ix(&vault, &alice);
+    env.ok(&[ix]);
+    assert_eq!(env.balance_of(&alice, &p.mint), 1_100);
+
+    let ix = env.set_global_pause_ix(false);
+    env.ok(&[ix]);
+    env.ok(&[deposit]);
+    env.claim(&vault, &alice);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 1_000_000 / 30);
+}
+
+#[test]
+fn vault_pause_freezes_emissions_and_shifts_end() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    let end_time = env.vault(&vault).end_time;
+
+    env.warp(10 * DAY);
+    let ix = env.pause_vault_ix(&vault);
+    let meta = env.ok(&[ix]);
+    let paused = &events::<VaultPauseChanged>(&meta)[0];
+    assert!(paused.paused);
+    assert_eq!(env.vault(&vault).paused_at, env.now());
+    let ix = env.pause_vault_ix(&vault);
+    assert_vault_err(env.send(&[ix]), VaultError::Paused);
+
+    // Nothing accrues while paused
+    env.warp(5 * DAY);
+    assert_eq!(env.preview_pending(&vault, &alice).owed, vec![333_333]);
+    let now = env.now();
+    assert_eq!(env.preview_vault_state(&vault, now).streams[0].rate_fp, 0);
+    let ix = env.claim_ix(&vault, &alice);
+    assert_vault_err(env.send(&[ix]), VaultError::Paused);
+
+    let ix = env.unpause_vault_ix(&vault);
+    let meta = env.ok(&[ix]);
+    let resumed = &events::<VaultPauseChanged>(&meta)[0];
+    assert!(!resumed.paused);
+    assert_eq!(resumed.end_time, end_time + 5 * DAY);
+    let v = env.vault(&vault);
+    assert_eq!((v.paused_at, v.end_time), (0, end_time + 5 * DAY));
+    assert_eq!(v.streams[0].end_time, end_time + 5 * DAY);
+    assert_eq!(env.preview_pending(&vault, &alice).owed, vec![333_333]);
+    let ix = env.unpause_vault_ix(&vault);
+    assert_vault_err(env.send(&[ix]), VaultError::NotPaused);
+
+    // The full budget still streams out, just five days later
+    env.warp(20 * DAY - 1);
+    assert!(env.preview_pending(&vault, &alice).owed[0] < 1_000_000);
+    env.warp(1);
+    env.claim(&vault, &alice);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 1_000_000);
+}
+
+#[test]
+fn global_pause_freezes_emissions_and_shifts_each_vault_on_next_touch() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+    let end_time = env.vault(&vault).end_time;
+
+    env.warp(10 * DAY);
+    let ix = env.set_global_pause_ix(true);
+    env.ok(&[ix]);
+    assert_eq!(env.global_state().paused_at, env.now());
+
+    // Nothing accrues while paused, even through a crank
+    env.warp(5 * DAY);
+    assert_eq!(env.preview_pending(&vault, &alice).owed, vec![333_333]);
+    let cranker = env.wallet();
+    let ix = env.crank_ix(&vault, &cranker, false);
+    env.ok(&[ix]);
+    assert_eq!(env.vault(&vault).streams[0].emitted, 333_333);
+
+    let ix = env.set_global_pause_ix(false);
+    let meta = env.ok(&[ix]);
+    let ev = &events::<GlobalStateChanged>(&meta)[0];
+    assert_eq!((ev.paused_at, ev.paused_secs), (0, 5 * DAY));
+
+    // The vault only catches up when next touched
+    assert_eq!(env.vault(&vault).end_time, end_time);
+    assert_eq!(env.preview_pending(&vault, &alice).owed, vec![333_333]);
+    let ix = env.crank_ix(&vault, &cranker, false);
+    env.ok(&[ix]);
+    let v = env.vault(&vault);
+    assert_eq!(
+        (v.end_time, v.global_paused_secs),
+        (end_time + 5 * DAY, 5 * DAY)
+    );
+    assert_eq!(v.streams[0].end_time, end_time + 5 * DAY);
+    assert_eq!(v.streams[0].emitted, 333_333);
+
+    // Vaults created afterwards are not shifted for it
+    let p2 = env.vault_params();
+    let later = env.create_vault(&p2);
+    assert_eq!(env.vault(&later).global_paused_secs, 5 * DAY);
+
+    // The full budget still streams out, just five days later
+    env.warp(20 * DAY - 1);
+    assert!(env.preview_pending(&vault, &alice).owed[0] < 1_000_000);
+    env.warp(1);
+    env.claim(&vault, &alice);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 1_000_000);
+}
+
+#[test]
+fn missed_global_pauses_each_shift_from_their_own_start() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.start_time = Some(env.now() + 3 * DAY);
+    let vault = env.create_vault(&p);
+    let v = env.vault(&vault);
+    let (start_time, end_time) = (v.start_time, v.end_time);
+
+    // One day before the start, then two days after it, with no touch between
+    for (wait, length) in [(0, DAY), (4 * DAY, 2 * DAY)] {
+        env.warp(wait);
+        let ix = env.set_global_pause_ix(true);
+        env.ok(&[ix]);
+        env.warp(length);
+        let ix = env.set_global_pause_ix(false);
+        env.ok(&[ix]);
+    }
+
+    let cranker = env.wallet();
+    let ix = env.crank_ix(&vault, &cranker, false);
+    env.ok(&[ix]);
+    let v = env.vault(&vault);
+    assert_eq!(
+        (v.start_time, v.end_time, v.global_paused_secs),
+        (start_time + DAY, end_time + 3 * DAY, 3 * DAY)
+    );
+}
+
+#[test]
+fn emergency_withdraw_keeps_earned_rewards_and_waives_lock_when_paused() {
+    let mut env = Env::new();
+    let mut p = env.vault_params();
+    p.policy.early_exit_penalty_bps = 1_000;
+    p.policy.min_stake_secs = 7 * DAY;
+    let vault = env.create_vault(&p);
+    let (alice, bob, carol) = (env.wallet(), env.wallet(), env.wallet());
+    env.fund(&alice, &p.mint, 1_000);
+    let ix = env.deposit_ix(&vault, &alice, 1_000, Some(20 * DAY));
+    env.ok(&[ix]);
+    env.stake(&vault, &bob, 1_000);
+    env.stake(&vault, &carol, 1_000);
+    env.warp(DAY);
+
+    let exit_alice = env.emergency_withdraw_ix(&vault, &alice);
+    assert_vault_err(
+        env.send(std::slice::from_ref(&exit_alice)),
+        VaultError::StakeLocked,
+    );
+
+    // Paused: lock and penalty are waived, nothing is paid, and what Alice
+    // earned up to the pause stays owed to her
+    let ix = env.pause_vault_ix(&vault);
+    env.ok(&[ix]);
+    let earned = env.preview_pending(&vault, &alice).owed[0];
+    assert!(earned > 0);
+    let meta = env.ok(&[exit_alice]);
+    let w = &events::<Withdrawn>(&meta)[0];
+    assert_eq!((w.amount, w.penalty, w.user_amount), (1_000, 0, 0));
+    assert_eq!(env.balance_of(&alice, &p.mint), 1_000);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 0);
+    let u = env.user_stake(&vault, &alice);
+    assert_eq!((u.amount, u.weight, u.owed[0]), (0, 0, earned));
+    assert_eq!(env.vault(&vault).streams[0].total_owed, earned);
+    assert_eq!(
+        env.balance_of(&pda::vault_reward(&vault).0, &p.reward_mint),
+        1_000_000
+    );
+    let ix = env.emergency_withdraw_ix(&vault, &alice);
+    assert_vault_err(env.send(&[ix]), VaultError::ZeroAmount);
+
+    // Running again: an early exit pays the usual penalty to whoever stays
+    let ix = env.unpause_vault_ix(&vault);
+    env.ok(&[ix]);
+    let ix = env.emergency_withdraw_ix(&vault, &bob);
+    let meta = env.ok(&[ix]);
+    assert_eq!(events::<Withdrawn>(&meta)[0].penalty, 100);
+    assert_eq!(env.balance_of(&bob, &p.mint), 900);
+
+    let v = env.vault(&vault);
+    assert_eq!((v.total_staked, v.penalty_pool), (1_000, 100));
+    assert_eq!(env.balance_of(&pda::vault_escrow(&vault).0, &p.mint), 1_100);
+
+    // Carol's share of that penalty leaves with her instead of staying pooled
+    let ix = env.emergency_withdraw_ix(&vault, &carol);
+    let meta = env.ok(&[ix]);
+    let w = &events::<Withdrawn>(&meta)[0];
+    assert_eq!((w.amount, w.penalty), (1_100, 0));
+    assert_eq!(env.balance_of(&carol, &p.mint), 1_100);
+    let v = env.vault(&vault);
+    assert_eq!((v.total_staked, v.penalty_pool), (0, 0));
+    assert_eq!(env.balance_of(&pda::vault_escrow(&vault).0, &p.mint), 0);
+}
@@ -0,0 +1,228 @@
+use driplet_vaults::{
+    Claimed, EmissionSchedule, RewardStreamAdded, VaultError, DEV_TREASURY, MAX_REWARD_STREAMS,
+};
+use driplet_vaults_integration::*;
+use driplet_vaults_sdk::pda;
+
+#[test]
+fn partner_stream_pays_alongside_the_primary() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    let partner = env.wallet();
+    let partner_mint = env.create_mint(TOKEN_2022);
+    env.stake(&vault, &alice, 1_000);
+
+    env.fund(&partner, &partner_mint, gross(600_000));
+    let ix = env.add_reward_stream_ix(
+        &vault,
+        &partner,
+        &partner_mint,
+        600_000,
+        None,
+        None,
+        EmissionSchedule::Linear,
+    );
+    let meta = env.ok(&[ix]);
+    let ev = &events::<RewardStreamAdded>(&meta)[0];
+    assert_eq!(
+        (ev.stream_index, ev.reward_net, ev.fee),
+        (1, 600_000, 18_000)
+    );
+    assert_eq!(ev.funder, partner);
+    let s = &env.vault(&vault).streams[1];
+    assert_eq!(
+        (s.mint, s.start_time, s.reward_net),
+        (partner_mint, env.now(), 600_000)
+    );
+    assert_eq!(env.balance_of(&DEV_TREASURY, &partner_mint), 18_000);
+
+    // Without the extra stream's accounts the claim is refused
+    env.warp(15 * DAY);
+    env.create_stream_atas(&vault, &alice);
+    let mut ix = env.claim_ix(&vault, &alice);
+    ix.accounts.truncate(ix.accounts.len() - 4);
+    assert_vault_err(env.send(&[ix]), VaultError::MissingStreamAccounts);
+
+    let preview = env.preview_pending(&vault, &alice);
+    let meta = env.claim(&vault, &alice);
+    assert_eq!(events::<Claimed>(&meta)[0].paid, preview.owed);
+    assert_eq!(preview.owed, vec![500_000, 300_000]);
+
+    env.warp(15 * DAY);
+    let ix = env.withdraw_ix(&vault, &alice, 1_000, true);
+    env.ok(&[ix]);
+    assert_eq!(env.balance_of(&alice, &p.reward_mint), 1_000_000);
+    assert_eq!(env.balance_of(&alice, &partner_mint), 600_000);
+}
+
+#[test]
+fn add_reward_stream_validates_mint_window_and_count() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let end = env.vault(&vault).end_time;
+    let creator = p.creator;
+
+    env.fund(&creator, &p.reward_mint, gross(1_000));
+    let ix = env.add_reward_stream_ix(
+        &vault,
+        &creator,
+        &p.reward_mint,
+        1_000,
+        None,
+        None,
+        EmissionSchedule::Linear,
+    );
+    assert_vault_err(env.send(&[ix]), VaultError::DuplicateRewardMint);
+
+    let mint = env.create_mint(TOKEN);
+    env.fund(&creator, &mint, gross(1_000));
+    for (start, stop) in [
+        (None, Some(end + 1)),
+        (Some(env.now() - 1), None),
+        (Some(end), Some(end)),
+    ] {
+        let ix = env.add_reward_stream_ix(
+            &vault,
+            &creator,
+            &mint,
+            1_000,
+            start,
+            stop,
+            EmissionSchedule::Linear,
+        );
+        assert_vault_err(env.send(&[ix]), VaultError::InvalidStreamWindow);
+    }
+    let ix = env.add_reward_stream_ix(
+        &vault,
+        &creator,
+        &mint,
+        0,
+        None,
+        None,
+        EmissionSchedule::Linear,
+    );
+    assert_vault_err(env.send(&[ix]), VaultError::ZeroAmount);
+
+    // Creator must sign even when a partner funds
+    let partner = env.wallet();
+    env.fund(&partner, &mint, gross(1_000));
+    let mut ix = env.add_reward_stream_ix(
+        &vault,
+        &partner,
+        &mint,
+        1_000,
+        None,
+        None,
+        EmissionSchedule::Linear,
+    );
+    ix.accounts[0].pubkey = partner;
+    assert!(env.send(&[ix]).is_err());
+
+    for _ in 1..MAX_REWARD_STREAMS {
+        let mint = env.create_mint(TOKEN);
+        env.add_reward_stream(&vault, &creator, &mint, 1_000);
+    }
+    let mint = env.create_mint(TOKEN);
+    env.fund(&creator, &mint, gross(1_000));
+    let ix = env.add_reward_stream_ix(
+        &vault,
+        &creator,
+        &mint,
+        1_000,
+        None,
+        None,
+        EmissionSchedule::Linear,
+    );
+    assert_vault_err(env.send(&[ix]), VaultError::TooManyRewardStreams);
+}
+
+#[test]
+fn late_stream_only_emits_inside_its_window() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let start = env.now();
+    let alice = env.wallet();
+    let mint = env.create_mint(TOKEN);
+    env.stake(&vault, &alice, 1_000);
+
+    env.fund(&p.creator, &mint, gross(100_000));
+    let ix = env.add_reward_stream_ix(
+        &vault,
+        &p.creator,
+        &mint,
+        100_000,
+        Some(start + 10 * DAY),
+        Some(start + 20 * DAY),
+        EmissionSchedule::Linear,
+    );
+    env.ok(&[ix]);
+
+    let at = |env: &mut Env, t: i64| env.preview_vault_state(&vault, start + t).streams[1].emitted;
+    assert_eq!(at(&mut env, 10 * DAY), 0);
+    assert_eq!(at(&mut env, 15 * DAY), 50_000);
+    assert_eq!(at(&mut env, 25 * DAY), 100_000);
+
+    // A top-up after the stream's own end is refused even though the vault runs on
+    env.warp(21 * DAY);
+    env.fund(&p.creator, &mint, gross(1_000));
+    let ix = env.top_up_ix(&vault, &p.creator, 1, 1_000);
+    assert_vault_err(env.send(&[ix]), VaultError::StreamEnded);
+
+    env.claim(&vault, &alice);
+    assert_eq!(env.balance_of(&alice, &mint), 100_000);
+}
+
+#[test]
+fn reconcile_reports_shortfall_and_surplus() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let alice = env.wallet();
+    env.stake(&vault, &alice, 1_000);
+
+    env.warp(10 * DAY);
+    let r = env.reconcile(&vault, 0);
+    assert_eq!(r.balance, 1_000_000);
+    assert_eq!(
+        (r.unemitted, r.total_owed, r.unsettled),
+        (666_667, 0, 333_333)
+    );
+    assert_eq!((r.shortfall, r.surplus), (0, 0));
+
+    env.claim(&vault, &alice);
+    let r = env.reconcile(&vault, 0);
+    assert_eq!(
+        (r.balance, r.unsettled, r.shortfall, r.surplus),
+        (666_667, 0, 0, 0)
+    );
+
+    // Tokens sent straight to the reward account are surplus
+    let reward_authority = pda::vault_reward(&vault).0;
+    env.fund(&reward_authority, &p.reward_mint, 42);
+    assert_eq!(env.reconcile(&vault, 0).surplus, 42);
+}
+
+#[test]
+fn close_vault_sweeps_and_closes_extra_streams() {
+    let mut env = Env::new();
+    let p = env.vault_params();
+    let vault = env.create_vault(&p);
+    let mint = env.create_mint(TOKEN_2022);
+    env.add_reward_stream(&vault, &p.creator, &mint, 50_000);
+    let extra_ata = env.ata(&pda::vault_reward(&vault).0, &mint);
+
+    env.warp(30 * DAY + GRACE_SECS);
+    let mut ix = env.close_vault_ix(&vault);
+    ix.accounts.truncate(ix.accounts.len() - 4);
+    assert_vault_err(env.send(&[ix]), VaultError::MissingStreamAccounts);
+
+    // Nobody staked, so everything goes back to the treasury
+    let ix = env.close_vault_ix(&vault);
+    env.ok(&[ix]);
+    assert_eq!(env.balance_of(&DEV_TREASURY, &mint), 1_500 + 50_000);
+    assert!(env.is_closed(&extra_ata));
+}
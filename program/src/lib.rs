@@ -2,9 +2,20 @@ use anchor_lang::prelude::*;
 use solana_program::pubkey;
 use anchor_lang::solana_program::{program::invoke, system_instruction};
 use anchor_spl::token::accessor;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFeeConfig;
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer},
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{
+        self, get_mint_extension_data, CloseAccount, HarvestWithheldTokensToMint, Mint,
+        TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+pub mod math;
+use math::{
+    apply_deposit, apply_emergency_withdraw, apply_withdraw, current_rate_fp, pending_rewards,
+    prepare_claim, record_payout, remaining_schedule_weight, set_weight, settle_pending, shift_schedule,
+    sync_global_pause, sync_reward_debts, update_rewards_at, validate_schedule,
 };
 
 declare_id!("DzDtMwhFDexPAkwRRZVYf9YjArbokm2V8aRghWZMYa8S");
@@ -12,13 +23,32 @@ declare_id!("DzDtMwhFDexPAkwRRZVYf9YjArbokm2V8aRghWZMYa8S");
 // ─────────────────────────────────────────────────────────────────────────────
 // Constants
 // ─────────────────────────────────────────────────────────────────────────────
-const TERM_SECS: i64 = 15_724_800; // 6 months (182 days)
-const CREATION_FEE_LAMPORTS: u64 = 100_000_000; // 0.1 SOL
-const FEE_BPS: u64 = 300; // 3%
-const RPT_SCALE: u128 = 1_000_000_000_000; // 1e12 (acc_reward_per_token precision)
-const RATE_SCALE: u128 = 1_000_000_000_000; // 1e12 (fractional tokens/sec)
+const DEFAULT_MIN_DURATION_SECS: i64 = 86_400; // 1 day
+const DEFAULT_MAX_DURATION_SECS: i64 = 63_072_000; // 2 years
+const DEFAULT_START_DELAY_SECS: i64 = 300; // create_vault start when none is given
+const DEFAULT_MAX_START_HORIZON_SECS: i64 = 31_536_000; // 1 year
+const DEFAULT_MAX_START_BACKDATE_SECS: i64 = 60; // absorbs client clock drift
+const DEFAULT_CREATION_FEE_LAMPORTS: u64 = 100_000_000; // 0.1 SOL
+const DEFAULT_FEE_BPS: u16 = 300; // 3%
+const DEFAULT_CRANK_INTERVAL_SECS: i64 = 3_600; // tipped cranks per vault at most hourly
+const DEFAULT_MAX_CRANK_TIPS_PER_VAULT: u64 = 2_000; // ~12 weeks of hourly tips
+const LEGACY_GLOBAL_STATE_LEN: usize = 8 + 40; // state PDA as first deployed
+const MAX_FEE_BPS: u16 = 1_000; // 10%
+pub const RPT_SCALE: u128 = 1_000_000_000_000; // 1e12 (acc_reward_per_token precision)
+pub const RATE_SCALE: u128 = 1_000_000_000_000; // 1e12 (fractional tokens/sec, reporting only)
+pub const MAX_SCHEDULE_STEPS: usize = 8; // breakpoints in a Stepped schedule
+pub const MAX_HALVINGS: i64 = 32; // ExponentialDecay periods per window (keeps weights < 2^64)
 const THREE_YEARS_SECS: i64 = 31_536_000 * 3; // 3 years in seconds
-pub const DEV_TREASURY: Pubkey = pubkey!("6Vf19AT2sKunpBS7kvPA1Tqw9QZE9UGso3Pc1jg2nYj5");
+const MIN_RETIRED_GRACE_SECS: i64 = 7_776_000; // 90 days; shortest grace retire_program may set
+const UNRETIRE_TIMELOCK_SECS: i64 = 604_800; // 7 days between request_unretire and unretire_program
+pub const MAX_LOCK_SECS: i64 = 31_536_000; // 1 year lock earns the full boost
+pub const MAX_LOCK_BOOST_BPS: u64 = 10_000; // +100% weight at MAX_LOCK_SECS
+const MAX_EARLY_EXIT_PENALTY_BPS: u16 = 5_000; // 50%
+pub const MAX_REWARD_STREAMS: usize = 4; // primary stream + 3 extra
+pub const VAULT_VERSION: u8 = 2; // Vault.version written by create_vault; 1 = VaultV1
+pub const MAX_PAUSE_HISTORY: usize = 8; // finished global pauses kept for vaults to catch up on
+const STREAM_ACCOUNTS: usize = 4; // remaining_accounts per extra stream
+pub const DEV_TREASURY: Pubkey = pubkey!("6Vf19AT2sKunpBS7kvPA1Tqw9QZE9UGso3Pc1jg2nYj5"); // initialises GlobalState
 
 // ─────────────────────────────────────────────────────────────────────────────
 // Program
@@ -27,20 +57,361 @@ pub const DEV_TREASURY: Pubkey = pubkey!("6Vf19AT2sKunpBS7kvPA1Tqw9QZE9UGso3Pc1j
 pub mod driplet_vaults {
     use super::*;
 
+    pub fn set_new_vaults_disabled(ctx: Context<SetNewVaultsDisabled>, disabled: bool) -> Result<()> {
+        set_new_vaults_disabled_on(&mut ctx.accounts.global_state, disabled)
+    }
+
+    /// The original one-way switch, kept for existing clients; the same as
+    /// set_new_vaults_disabled(true).
     pub fn disable_new_vaults(ctx: Context<DisableNewVaults>) -> Result<()> {
+        set_new_vaults_disabled_on(&mut ctx.accounts.global_state, true)
+    }
+
+    /// With the allowlist enforced, create_vault only accepts mints listed as
+    /// Allowed; otherwise only Denied mints are refused.
+    pub fn set_mint_allowlist_only(
+        ctx: Context<SetMintAllowlistOnly>,
+        allowlist_only: bool,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        state.mint_allowlist_only = allowlist_only;
+        emit_global_state_changed(state)
+    }
+
+    /// Lists a mint for create_vault, as either the staking or the reward mint.
+    pub fn set_mint_listing(ctx: Context<SetMintListing>, listing: MintListing) -> Result<()> {
+        let rule = &mut ctx.accounts.mint_rule;
+        rule.mint = ctx.accounts.mint.key();
+        rule.listing = listing;
+        emit!(MintListingChanged {
+            mint: rule.mint,
+            listing: Some(listing),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Drops a mint's listing, so it is treated like any unlisted mint again.
+    pub fn clear_mint_listing(ctx: Context<ClearMintListing>) -> Result<()> {
+        emit!(MintListingChanged {
+            mint: ctx.accounts.mint_rule.mint,
+            listing: None,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// DEV_TREASURY bootstraps the program: it becomes both the admin and the
+    /// fee recipient, and either can be rotated afterwards without a redeploy.
+    pub fn init_global_state(ctx: Context<InitGlobalState>) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        state.set_inner(GlobalState::new(ctx.accounts.dev_treasury.key()));
+        emit_global_state_changed(state)
+    }
+
+    /// Grows a state PDA still in the original layout (`new_vaults_disabled`
+    /// and `admin`, 8+40 bytes) to the current GlobalState. The old admin
+    /// keeps control and becomes the fee recipient; every other field takes
+    /// its init_global_state default. The admin pays the extra rent.
+    pub fn migrate_global_state(ctx: Context<MigrateGlobalState>) -> Result<()> {
+        let info = ctx.accounts.global_state.to_account_info();
+        let (new_vaults_disabled, admin) = {
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() == LEGACY_GLOBAL_STATE_LEN && data[..8] == *GlobalState::DISCRIMINATOR,
+                VaultError::AlreadyMigrated
+            );
+            (data[8] != 0, Pubkey::new_from_array(data[9..41].try_into().unwrap()))
+        };
+        require_keys_eq!(admin, ctx.accounts.admin.key(), VaultError::Unauthorized);
+
+        let mut state = GlobalState::new(admin);
+        state.new_vaults_disabled = new_vaults_disabled;
+        rewrite_account(
+            &info,
+            &state,
+            8 + GlobalState::SPACE,
+            ctx.accounts.admin.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
+        emit_global_state_changed(&state)
+    }
+
+    pub fn set_duration_bounds(
+        ctx: Context<SetDurationBounds>,
+        min_duration_secs: i64,
+        max_duration_secs: i64,
+    ) -> Result<()> {
         let state = &mut ctx.accounts.global_state;
         require!(
-            ctx.accounts.dev_treasury.key() == state.admin,
-            VaultError::Unauthorized
+            min_duration_secs > 0 && min_duration_secs <= max_duration_secs,
+            VaultError::InvalidDuration
         );
-        state.new_vaults_disabled = true;
+        state.min_duration_secs = min_duration_secs;
+        state.max_duration_secs = max_duration_secs;
+        emit_global_state_changed(state)
+    }
+
+    /// Rewrites a vault created before reward streams (`version == 1`) in the
+    /// current layout; see `VaultV1::upgrade`. A v1 close_vault always swept
+    /// unwithdrawn principal, so there is no policy to carry over: the admin
+    /// picks `principal` here, and covers the extra rent.
+    pub fn migrate_vault(ctx: Context<MigrateVault>, principal: PrincipalPolicy) -> Result<()> {
+        let info = ctx.accounts.vault.to_account_info();
+        let old = {
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() == VaultV1::LEN
+                    && data[..8] == *Vault::DISCRIMINATOR
+                    && data[VaultV1::LEN - VaultV1::PADDING - 1] == 1,
+                VaultError::AlreadyMigrated
+            );
+            VaultV1::deserialize(&mut &data[8..])?
+        };
+        let vault = old.upgrade(principal);
+        rewrite_account(
+            &info,
+            &vault,
+            8 + Vault::SPACE,
+            ctx.accounts.admin.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
+        emit!(AccountMigrated {
+            account: info.key(),
+            version: VAULT_VERSION,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
-    pub fn init_global_state(ctx: Context<InitGlobalState>) -> Result<()> {
+    /// Rewrites a UserStake from before reward streams in the current layout;
+    /// see `UserStakeV1::upgrade`. Permissionless: the result is fully
+    /// determined by the old account. The payer covers the extra rent.
+    pub fn migrate_user_stake(ctx: Context<MigrateUserStake>) -> Result<()> {
+        let info = ctx.accounts.user_stake.to_account_info();
+        let old = {
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() == UserStakeV1::LEN && data[..8] == *UserStake::DISCRIMINATOR,
+                VaultError::AlreadyMigrated
+            );
+            UserStakeV1::deserialize(&mut &data[8..])?
+        };
+        rewrite_account(
+            &info,
+            &old.upgrade(),
+            8 + UserStake::SPACE,
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
+        emit!(AccountMigrated {
+            account: info.key(),
+            version: VAULT_VERSION,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Rules for vault start times, see GlobalState::resolve_start.
+    pub fn set_start_bounds(
+        ctx: Context<SetStartBounds>,
+        min_lead_secs: i64,
+        max_horizon_secs: i64,
+        max_backdate_secs: i64,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        require!(
+            min_lead_secs >= 0 && min_lead_secs <= max_horizon_secs && max_backdate_secs >= 0,
+            VaultError::InvalidStartTime
+        );
+        state.min_start_lead_secs = min_lead_secs;
+        state.max_start_horizon_secs = max_horizon_secs;
+        state.max_start_backdate_secs = max_backdate_secs;
+        emit_global_state_changed(state)
+    }
+
+    /// First half of an admin transfer; the new key must call accept_admin.
+    /// Proposing `Pubkey::default()` cancels a pending transfer.
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        state.pending_admin = new_admin;
+        emit_global_state_changed(state)
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        state.admin = ctx.accounts.pending_admin.key();
+        state.pending_admin = Pubkey::default();
+        emit_global_state_changed(state)
+    }
+
+    /// Applies to vaults created, rewards funded and vaults cancelled from now
+    /// on; fees already taken are not touched. With `cancel_refunds_fee` the
+    /// token fee stays in the reward ATA instead of going to the recipient:
+    /// cancel_vault hands it back with the rewards, and otherwise it reaches
+    /// the recipient with close_vault's sweep.
+    pub fn update_fees(
+        ctx: Context<UpdateFees>,
+        fee_recipient: Pubkey,
+        creation_fee_lamports: u64,
+        fee_bps: u16,
+        cancel_fee_lamports: u64,
+        cancel_refunds_fee: bool,
+    ) -> Result<()> {
+        require!(fee_bps <= MAX_FEE_BPS, VaultError::InvalidFee);
+        require!(fee_recipient != Pubkey::default(), VaultError::InvalidFee);
+        let state = &mut ctx.accounts.global_state;
+        state.fee_recipient = fee_recipient;
+        state.creation_fee_lamports = creation_fee_lamports;
+        state.fee_bps = fee_bps;
+        state.cancel_fee_lamports = cancel_fee_lamports;
+        state.cancel_refunds_fee = cancel_refunds_fee;
+        emit_global_state_changed(state)
+    }
+
+    /// Stops new vaults for good (until unretired) and optionally shortens the
+    /// grace before close_vault / admin_close_expired_stake. Every vault still
+    /// gets `grace_secs` after the later of its end and the retirement, and
+    /// never more than the usual three years after its end.
+    pub fn retire_program(ctx: Context<RetireProgram>, grace_secs: Option<i64>) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        require!(state.retired_at == 0, VaultError::AlreadyRetired);
+        let grace_secs = grace_secs.unwrap_or(THREE_YEARS_SECS);
+        require!(
+            (MIN_RETIRED_GRACE_SECS..=THREE_YEARS_SECS).contains(&grace_secs),
+            VaultError::InvalidDuration
+        );
+        state.retired_at = Clock::get()?.unix_timestamp;
+        state.retired_grace_secs = grace_secs;
+        state.unretire_requested_at = 0;
+        emit_global_state_changed(state)
+    }
+
+    /// Starts the UNRETIRE_TIMELOCK_SECS countdown; calling it again restarts it.
+    pub fn request_unretire(ctx: Context<RequestUnretire>) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        require!(state.retired_at != 0, VaultError::NotRetired);
+        state.unretire_requested_at = Clock::get()?.unix_timestamp;
+        emit_global_state_changed(state)
+    }
+
+    pub fn unretire_program(ctx: Context<UnretireProgram>) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        require!(state.retired_at != 0, VaultError::NotRetired);
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            state.unretire_requested_at != 0
+                && now >= state.unretire_requested_at + UNRETIRE_TIMELOCK_SECS,
+            VaultError::TimelockNotElapsed
+        );
+        state.retired_at = 0;
+        state.retired_grace_secs = 0;
+        state.unretire_requested_at = 0;
+        emit_global_state_changed(state)
+    }
+
+    /// Blocks create_vault, deposit and claim in every vault and freezes
+    /// emissions everywhere. Lifting it pushes each vault's remaining schedule
+    /// back by the pause, applied lazily the next time the vault is touched;
+    /// the last MAX_PAUSE_HISTORY pauses are kept so each is applied from its
+    /// own start.
+    pub fn set_global_pause(ctx: Context<SetGlobalPause>, paused: bool) -> Result<()> {
+        let state = &mut ctx.accounts.global_state;
+        let now = Clock::get()?.unix_timestamp;
+        if paused && !state.paused {
+            state.paused_at = now;
+        } else if !paused && state.paused {
+            state.record_pause(now)?;
+        }
+        state.paused = paused;
+        emit_global_state_changed(state)
+    }
+
+    /// Tip paid per crank from the crank pool (0 = none), how long a vault's
+    /// accrual must have gone stale before a crank earns it, and how many
+    /// tips one vault may collect over its life.
+    pub fn set_crank_tip(
+        ctx: Context<SetCrankTip>,
+        tip_lamports: u64,
+        interval_secs: i64,
+        max_tips_per_vault: u64,
+    ) -> Result<()> {
+        require!(interval_secs > 0, VaultError::InvalidDuration);
         let state = &mut ctx.accounts.global_state;
-        state.new_vaults_disabled = false;
-        state.admin = ctx.accounts.dev_treasury.key();
+        state.crank_tip_lamports = tip_lamports;
+        state.crank_interval_secs = interval_secs;
+        state.max_crank_tips_per_vault = max_tips_per_vault;
+        emit_global_state_changed(state)
+    }
+
+    /// Anyone may fund the crank pool; the treasury is expected to. Creates
+    /// the pool on first use.
+    pub fn fund_crank_pool(ctx: Context<FundCrankPool>, lamports: u64) -> Result<()> {
+        require!(lamports > 0, VaultError::ZeroAmount);
+        let funder = ctx.accounts.funder.to_account_info();
+        let pool = ctx.accounts.crank_pool.to_account_info();
+        invoke(
+            &system_instruction::transfer(&funder.key(), &pool.key(), lamports),
+            &[funder.clone(), pool, ctx.accounts.system_program.to_account_info()],
+        )?;
+        ctx.accounts.crank_pool.bump = ctx.bumps.crank_pool;
+        emit_crank_pool_changed(&ctx.accounts.crank_pool, funder.key(), lamports, 0)
+    }
+
+    /// Returns unspent tips to the fee recipient; the pool stays rent-exempt.
+    pub fn withdraw_crank_pool(ctx: Context<WithdrawCrankPool>, lamports: u64) -> Result<()> {
+        require!(lamports > 0, VaultError::ZeroAmount);
+        let pool = ctx.accounts.crank_pool.to_account_info();
+        require!(
+            crank_pool_available(&pool)? >= lamports,
+            VaultError::InsufficientCrankPool
+        );
+        **pool.try_borrow_mut_lamports()? -= lamports;
+        **ctx.accounts.dev_treasury.try_borrow_mut_lamports()? += lamports;
+        emit_crank_pool_changed(&ctx.accounts.crank_pool, ctx.accounts.admin.key(), 0, lamports)
+    }
+
+    /// Halts one vault: deposits, claims and funding are refused and nothing
+    /// is emitted until unpause_vault. emergency_withdraw keeps working. Under
+    /// a global pause the vault is already frozen, so its pause dates from
+    /// the global one's start.
+    pub fn pause_vault(ctx: Context<PauseVault>) -> Result<()> {
+        let v = &mut ctx.accounts.vault;
+        require!(v.paused_at == 0, VaultError::Paused);
+        let accrued_to = update_rewards(v, &ctx.accounts.global_state)?;
+        let now = Clock::get()?.unix_timestamp;
+        v.paused_at = accrued_to;
+        emit!(VaultPauseChanged {
+            vault: v.key(),
+            paused: true,
+            start_time: v.start_time,
+            end_time: v.end_time,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Pushes the rest of the schedule, end_time included, back by the time
+    /// spent paused, so stakers lose no emissions to the pause. Locks run on
+    /// wall-clock time and are not extended. Lift any global pause first; the
+    /// shift covers global pauses that ended while the vault was paused.
+    pub fn unpause_vault(ctx: Context<UnpauseVault>) -> Result<()> {
+        let state = &ctx.accounts.global_state;
+        let v = &mut ctx.accounts.vault;
+        require!(v.paused_at != 0, VaultError::NotPaused);
+        require!(!state.paused, VaultError::Paused);
+        let now = Clock::get()?.unix_timestamp;
+        sync_global_pause(v, state, now);
+        let paused_at = v.paused_at;
+        shift_schedule(v, paused_at, now - paused_at);
+        v.paused_at = 0;
+        emit!(VaultPauseChanged {
+            vault: v.key(),
+            paused: false,
+            start_time: v.start_time,
+            end_time: v.end_time,
+            timestamp: now,
+        });
         Ok(())
     }
 
@@ -49,19 +420,37 @@ pub mod driplet_vaults {
         reward_net: u64,
         maybe_start_time: Option<i64>,
         vault_id: u64,                    // NEW
+        duration_secs: i64,
+        schedule: EmissionSchedule,
+        policy: VaultPolicy,
     ) -> Result<()> {
         // Enforce no new vaults if disabled
+        let state = &ctx.accounts.global_state;
+        require!(state.retired_at == 0, VaultError::ProgramRetired);
+        require!(!state.paused, VaultError::Paused);
+        require!(!state.new_vaults_disabled, VaultError::VaultCreationDisabled);
         require!(
-            !ctx.accounts.global_state.new_vaults_disabled,
-            VaultError::VaultCreationDisabled
+            mint_allowed(state, &ctx.accounts.mint_rule)?
+                && mint_allowed(state, &ctx.accounts.reward_mint_rule)?,
+            VaultError::MintNotAllowed
         );
         require!(reward_net > 0, VaultError::ZeroAmount);
+        require!(
+            duration_secs >= state.min_duration_secs && duration_secs <= state.max_duration_secs,
+            VaultError::InvalidDuration
+        );
+        require!(
+            policy.early_exit_penalty_bps <= MAX_EARLY_EXIT_PENALTY_BPS
+                && policy.min_stake_secs >= 0
+                && policy.min_stake_secs <= duration_secs,
+            VaultError::InvalidPolicy
+        );
 
         // 0) Creation fee (SOL) -> dev treasury
         let dev_treasury = ctx.accounts.dev_treasury.to_account_info();
         let creator = ctx.accounts.creator.to_account_info();
         invoke(
-            &system_instruction::transfer(&creator.key(), &dev_treasury.key(), CREATION_FEE_LAMPORTS),
+            &system_instruction::transfer(&creator.key(), &dev_treasury.key(), state.creation_fee_lamports),
             &[
                 creator.clone(),
                 dev_treasury.clone(),
@@ -71,11 +460,16 @@ pub mod driplet_vaults {
 
         // 1) Times
         let now = Clock::get()?.unix_timestamp;
-        let start = maybe_start_time.unwrap_or(now + 300);
-        let end = start + TERM_SECS;
+        let start = state.resolve_start(
+            maybe_start_time.unwrap_or(now + DEFAULT_START_DELAY_SECS.max(state.min_start_lead_secs)),
+            now,
+        )?;
+        let end = start
+            .checked_add(duration_secs)
+            .ok_or(VaultError::MathOverflow)?;
 
         // 2) Fees & totals
-        let fee_tokens = reward_net.saturating_mul(FEE_BPS) / 10_000;
+        let fee_tokens = state.fee_on(reward_net);
         let reward_gross = reward_net
             .checked_add(fee_tokens)
             .ok_or(VaultError::MathOverflow)?;
@@ -85,18 +479,21 @@ pub mod driplet_vaults {
         let reward_bump = ctx.bumps.vault_reward_authority;
         let escrow_bump = ctx.bumps.vault_escrow_authority;
         let mint_key = ctx.accounts.mint.key();
+        let reward_mint_key = ctx.accounts.reward_mint.key();
 
         // --- SANITY CHECKS (before any CPI) ---
+        // Rewards (creator funding, reward vault, dev fee) are in `reward_mint`;
+        // only the escrow holds the staked `mint`.
         require!(
-            accessor::mint(&ctx.accounts.creator_token_ata.to_account_info())? == mint_key,
+            accessor::mint(&ctx.accounts.creator_token_ata.to_account_info())? == reward_mint_key,
             VaultError::BadMint
         );
         require!(
-            accessor::mint(&ctx.accounts.reward_vault_ata.to_account_info())? == mint_key,
+            accessor::mint(&ctx.accounts.reward_vault_ata.to_account_info())? == reward_mint_key,
             VaultError::BadMint
         );
         require!(
-            accessor::mint(&ctx.accounts.dev_token_ata.to_account_info())? == mint_key,
+            accessor::mint(&ctx.accounts.dev_token_ata.to_account_info())? == reward_mint_key,
             VaultError::BadMint
         );
         require!(
@@ -123,248 +520,1143 @@ pub mod driplet_vaults {
                 == ctx.accounts.dev_treasury.key(),
             VaultError::BadMint
         );
+        // Unchecked ATAs must belong to the token program of their mint
+        let reward_program_key = ctx.accounts.reward_token_program.key();
+        require!(
+            *ctx.accounts.creator_token_ata.owner == reward_program_key
+                && *ctx.accounts.reward_vault_ata.owner == reward_program_key
+                && *ctx.accounts.dev_token_ata.owner == reward_program_key
+                && *ctx.accounts.vault_escrow_ata.owner == ctx.accounts.token_program.key(),
+            VaultError::BadMint
+        );
 
         // 4) creator sends gross rewards to vault reward ATA
-        token::transfer(
+        let reward_decimals = ctx.accounts.reward_mint.decimals;
+        let reward_before = accessor::amount(&ctx.accounts.reward_vault_ata.to_account_info())?;
+        token_interface::transfer_checked(
             CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                ctx.accounts.reward_token_program.to_account_info(),
+                TransferChecked {
                     from: ctx.accounts.creator_token_ata.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
                     to: ctx.accounts.reward_vault_ata.to_account_info(),
                     authority: ctx.accounts.creator.to_account_info(),
                 },
             ),
             reward_gross,
+            reward_decimals,
         )?;
+        // Token-2022 transfer fees are withheld from what arrives, so book the
+        // balance delta rather than the requested amount.
+        let reward_received = accessor::amount(&ctx.accounts.reward_vault_ata.to_account_info())?
+            .checked_sub(reward_before)
+            .ok_or(VaultError::MathOverflow)?;
 
-        // 5) Skim 3% fee to dev ATA (signed by reward PDA)
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.reward_vault_ata.to_account_info(),
-                    to: ctx.accounts.dev_token_ata.to_account_info(),
-                    authority: ctx.accounts.vault_reward_authority.to_account_info(),
-                },
-                &[&[b"vault_reward", vault_key.as_ref(), &[reward_bump]]],
-            ),
-            fee_tokens,
-        )?;
+        // 5) Skim 3% fee to dev ATA (signed by reward PDA), unless it is held
+        //    in the reward ATA for cancel_vault to refund
+        if !state.cancel_refunds_fee {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.reward_token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.reward_vault_ata.to_account_info(),
+                        mint: ctx.accounts.reward_mint.to_account_info(),
+                        to: ctx.accounts.dev_token_ata.to_account_info(),
+                        authority: ctx.accounts.vault_reward_authority.to_account_info(),
+                    },
+                    &[&[b"vault_reward", vault_key.as_ref(), &[reward_bump]]],
+                ),
+                fee_tokens,
+                reward_decimals,
+            )?;
+        }
+        let reward_net = reward_received
+            .checked_sub(fee_tokens)
+            .ok_or(VaultError::MathOverflow)?;
+        require!(reward_net > 0, VaultError::ZeroAmount);
 
-        // 6) Write vault state
+        // 6) Write vault state; the creator's rewards become stream 0
         let vault = &mut ctx.accounts.vault;
         vault.id = vault_id;                          // NEW: persist the id
         vault.creator = ctx.accounts.creator.key();
-        vault.mint = ctx.accounts.mint.key();
+        vault.mint = mint_key;
         vault.start_time = start;
         vault.end_time = end;
-        vault.reward_net = reward_net;
-        vault.reward_fee = fee_tokens;
-        vault.reward_gross = reward_gross;
-        vault.rate_fp = (reward_net as u128)
-            .checked_mul(RATE_SCALE).ok_or(VaultError::MathOverflow)?
-            .checked_div(TERM_SECS as u128).ok_or(VaultError::MathOverflow)?;
-        vault.emission_acc_fp = 0;
-        vault.emitted = 0;
-        vault.last_update_time = start;
-        vault.acc_reward_per_token = 0;
-        vault.unallocated = 0;
+        vault.duration_secs = duration_secs;
         vault.total_staked = 0;
+        vault.total_weighted_stake = 0;
+        vault.policy = policy;
+        vault.acc_penalty_per_token = 0;
+        vault.penalty_pool = 0;
+        vault.paused_at = 0;
+        vault.global_paused_secs = ctx.accounts.global_state.paused_secs;
+        vault.last_cranked_at = 0;
+        vault.crank_tips_paid = 0;
+        vault.streams = vec![RewardStream::new(
+            reward_mint_key,
+            vault.creator,
+            start,
+            end,
+            schedule,
+            reward_net,
+            fee_tokens,
+            reward_gross,
+        )?];
         vault.bump = ctx.bumps.vault;
         vault.vault_escrow_bump = escrow_bump;
         vault.vault_reward_bump = reward_bump;
-        vault.version = 1;
+        vault.version = VAULT_VERSION;
 
+        let (acc_reward_per_token, emitted) = stream_totals(vault);
+        emit!(VaultCreated {
+            vault: vault.key(),
+            vault_id,
+            creator: vault.creator,
+            mint: vault.mint,
+            reward_mint: reward_mint_key,
+            start_time: start,
+            end_time: end,
+            reward_net,
+            fee: fee_tokens,
+            total_staked: vault.total_staked,
+            acc_reward_per_token,
+            emitted,
+            timestamp: now,
+        });
         Ok(())
     }
 
-    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-        require!(amount > 0, VaultError::ZeroAmount);
-
+    /// Moves a vault that has not started yet, along with its streams. Only
+    /// while nobody has staked; the new start follows the same rules as
+    /// create_vault's.
+    pub fn reschedule_start(ctx: Context<RescheduleStart>, new_start_time: i64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
         let v = &mut ctx.accounts.vault;
+        sync_global_pause(v, &ctx.accounts.global_state, now);
+        require!(now < v.start_time, VaultError::AlreadyStarted);
+        require!(v.total_staked == 0, VaultError::StillStaked);
+        require!(v.paused_at == 0, VaultError::Paused);
+        let start = ctx.accounts.global_state.resolve_start(new_start_time, now)?;
 
-        // Allow deposits only within [start_time, end_time]
+        let old_start = v.start_time;
+        shift_schedule(v, now, start - old_start);
+        emit!(VaultRescheduled {
+            vault: v.key(),
+            start_time: v.start_time,
+            end_time: v.end_time,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Unwinds a vault nobody has used: before its start, or while nothing is
+    /// staked and nothing has been emitted. Rewards go back to each
+    /// stream's funder (the creator for the primary stream). Token fees are
+    /// refunded with them if they were held under `cancel_refunds_fee`, and
+    /// stay with the treasury otherwise; cancel_fee_lamports is charged on
+    /// top. Every account is closed to the creator. Pass `[mint,
+    /// reward_vault_ata, funder_ata, token_program]` per extra stream.
+    pub fn cancel_vault<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelVault<'info>>,
+    ) -> Result<()> {
+        let extra = extra_stream_accounts(
+            &ctx.accounts.vault,
+            &ctx.accounts.vault_reward_authority.key(),
+            |s| s.funder,
+            ctx.remaining_accounts,
+        )?;
+        let vault_key = ctx.accounts.vault.key();
+        let reward_bump = ctx.accounts.vault.vault_reward_bump;
+        let escrow_bump = ctx.accounts.vault.vault_escrow_bump;
         let now = Clock::get()?.unix_timestamp;
-        require!(now >= v.start_time && now <= v.end_time, VaultError::VaultEnded);
+        let vault = &mut ctx.accounts.vault;
 
-        update_rewards(v)?;
+        update_rewards(vault, &ctx.accounts.global_state)?;
+        require!(
+            now < vault.start_time
+                || (vault.total_staked == 0 && vault.streams.iter().all(|s| s.emitted == 0)),
+            VaultError::CancelNotAllowed
+        );
+
+        let fee = ctx.accounts.global_state.cancel_fee_lamports;
+        if fee > 0 {
+            let creator = ctx.accounts.creator.to_account_info();
+            let dev_treasury = ctx.accounts.dev_treasury.to_account_info();
+            invoke(
+                &system_instruction::transfer(&creator.key(), &dev_treasury.key(), fee),
+                &[creator, dev_treasury, ctx.accounts.system_program.to_account_info()],
+            )?;
+        }
 
-        let vault_key = v.key();
+        // ── Refund the primary stream to the creator, then close its ATA
+        let mut refunded = Vec::with_capacity(vault.streams.len());
+        let rewards_left = ctx.accounts.reward_vault_ata.amount;
+        if rewards_left > 0 {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.reward_token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.reward_vault_ata.to_account_info(),
+                        mint: ctx.accounts.reward_mint.to_account_info(),
+                        to: ctx.accounts.creator_token_ata.to_account_info(),
+                        authority: ctx.accounts.vault_reward_authority.to_account_info(),
+                    },
+                    &[&[b"vault_reward", vault_key.as_ref(), &[reward_bump]]],
+                ),
+                rewards_left,
+                ctx.accounts.reward_mint.decimals,
+            )?;
+        }
+        refunded.push(rewards_left);
+        harvest_withheld_fees(
+            ctx.accounts.reward_token_program.to_account_info(),
+            ctx.accounts.reward_mint.to_account_info(),
+            ctx.accounts.reward_vault_ata.to_account_info(),
+        )?;
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.reward_token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.reward_vault_ata.to_account_info(),
+                destination: ctx.accounts.creator.to_account_info(),
+                authority: ctx.accounts.vault_reward_authority.to_account_info(),
+            },
+            &[&[b"vault_reward", vault_key.as_ref(), &[reward_bump]]],
+        ))?;
 
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_token_ata.to_account_info(),
-                    to: ctx.accounts.vault_escrow_ata.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
+        // ── Same for every extra stream, refunded to its funder
+        for acc in extra.iter() {
+            let left = accessor::amount(&acc.reward_vault_ata)?;
+            if left > 0 {
+                token_interface::transfer_checked(
+                    CpiContext::new_with_signer(
+                        acc.token_program.clone(),
+                        TransferChecked {
+                            from: acc.reward_vault_ata.clone(),
+                            mint: acc.mint.to_account_info(),
+                            to: acc.counterparty_ata.clone(),
+                            authority: ctx.accounts.vault_reward_authority.to_account_info(),
+                        },
+                        &[&[b"vault_reward", vault_key.as_ref(), &[reward_bump]]],
+                    ),
+                    left,
+                    acc.mint.decimals,
+                )?;
+            }
+            refunded.push(left);
+            harvest_withheld_fees(
+                acc.token_program.clone(),
+                acc.mint.to_account_info(),
+                acc.reward_vault_ata.clone(),
+            )?;
+            token_interface::close_account(CpiContext::new_with_signer(
+                acc.token_program.clone(),
+                CloseAccount {
+                    account: acc.reward_vault_ata.clone(),
+                    destination: ctx.accounts.creator.to_account_info(),
+                    authority: ctx.accounts.vault_reward_authority.to_account_info(),
                 },
-            ),
-            amount,
-        )?;
+                &[&[b"vault_reward", vault_key.as_ref(), &[reward_bump]]],
+            ))?;
+        }
 
-        let user_stake = &mut ctx.accounts.user_stake;
-        if user_stake.initialized == 0 {
-            user_stake.owner = ctx.accounts.user.key();
-            user_stake.vault = vault_key;
-            user_stake.amount = 0;
-            user_stake.reward_debt = 0;
-            user_stake.initialized = 1;
+        // Nothing is staked, so no principal is held: anything in escrow is
+        // penalty rounding dust or was sent here directly
+        let escrow_left = ctx.accounts.vault_escrow_ata.amount;
+        if escrow_left > 0 {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.vault_escrow_ata.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.creator_stake_ata.to_account_info(),
+                        authority: ctx.accounts.vault_escrow_authority.to_account_info(),
+                    },
+                    &[&[b"vault_escrow", vault_key.as_ref(), &[escrow_bump]]],
+                ),
+                escrow_left,
+                ctx.accounts.mint.decimals,
+            )?;
         }
 
-        v.total_staked = v.total_staked.checked_add(amount).ok_or(VaultError::MathOverflow)?;
-        user_stake.amount = user_stake.amount.checked_add(amount).ok_or(VaultError::MathOverflow)?;
-        user_stake.reward_debt = reward_debt(user_stake.amount, v.acc_reward_per_token);
+        emit!(VaultCancelled {
+            vault: vault_key,
+            creator: vault.creator,
+            refunded,
+            escrow_swept: escrow_left,
+            cancel_fee_lamports: fee,
+            timestamp: now,
+        });
 
-        Ok(())
+        close_escrow_and_vault(
+            &mut ctx.accounts.vault,
+            &mut ctx.accounts.vault_escrow_ata,
+            &ctx.accounts.mint,
+            ctx.accounts.vault_escrow_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.creator.to_account_info(),
+        )
     }
 
+    /// Attach an additional reward stream (different mint, own window) to a
+    /// live vault. The creator authorises; `funder` may be a partner. Refused
+    /// while the vault or the program is paused.
+    pub fn add_reward_stream(
+        ctx: Context<AddRewardStream>,
+        reward_net: u64,
+        maybe_start_time: Option<i64>,
+        maybe_end_time: Option<i64>,
+        schedule: EmissionSchedule,
+    ) -> Result<()> {
+        require!(reward_net > 0, VaultError::ZeroAmount);
+        require_live(&ctx.accounts.global_state, &ctx.accounts.vault)?;
+        // Catch up on global pauses so the window is checked against the
+        // schedule as it now stands
+        let now = update_rewards(&mut ctx.accounts.vault, &ctx.accounts.global_state)?;
+        let vault = &ctx.accounts.vault;
+        let reward_mint_key = ctx.accounts.reward_mint.key();
+        require!(
+            vault.streams.len() < MAX_REWARD_STREAMS,
+            VaultError::TooManyRewardStreams
+        );
+        require!(
+            vault.streams.iter().all(|s| s.mint != reward_mint_key),
+            VaultError::DuplicateRewardMint
+        );
+
+        // Stream window must sit inside the vault term and not be backdated
+        let earliest = now.max(vault.start_time);
+        let start = maybe_start_time.unwrap_or(earliest);
+        let end = maybe_end_time.unwrap_or(vault.end_time);
+        require!(
+            start >= earliest && start < end && end <= vault.end_time,
+            VaultError::InvalidStreamWindow
+        );
 
+        let fee_tokens = ctx.accounts.global_state.fee_on(reward_net);
+        let reward_gross = reward_net
+            .checked_add(fee_tokens)
+            .ok_or(VaultError::MathOverflow)?;
 
-    pub fn claim(ctx: Context<Claim>) -> Result<()> {
-        let vault = &mut ctx.accounts.vault;
-        update_rewards(vault)?;
+        let vault_key = vault.key();
+        let reward_bump = vault.vault_reward_bump;
+        let reward_decimals = ctx.accounts.reward_mint.decimals;
 
-        payout_pending(
-            vault,
-            &ctx.accounts.user_stake,
-            ctx.accounts.reward_vault_ata.to_account_info(),
-            ctx.accounts.user_token_ata.to_account_info(),
-            ctx.accounts.token_program.to_account_info(),
-            ctx.accounts.vault_reward_authority.to_account_info(),
+        // Funder sends gross rewards to the stream's reward ATA
+        let reward_before = ctx.accounts.reward_vault_ata.amount;
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.reward_token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.funder_token_ata.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    to: ctx.accounts.reward_vault_ata.to_account_info(),
+                    authority: ctx.accounts.funder.to_account_info(),
+                },
+            ),
+            reward_gross,
+            reward_decimals,
+        )?;
+        ctx.accounts.reward_vault_ata.reload()?;
+        let reward_received = ctx.accounts.reward_vault_ata.amount
+            .checked_sub(reward_before)
+            .ok_or(VaultError::MathOverflow)?;
+
+        // Same 3% skim as create_vault
+        if !ctx.accounts.global_state.cancel_refunds_fee {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.reward_token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.reward_vault_ata.to_account_info(),
+                        mint: ctx.accounts.reward_mint.to_account_info(),
+                        to: ctx.accounts.dev_token_ata.to_account_info(),
+                        authority: ctx.accounts.vault_reward_authority.to_account_info(),
+                    },
+                    &[&[b"vault_reward", vault_key.as_ref(), &[reward_bump]]],
+                ),
+                fee_tokens,
+                reward_decimals,
+            )?;
+        }
+        let reward_net = reward_received
+            .checked_sub(fee_tokens)
+            .ok_or(VaultError::MathOverflow)?;
+        require!(reward_net > 0, VaultError::ZeroAmount);
+
+        let stream = RewardStream::new(
+            reward_mint_key,
+            ctx.accounts.funder.key(),
+            start,
+            end,
+            schedule,
+            reward_net,
+            fee_tokens,
+            reward_gross,
         )?;
+        let vault = &mut ctx.accounts.vault;
+        vault.streams.push(stream);
 
-        let user = &mut ctx.accounts.user_stake;
-        user.reward_debt = reward_debt(user.amount, vault.acc_reward_per_token);
+        let (acc_reward_per_token, emitted) = stream_totals(vault);
+        emit!(RewardStreamAdded {
+            vault: vault.key(),
+            stream_index: (vault.streams.len() - 1) as u8,
+            mint: reward_mint_key,
+            funder: ctx.accounts.funder.key(),
+            start_time: start,
+            end_time: end,
+            reward_net,
+            fee: fee_tokens,
+            total_staked: vault.total_staked,
+            acc_reward_per_token,
+            emitted,
+            timestamp: now,
+        });
         Ok(())
     }
 
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64, claim_all: bool) -> Result<()> {
+    /// Add rewards to a live stream. Anyone may fund; the same fee_bps skim
+    /// applies, and what remains unemitted is re-spread over [now, end_time].
+    pub fn top_up_rewards(ctx: Context<TopUpRewards>, stream_index: u8, amount: u64) -> Result<()> {
         require!(amount > 0, VaultError::ZeroAmount);
-        let vault_key = ctx.accounts.vault.key();
-        let escrow_bump = ctx.accounts.vault.vault_escrow_bump;
-        let vault = &mut ctx.accounts.vault;
-        update_rewards(vault)?;
+        let idx = stream_index as usize;
+        let now = Clock::get()?.unix_timestamp;
+        // Rebasing at `now` would emit the paused time once the vault resumes
+        require!(
+            ctx.accounts.vault.paused_at == 0 && !ctx.accounts.global_state.paused,
+            VaultError::Paused
+        );
+        sync_global_pause(&mut ctx.accounts.vault, &ctx.accounts.global_state, now);
+        let stream = &ctx.accounts.vault.streams[idx];
+        require!(now < stream.end_time, VaultError::StreamEnded);
+        // A Stepped schedule may end in zero-weight steps; a top-up there
+        // would sit unemitted and land in one lump at end_time
+        require!(
+            remaining_schedule_weight(stream, now.max(stream.start_time)) > 0,
+            VaultError::NoEmissionsLeft
+        );
 
-        if claim_all {
-            payout_pending(
-                vault,
-                &ctx.accounts.user_stake,
-                ctx.accounts.reward_vault_ata.to_account_info(),
-                ctx.accounts.user_token_ata.to_account_info(),
-                ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.vault_reward_authority.to_account_info(),
-            )?;
-        }
+        let fee_tokens = ctx.accounts.global_state.fee_on(amount);
+        let amount_gross = amount
+            .checked_add(fee_tokens)
+            .ok_or(VaultError::MathOverflow)?;
 
-        let user_stake = &mut ctx.accounts.user_stake;
-        require!(user_stake.amount >= amount, VaultError::InsufficientStake);
+        let vault_key = ctx.accounts.vault.key();
+        let reward_bump = ctx.accounts.vault.vault_reward_bump;
+        let reward_decimals = ctx.accounts.reward_mint.decimals;
 
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault_escrow_ata.to_account_info(),
-                    to: ctx.accounts.user_token_ata.to_account_info(),
-                    authority: ctx.accounts.vault_escrow_authority.to_account_info(),
+        let reward_before = ctx.accounts.reward_vault_ata.amount;
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.reward_token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.funder_token_ata.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    to: ctx.accounts.reward_vault_ata.to_account_info(),
+                    authority: ctx.accounts.funder.to_account_info(),
                 },
-                &[&[b"vault_escrow", vault_key.as_ref(), &[escrow_bump]]],
             ),
-            amount,
+            amount_gross,
+            reward_decimals,
         )?;
+        ctx.accounts.reward_vault_ata.reload()?;
+        let received = ctx.accounts.reward_vault_ata.amount
+            .checked_sub(reward_before)
+            .ok_or(VaultError::MathOverflow)?;
 
-        user_stake.amount =
-            user_stake.amount.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
-        vault.total_staked =
-            vault.total_staked.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
-        user_stake.reward_debt = reward_debt(user_stake.amount, vault.acc_reward_per_token);
+        if !ctx.accounts.global_state.cancel_refunds_fee {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.reward_token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.reward_vault_ata.to_account_info(),
+                        mint: ctx.accounts.reward_mint.to_account_info(),
+                        to: ctx.accounts.dev_token_ata.to_account_info(),
+                        authority: ctx.accounts.vault_reward_authority.to_account_info(),
+                    },
+                    &[&[b"vault_reward", vault_key.as_ref(), &[reward_bump]]],
+                ),
+                fee_tokens,
+                reward_decimals,
+            )?;
+        }
+        let added_net = received
+            .checked_sub(fee_tokens)
+            .ok_or(VaultError::MathOverflow)?;
+        require!(added_net > 0, VaultError::ZeroAmount);
+
+        // Settle emissions up to now under the old curve, then rebase
+        let vault = &mut ctx.accounts.vault;
+        update_rewards(vault, &ctx.accounts.global_state)?;
+        let stream = &mut vault.streams[idx];
+        stream.base_time = now.max(stream.start_time);
+        stream.base_emitted = stream.emitted;
+        stream.reward_net = stream.reward_net
+            .checked_add(added_net)
+            .ok_or(VaultError::MathOverflow)?;
+        stream.reward_fee = stream.reward_fee.saturating_add(fee_tokens);
+        stream.reward_gross = stream.reward_gross.saturating_add(amount_gross);
+
+        emit!(RewardsToppedUp {
+            vault: vault_key,
+            stream_index,
+            funder: ctx.accounts.funder.key(),
+            amount_net: added_net,
+            fee: fee_tokens,
+            reward_net: stream.reward_net,
+            rate_fp: current_rate_fp(stream, stream.base_time),
+            timestamp: now,
+        });
         Ok(())
     }
 
-    pub fn close_vault(ctx: Context<CloseVaultTreasuryOnly>) -> Result<()> {
-    let vault_key = ctx.accounts.vault.key();
-    let reward_bump = ctx.accounts.vault.vault_reward_bump;
-    let escrow_bump = ctx.accounts.vault.vault_escrow_bump;
-    let vault = &mut ctx.accounts.vault;
-    let now = Clock::get()?.unix_timestamp;
+    /// Returns a stream's `unallocated` backlog (emitted while nobody was
+    /// staked) to its funder. Under BacklogPolicy::ToFirstStaker only after
+    /// end_time, when no staker can arrive to take it; under ReturnToFunder
+    /// at any time.
+    pub fn reclaim_unallocated(ctx: Context<ReclaimUnallocated>, stream_index: u8) -> Result<()> {
+        let idx = stream_index as usize;
+        let now = Clock::get()?.unix_timestamp;
+        let vault_key = ctx.accounts.vault.key();
+        let reward_bump = ctx.accounts.vault.vault_reward_bump;
+        let vault = &mut ctx.accounts.vault;
+        update_rewards(vault, &ctx.accounts.global_state)?;
+        if vault.policy.backlog == BacklogPolicy::ToFirstStaker {
+            // A deposit at exactly end_time still collects the backlog
+            require!(now > vault.end_time, VaultError::NotEnded);
+        }
 
-    // Enforce 3-year grace period after the vault term ends
-    require!(
-        now >= vault.end_time + THREE_YEARS_SECS,
-        VaultError::GraceNotElapsed
-    );
+        let stream = &mut vault.streams[idx];
+        let amount = stream.unallocated;
+        require!(amount > 0, VaultError::ZeroAmount);
 
-    // ── Sweep remaining rewards -> dev token ATA
-    let rewards_left = ctx.accounts.reward_vault_ata.amount;
-    if rewards_left > 0 {
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                ctx.accounts.reward_token_program.to_account_info(),
+                TransferChecked {
                     from: ctx.accounts.reward_vault_ata.to_account_info(),
-                    to: ctx.accounts.dev_token_ata.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    to: ctx.accounts.funder_token_ata.to_account_info(),
                     authority: ctx.accounts.vault_reward_authority.to_account_info(),
                 },
                 &[&[b"vault_reward", vault_key.as_ref(), &[reward_bump]]],
             ),
-            rewards_left,
+            amount,
+            ctx.accounts.reward_mint.decimals,
         )?;
+        stream.unallocated = 0;
+        stream.reclaimed = stream.reclaimed
+            .checked_add(amount)
+            .ok_or(VaultError::MathOverflow)?;
+
+        emit!(UnallocatedReclaimed {
+            vault: vault_key,
+            stream_index,
+            funder: stream.funder,
+            amount,
+            reclaimed: stream.reclaimed,
+            timestamp: now,
+        });
+        Ok(())
     }
 
-    // ── Sweep remaining escrow (unwithdrawn deposits) -> dev token ATA
-    let escrow_left = ctx.accounts.vault_escrow_ata.amount;
-    if escrow_left > 0 {
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault_escrow_ata.to_account_info(),
-                    to: ctx.accounts.dev_token_ata.to_account_info(),
-                    authority: ctx.accounts.vault_escrow_authority.to_account_info(),
-                },
-                &[&[b"vault_escrow", vault_key.as_ref(), &[escrow_bump]]],
-            ),
-            escrow_left,
-        )?;
-        // reset global accounting since all deposits were swept
-        vault.total_staked = 0;
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        stake(ctx, amount, 0)
     }
 
-    // Ensure both ATAs are empty before closing
-    ctx.accounts.reward_vault_ata.reload()?;
-    ctx.accounts.vault_escrow_ata.reload()?;
-    require!(ctx.accounts.reward_vault_ata.amount == 0, VaultError::RewardNotEmpty);
-    require!(ctx.accounts.vault_escrow_ata.amount == 0, VaultError::EscrowNotEmpty);
+    /// Deposit and lock the whole position until `now + lock_secs` (or its
+    /// current lock, if later). Locked stake earns a boosted reward weight.
+    pub fn deposit_locked(ctx: Context<Deposit>, amount: u64, lock_secs: i64) -> Result<()> {
+        require!(lock_secs > 0 && lock_secs <= MAX_LOCK_SECS, VaultError::InvalidLock);
+        stake(ctx, amount, lock_secs)
+    }
 
-    // Close reward ATA (lamports to dev treasury)
-    token::close_account(
-        CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            CloseAccount {
-                account: ctx.accounts.reward_vault_ata.to_account_info(),
-                destination: ctx.accounts.dev_treasury.to_account_info(),
-                authority: ctx.accounts.vault_reward_authority.to_account_info(),
-            },
-            &[&[b"vault_reward", vault_key.as_ref(), &[reward_bump]]],
-        ),
+    /// Drops the boost of a position whose lock has run out. Permissionless, so
+    /// an expired lock cannot keep earning boosted rewards just by sitting idle.
+    pub fn expire_lock(ctx: Context<ExpireLock>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let v = &mut ctx.accounts.vault;
+        let user_stake = &mut ctx.accounts.user_stake;
+        require!(now >= user_stake.lock_until, VaultError::StakeLocked);
+        if user_stake.weight == user_stake.amount {
+            return Ok(());
+        }
+
+        update_rewards(v, &ctx.accounts.global_state)?;
+        settle_pending(user_stake, v)?;
+        let base_weight = user_stake.amount;
+        set_weight(v, user_stake, base_weight)?;
+        sync_reward_debts(user_stake, v);
+
+        emit!(LockExpired {
+            vault: v.key(),
+            user: user_stake.owner,
+            weight: user_stake.weight,
+            total_weighted_stake: v.total_weighted_stake,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Pays every stream. Extra streams (index >= 1) take their accounts from
+    /// `remaining_accounts`: `[mint, reward_vault_ata, user_reward_ata, token_program]` each.
+    pub fn claim<'info>(ctx: Context<'_, '_, 'info, 'info, Claim<'info>>) -> Result<()> {
+        require_live(&ctx.accounts.global_state, &ctx.accounts.vault)?;
+        let extra = extra_stream_accounts(
+            &ctx.accounts.vault,
+            &ctx.accounts.vault_reward_authority.key(),
+            |_| ctx.accounts.user.key(),
+            ctx.remaining_accounts,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &mut ctx.accounts.vault;
+        let user = &mut ctx.accounts.user_stake;
+        let accrue_to = sync_global_pause(vault, &ctx.accounts.global_state, now);
+        prepare_claim(vault, user, accrue_to)?;
+        let owed_total = user.owed.iter().fold(0u64, |acc, o| acc.saturating_add(*o));
+
+        let mut paid = vec![payout_pending(
+            vault,
+            &mut ctx.accounts.user_stake,
+            0,
+            ctx.accounts.reward_vault_ata.to_account_info(),
+            ctx.accounts.user_reward_ata.to_account_info(),
+            &ctx.accounts.reward_mint,
+            ctx.accounts.reward_token_program.to_account_info(),
+            ctx.accounts.vault_reward_authority.to_account_info(),
+        )?];
+        for (i, acc) in extra.iter().enumerate() {
+            paid.push(payout_pending(
+                vault,
+                &mut ctx.accounts.user_stake,
+                i + 1,
+                acc.reward_vault_ata.clone(),
+                acc.counterparty_ata.clone(),
+                &acc.mint,
+                acc.token_program.clone(),
+                ctx.accounts.vault_reward_authority.to_account_info(),
+            )?);
+        }
+        // Owed but nothing could be paid: fail loudly rather than claim nothing
+        require!(
+            owed_total == 0 || paid.iter().any(|p| *p > 0),
+            VaultError::InsufficientRewards
+        );
+
+        let user = &mut ctx.accounts.user_stake;
+        sync_reward_debts(user, vault);
+
+        emit_claimed(vault, user, paid, now)
+    }
+
+    /// View: compares a stream's reward ATA balance with what the stream still
+    /// has to pay out, accrued up to now.
+    pub fn reconcile_rewards(
+        ctx: Context<ReconcileRewards>,
+        stream_index: u8,
+    ) -> Result<RewardReconciliation> {
+        let mut v = (*ctx.accounts.vault).clone();
+        update_rewards(&mut v, &ctx.accounts.global_state)?;
+        let s = &v.streams[stream_index as usize];
+
+        let unemitted = s.reward_net.saturating_sub(s.emitted);
+        // Emitted to the accumulator (or parked as unallocated) but not yet
+        // settled into anyone's owed
+        let unsettled = s
+            .emitted
+            .saturating_sub(s.paid)
+            .saturating_sub(s.reclaimed)
+            .saturating_sub(s.total_owed);
+        let required = s.reward_net.saturating_sub(s.paid).saturating_sub(s.reclaimed);
+        let balance = ctx.accounts.reward_vault_ata.amount;
+
+        Ok(RewardReconciliation {
+            balance,
+            unemitted,
+            total_owed: s.total_owed,
+            unsettled,
+            shortfall: required.saturating_sub(balance),
+            surplus: balance.saturating_sub(required),
+        })
+    }
+
+    /// View: what `user_stake` would receive from `claim` right now, using
+    /// the same accrual and settlement as the real instructions.
+    pub fn preview_pending(ctx: Context<PreviewPending>) -> Result<PendingPreview> {
+        let now = Clock::get()?.unix_timestamp;
+        let mut v = (*ctx.accounts.vault).clone();
+        let mut user = (*ctx.accounts.user_stake).clone();
+        let accrue_to = sync_global_pause(&mut v, &ctx.accounts.global_state, now);
+        update_rewards_at(&mut v, accrue_to)?;
+        settle_pending(&mut user, &mut v)?;
+
+        Ok(PendingPreview {
+            owed: user.owed[..v.streams.len()].to_vec(),
+            amount: user.amount,
+            weight: if now >= user.lock_until { user.amount } else { user.weight },
+            lock_until: user.lock_until,
+            timestamp: now,
+        })
+    }
+
+    /// View: vault accounting accrued to `at_ts` (no-op for times before the
+    /// last update), with each stream's instantaneous rate at `at_ts`.
+    pub fn preview_vault_state(
+        ctx: Context<PreviewVaultState>,
+        at_ts: i64,
+    ) -> Result<VaultStatePreview> {
+        let mut v = (*ctx.accounts.vault).clone();
+        let accrue_to = sync_global_pause(&mut v, &ctx.accounts.global_state, at_ts);
+        update_rewards_at(&mut v, accrue_to)?;
+
+        let streams = v
+            .streams
+            .iter()
+            .map(|s| {
+                let rate_fp = if v.paused_at != 0 && at_ts >= v.paused_at {
+                    0
+                } else {
+                    current_rate_fp(s, at_ts)
+                };
+                StreamPreview {
+                    mint: s.mint,
+                    emitted: s.emitted,
+                    unallocated: s.unallocated,
+                    acc_reward_per_token: s.acc_reward_per_token,
+                    rate_fp,
+                    rate_per_weight_fp: rate_fp
+                        .checked_div(v.total_weighted_stake as u128)
+                        .unwrap_or(0),
+                }
+            })
+            .collect();
+
+        Ok(VaultStatePreview {
+            timestamp: at_ts,
+            total_staked: v.total_staked,
+            total_weighted_stake: v.total_weighted_stake,
+            streams,
+        })
+    }
+
+    /// Permissionless: accrues every stream of the vault to now, so indexers
+    /// read fresh `emitted` and `acc_reward_per_token`. Pass the crank pool
+    /// to collect the tip when one is due (see crank_tip_due).
+    pub fn crank(ctx: Context<Crank>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let accounts = ctx.accounts;
+        crank_vault(
+            &mut accounts.vault,
+            &accounts.global_state,
+            accounts.crank_pool.as_mut(),
+            &accounts.cranker,
+            now,
+        )?;
+        Ok(())
+    }
+
+    /// `crank` for every vault passed in `remaining_accounts` (writable),
+    /// each tipped on its own.
+    pub fn crank_many<'info>(ctx: Context<'_, '_, 'info, 'info, CrankMany<'info>>) -> Result<()> {
+        require!(!ctx.remaining_accounts.is_empty(), VaultError::ZeroAmount);
+        let now = Clock::get()?.unix_timestamp;
+        let accounts = ctx.accounts;
+        for info in ctx.remaining_accounts.iter() {
+            let mut vault = Account::<Vault>::try_from(info)?;
+            crank_vault(
+                &mut vault,
+                &accounts.global_state,
+                accounts.crank_pool.as_mut(),
+                &accounts.cranker,
+                now,
+            )?;
+            // Persist before a repeat of the same vault is read again
+            vault.exit(&crate::ID)?;
+        }
+        Ok(())
+    }
+
+    /// With `claim_all`, extra-stream accounts are expected in `remaining_accounts`
+    /// exactly as for `claim`, and the claim is refused while paused.
+    pub fn withdraw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Withdraw<'info>>,
+        amount: u64,
+        claim_all: bool,
+    ) -> Result<()> {
+        require!(amount > 0, VaultError::ZeroAmount);
+        if claim_all {
+            require_live(&ctx.accounts.global_state, &ctx.accounts.vault)?;
+        }
+        let vault_key = ctx.accounts.vault.key();
+        let escrow_bump = ctx.accounts.vault.vault_escrow_bump;
+        let now = Clock::get()?.unix_timestamp;
+        let vault = &mut ctx.accounts.vault;
+        update_rewards(vault, &ctx.accounts.global_state)?;
+        settle_pending(&mut ctx.accounts.user_stake, vault)?;
+
+        let mut claimed = Vec::new();
+        if claim_all {
+            let extra = extra_stream_accounts(
+                vault,
+                &ctx.accounts.vault_reward_authority.key(),
+                |_| ctx.accounts.user.key(),
+                ctx.remaining_accounts,
+            )?;
+            claimed.push(payout_pending(
+                vault,
+                &mut ctx.accounts.user_stake,
+                0,
+                ctx.accounts.reward_vault_ata.to_account_info(),
+                ctx.accounts.user_reward_ata.to_account_info(),
+                &ctx.accounts.reward_mint,
+                ctx.accounts.reward_token_program.to_account_info(),
+                ctx.accounts.vault_reward_authority.to_account_info(),
+            )?);
+            for (i, acc) in extra.iter().enumerate() {
+                claimed.push(payout_pending(
+                    vault,
+                    &mut ctx.accounts.user_stake,
+                    i + 1,
+                    acc.reward_vault_ata.clone(),
+                    acc.counterparty_ata.clone(),
+                    &acc.mint,
+                    acc.token_program.clone(),
+                    ctx.accounts.vault_reward_authority.to_account_info(),
+                )?);
+            }
+        }
+
+        let user_stake = &mut ctx.accounts.user_stake;
+        let penalty = apply_withdraw(vault, user_stake, amount, now)?;
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault_escrow_ata.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.user_token_ata.to_account_info(),
+                    authority: ctx.accounts.vault_escrow_authority.to_account_info(),
+                },
+                &[&[b"vault_escrow", vault_key.as_ref(), &[escrow_bump]]],
+            ),
+            amount - penalty,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        if claim_all {
+            emit_claimed(vault, user_stake, claimed, now)?;
+        }
+        emit_withdrawn(vault, user_stake, amount, penalty, now)
+    }
+
+    /// Returns the caller's whole principal without accruing or paying any
+    /// rewards, so it needs no reward accounts and works even while paused.
+    /// It bypasses the lock and early-exit penalty only while the vault or
+    /// program is paused; otherwise they apply exactly as for withdraw, so a
+    /// boosted lock cannot be walked out of early. Rewards up to the vault's last accrual are
+    /// settled into `owed` and stay claimable; later emissions go to the
+    /// remaining stakers.
+    pub fn emergency_withdraw(ctx: Context<EmergencyWithdraw>) -> Result<()> {
+        let vault_key = ctx.accounts.vault.key();
+        let escrow_bump = ctx.accounts.vault.vault_escrow_bump;
+        let now = Clock::get()?.unix_timestamp;
+        let paused = ctx.accounts.global_state.paused || ctx.accounts.vault.paused_at != 0;
+        let vault = &mut ctx.accounts.vault;
+        let user_stake = &mut ctx.accounts.user_stake;
+        let (amount, penalty) = apply_emergency_withdraw(vault, user_stake, paused, now)?;
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault_escrow_ata.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.user_token_ata.to_account_info(),
+                    authority: ctx.accounts.vault_escrow_authority.to_account_info(),
+                },
+                &[&[b"vault_escrow", vault_key.as_ref(), &[escrow_bump]]],
+            ),
+            amount - penalty,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        emit_withdrawn(vault, user_stake, amount, penalty, now)
+    }
+
+    /// Closes the caller's own UserStake and refunds its rent. While the
+    /// vault exists the position must be empty and fully claimed; an empty
+    /// position accrues nothing, so `owed` is final. Depositing again
+    /// recreates it. Once close_vault has removed the vault nothing in the
+    /// record can be paid out any more, so it closes as it is.
+    pub fn close_user_stake(ctx: Context<CloseUserStake>) -> Result<()> {
+        let user_stake = &ctx.accounts.user_stake;
+        let info = ctx.accounts.vault.to_account_info();
+        let (amount, total_staked, (acc_reward_per_token, emitted)) =
+            if info.owner == &crate::ID && !info.data_is_empty() {
+                let vault = Vault::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+                require_settled(user_stake, &vault)?;
+                (0, vault.total_staked, stream_totals(&vault))
+            } else {
+                (user_stake.amount, 0, (Vec::new(), Vec::new()))
+            };
+
+        emit!(StakeClosed {
+            vault: info.key(),
+            user: user_stake.owner,
+            amount,
+            total_staked,
+            acc_reward_per_token,
+            emitted,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Extra-stream reward ATAs are swept and closed too; pass
+    /// `[mint, reward_vault_ata, dev_token_ata, token_program]` per extra stream.
+    /// In HoldForOwners mode every position still holding principal or owed
+    /// rewards must first go through release_position; after that nothing
+    /// left in the escrow or the reward ATAs belongs to a staker, and the
+    /// vault closes just as in SweepToTreasury mode.
+    pub fn close_vault<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseVaultTreasuryOnly<'info>>,
+    ) -> Result<()> {
+    let extra = extra_stream_accounts(
+        &ctx.accounts.vault,
+        &ctx.accounts.vault_reward_authority.key(),
+        |_| ctx.accounts.dev_treasury.key(),
+        ctx.remaining_accounts,
     )?;
+    let vault_key = ctx.accounts.vault.key();
+    let escrow_bump = ctx.accounts.vault.vault_escrow_bump;
+    let vault = &mut ctx.accounts.vault;
+    let now = Clock::get()?.unix_timestamp;
 
-    // Close escrow ATA (lamports to dev treasury)
-    token::close_account(
-        CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            CloseAccount {
-                account: ctx.accounts.vault_escrow_ata.to_account_info(),
-                destination: ctx.accounts.dev_treasury.to_account_info(),
-                authority: ctx.accounts.vault_escrow_authority.to_account_info(),
-            },
-            &[&[b"vault_escrow", vault_key.as_ref(), &[escrow_bump]]],
-        ),
+    // Accrued to now, so `emitted` is final
+    update_rewards(vault, &ctx.accounts.global_state)?;
+    // Enforce the grace period after the vault term ends (3 years unless retired)
+    require!(
+        now >= ctx.accounts.global_state.grace_ends_at(vault.end_time),
+        VaultError::GraceNotElapsed
+    );
+    if vault.policy.principal == PrincipalPolicy::HoldForOwners {
+        require!(vault.total_staked == 0, VaultError::StillStaked);
+        require!(
+            vault.streams.iter().all(|s| s.total_owed == 0),
+            VaultError::RewardsOwed
+        );
+    }
+
+    // ── Sweep remaining rewards -> dev token ATAs, primary stream first
+    let primary = ExtraStreamAccounts {
+        mint: (*ctx.accounts.reward_mint).clone(),
+        reward_vault_ata: ctx.accounts.reward_vault_ata.to_account_info(),
+        counterparty_ata: ctx.accounts.dev_token_ata.to_account_info(),
+        token_program: ctx.accounts.reward_token_program.to_account_info(),
+    };
+    sweep_reward_atas(
+        vault,
+        std::iter::once(&primary).chain(extra.iter()),
+        ctx.accounts.vault_reward_authority.to_account_info(),
+        ctx.accounts.dev_treasury.to_account_info(),
+    )?;
+
+    // ── Sweep remaining escrow -> dev stake-mint ATA: unwithdrawn deposits,
+    //    or only penalty dust once HoldForOwners positions are released
+    let escrow_left = ctx.accounts.vault_escrow_ata.amount;
+    if escrow_left > 0 {
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault_escrow_ata.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.dev_stake_ata.to_account_info(),
+                    authority: ctx.accounts.vault_escrow_authority.to_account_info(),
+                },
+                &[&[b"vault_escrow", vault_key.as_ref(), &[escrow_bump]]],
+            ),
+            escrow_left,
+            ctx.accounts.mint.decimals,
+        )?;
+        // reset global accounting since all deposits were swept
+        vault.total_staked = 0;
+        vault.total_weighted_stake = 0;
+        vault.penalty_pool = 0;
+    }
+    emit_vault_closed(vault, escrow_left, now)?;
+
+    close_escrow_and_vault(
+        &mut ctx.accounts.vault,
+        &mut ctx.accounts.vault_escrow_ata,
+        &ctx.accounts.mint,
+        ctx.accounts.vault_escrow_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.dev_treasury.to_account_info(),
+    )
+}
+
+/// Moves one position out of a HoldForOwners vault once its grace period is
+/// over, so that close_vault can close the vault whether or not the owner is
+/// still around. Principal goes to the owner's UnclaimedPrincipal PDA, where
+/// owner_reclaim pays it out at any time; rewards still owed are paid to the
+/// owner's reward ATAs, as claim would. Permissionless: the caller pays for
+/// the new accounts. Pass `[mint, reward_vault_ata, owner_ata, token_program]`
+/// per extra stream.
+pub fn release_position<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ReleasePosition<'info>>,
+) -> Result<()> {
+    require_live(&ctx.accounts.global_state, &ctx.accounts.vault)?;
+    let extra = extra_stream_accounts(
+        &ctx.accounts.vault,
+        &ctx.accounts.vault_reward_authority.key(),
+        |_| ctx.accounts.owner.key(),
+        ctx.remaining_accounts,
+    )?;
+    let vault_key = ctx.accounts.vault.key();
+    let escrow_bump = ctx.accounts.vault.vault_escrow_bump;
+    let vault = &mut ctx.accounts.vault;
+    let now = update_rewards(vault, &ctx.accounts.global_state)?;
+    require!(
+        now >= ctx.accounts.global_state.grace_ends_at(vault.end_time),
+        VaultError::GraceNotElapsed
+    );
+
+    let user = &mut ctx.accounts.user_stake;
+    // Picks up any early-exit penalty share into the principal
+    settle_pending(user, vault)?;
+    let amount = user.amount;
+    require!(
+        amount > 0 || user.owed.iter().any(|o| *o > 0),
+        VaultError::ZeroAmount
+    );
+
+    let mut paid = vec![payout_pending(
+        vault,
+        user,
+        0,
+        ctx.accounts.reward_vault_ata.to_account_info(),
+        ctx.accounts.owner_reward_ata.to_account_info(),
+        &ctx.accounts.reward_mint,
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.vault_reward_authority.to_account_info(),
+    )?];
+    for (i, acc) in extra.iter().enumerate() {
+        paid.push(payout_pending(
+            vault,
+            user,
+            i + 1,
+            acc.reward_vault_ata.clone(),
+            acc.counterparty_ata.clone(),
+            &acc.mint,
+            acc.token_program.clone(),
+            ctx.accounts.vault_reward_authority.to_account_info(),
+        )?);
+    }
+    // A shortfall would leave rewards owed and the vault unclosable
+    require!(user.owed.iter().all(|o| *o == 0), VaultError::InsufficientRewards);
+
+    let penalty = if amount > 0 { apply_withdraw(vault, user, amount, now)? } else { 0 };
+    if amount > penalty {
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault_escrow_ata.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.unclaimed_principal_ata.to_account_info(),
+                    authority: ctx.accounts.vault_escrow_authority.to_account_info(),
+                },
+                &[&[b"vault_escrow", vault_key.as_ref(), &[escrow_bump]]],
+            ),
+            amount - penalty,
+            ctx.accounts.mint.decimals,
+        )?;
+    }
+
+    let held = &mut ctx.accounts.unclaimed_principal;
+    held.vault = vault_key;
+    held.owner = user.owner;
+    held.mint = vault.mint;
+    held.amount = amount - penalty;
+    held.bump = ctx.bumps.unclaimed_principal;
+
+    emit_claimed(vault, user, paid, now)?;
+    emit!(PrincipalReleased {
+        vault: vault_key,
+        owner: held.owner,
+        amount: held.amount,
+        total_staked: vault.total_staked,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+/// Pays out the principal release_position held for the caller, then closes
+/// the UnclaimedPrincipal and its ATA, refunding their rent to the owner.
+/// Works whether or not the vault still exists.
+pub fn owner_reclaim(ctx: Context<OwnerReclaim>) -> Result<()> {
+    let held = &ctx.accounts.unclaimed_principal;
+    let signer: &[&[u8]] = &[b"unclaimed", held.vault.as_ref(), held.owner.as_ref(), &[held.bump]];
+    let amount = ctx.accounts.unclaimed_principal_ata.amount;
+    if amount > 0 {
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.unclaimed_principal_ata.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.user_token_ata.to_account_info(),
+                    authority: held.to_account_info(),
+                },
+                &[signer],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+    }
+    // Token-2022 refuses to close accounts holding withheld transfer fees
+    harvest_withheld_fees(
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.mint.to_account_info(),
+        ctx.accounts.unclaimed_principal_ata.to_account_info(),
     )?;
+    token_interface::close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: ctx.accounts.unclaimed_principal_ata.to_account_info(),
+            destination: ctx.accounts.owner.to_account_info(),
+            authority: held.to_account_info(),
+        },
+        &[signer],
+    ))?;
 
+    emit!(PrincipalReclaimed {
+        vault: held.vault,
+        owner: held.owner,
+        amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
     Ok(())
 }
-pub fn admin_close_expired_stake(_ctx: Context<AdminCloseExpiredStake>) -> Result<()> {
+
+pub fn admin_close_expired_stake(ctx: Context<AdminCloseExpiredStake>) -> Result<()> {
+    // Held principal and unclaimed rewards are the owner's, and the stream's
+    // total_owed still counts them; only settled records may be cleaned up
+    if ctx.accounts.vault.policy.principal == PrincipalPolicy::HoldForOwners {
+        require_settled(&ctx.accounts.user_stake, &ctx.accounts.vault)?;
+    }
+    let (acc_reward_per_token, emitted) = stream_totals(&ctx.accounts.vault);
+    emit!(StakeClosed {
+        vault: ctx.accounts.vault.key(),
+        user: ctx.accounts.user_stake.owner,
+        amount: ctx.accounts.user_stake.amount,
+        total_staked: ctx.accounts.vault.total_staked,
+        acc_reward_per_token,
+        emitted,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
     Ok(())
 }
 
@@ -373,139 +1665,548 @@ pub fn admin_close_expired_stake(_ctx: Context<AdminCloseExpiredStake>) -> Resul
 // Internal helpers
 // ─────────────────────────────────────────────────────────────────────────────
 
-fn update_rewards(v: &mut Account<Vault>) -> Result<()> {
-    let now = Clock::get()?.unix_timestamp;
-    let capped_now = now.min(v.end_time);
-    if capped_now <= v.last_update_time {
-        return Ok(());
-    }
+/// Accrues `v` to now, after catching it up on global pauses. Returns the
+/// time accrued to, which stops at the start of a global pause in progress.
+fn update_rewards(v: &mut Vault, state: &GlobalState) -> Result<i64> {
+    let now = sync_global_pause(v, state, Clock::get()?.unix_timestamp);
+    update_rewards_at(v, now)?;
+    Ok(now)
+}
 
-    let dt = (capped_now - v.last_update_time) as u128;
+fn set_new_vaults_disabled_on(state: &mut GlobalState, disabled: bool) -> Result<()> {
+    state.new_vaults_disabled = disabled;
+    emit_global_state_changed(state)
+}
 
-    // Accumulate fractional emission
-    let add_fp = v.rate_fp.checked_mul(dt).ok_or(VaultError::MathOverflow)?;
-    v.emission_acc_fp = v.emission_acc_fp.checked_add(add_fp).ok_or(VaultError::MathOverflow)?;
+fn require_live(state: &GlobalState, v: &Vault) -> Result<()> {
+    require!(!state.paused && v.paused_at == 0, VaultError::Paused);
+    Ok(())
+}
 
-    // Convert to integer tokens and keep remainder
-    let mut to_emit = (v.emission_acc_fp / RATE_SCALE) as u64;
-    v.emission_acc_fp %= RATE_SCALE;
+/// A record can only go once it holds no principal and nothing it earned is
+/// left to claim, whether settled into `owed` or still pending.
+fn require_settled(user: &UserStake, v: &Vault) -> Result<()> {
+    require!(user.amount == 0, VaultError::StillStaked);
+    let unclaimed = v.streams.iter().enumerate().any(|(i, s)| {
+        user.owed[i] > 0
+            || pending_rewards(user.weight, user.reward_debt[i], s.acc_reward_per_token) > 0
+    });
+    require!(!unclaimed, VaultError::RewardsOwed);
+    Ok(())
+}
 
-    // Clamp to remaining
-    let remaining = v.reward_net.saturating_sub(v.emitted);
-    if to_emit > remaining {
-        to_emit = remaining;
-    }
+/// Shared body of `deposit` / `deposit_locked`; `lock_secs == 0` leaves any
+/// existing lock as it is.
+fn stake(ctx: Context<Deposit>, amount: u64, lock_secs: i64) -> Result<()> {
+    require!(amount > 0, VaultError::ZeroAmount);
+    require_live(&ctx.accounts.global_state, &ctx.accounts.vault)?;
 
-    if to_emit > 0 {
-        if v.total_staked == 0 {
-            v.unallocated = v.unallocated.saturating_add(to_emit);
-        } else {
-            let total = (v.unallocated as u128)
-                .checked_add(to_emit as u128).ok_or(VaultError::MathOverflow)?;
-            let incr = total
-                .checked_mul(RPT_SCALE).ok_or(VaultError::MathOverflow)?
-                .checked_div(v.total_staked as u128).ok_or(VaultError::MathOverflow)?;
-            v.acc_reward_per_token = v.acc_reward_per_token
-                .checked_add(incr).ok_or(VaultError::MathOverflow)?;
-            v.unallocated = 0;
-        }
-        v.emitted = v.emitted.saturating_add(to_emit);
-    } else {
-        // NEW: If the term is over (or no fresh emission this tick), but there are stakers
-        // and unallocated rewards, flush the backlog now so late-but-still-in-term stakers
-        // receive prior empty-time emissions.
-        if v.total_staked > 0 && v.unallocated > 0 {
-            let incr = (v.unallocated as u128)
-                .checked_mul(RPT_SCALE).ok_or(VaultError::MathOverflow)?
-                .checked_div(v.total_staked as u128).ok_or(VaultError::MathOverflow)?;
-            v.acc_reward_per_token = v.acc_reward_per_token
-                .checked_add(incr).ok_or(VaultError::MathOverflow)?;
-            v.unallocated = 0;
-        }
-    }
+    let v = &mut ctx.accounts.vault;
 
-    v.last_update_time = capped_now;
-    Ok(())
-}
+    // Allow deposits only within [start_time, end_time], as moved by any
+    // global pause
+    let now = Clock::get()?.unix_timestamp;
+    update_rewards(v, &ctx.accounts.global_state)?;
+    require!(now >= v.start_time && now <= v.end_time, VaultError::VaultEnded);
 
-fn pending_rewards(user_amount: u64, reward_debt: u128, acc_rpt: u128) -> u64 {
-    if user_amount == 0 {
-        return 0;
+    let vault_key = v.key();
+
+    let escrow_before = ctx.accounts.vault_escrow_ata.amount;
+    token_interface::transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_token_ata.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.vault_escrow_ata.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        ),
+        amount,
+        ctx.accounts.mint.decimals,
+    )?;
+    // Stake what actually arrived (net of any Token-2022 transfer fee)
+    ctx.accounts.vault_escrow_ata.reload()?;
+    let amount = ctx.accounts.vault_escrow_ata.amount
+        .checked_sub(escrow_before)
+        .ok_or(VaultError::MathOverflow)?;
+    require!(amount > 0, VaultError::ZeroAmount);
+
+    let user_stake = &mut ctx.accounts.user_stake;
+    if user_stake.initialized == 0 {
+        user_stake.owner = ctx.accounts.user.key();
+        user_stake.vault = vault_key;
+        user_stake.amount = 0;
+        user_stake.weight = 0;
+        user_stake.lock_until = 0;
+        user_stake.reward_debt = [0; MAX_REWARD_STREAMS];
+        user_stake.owed = [0; MAX_REWARD_STREAMS];
+        user_stake.penalty_debt = 0;
+        user_stake.staked_at = now;
+        user_stake.initialized = 1;
     }
-    let accrued = (user_amount as u128)
-        .saturating_mul(acc_rpt)
-        .checked_div(RPT_SCALE)
-        .unwrap_or(0);
-    let diff = accrued.saturating_sub(reward_debt);
-    diff.min(u128::from(u64::MAX)) as u64
-}
+    apply_deposit(v, user_stake, amount, lock_secs, now)?;
 
-fn reward_debt(amount: u64, acc_rpt: u128) -> u128 {
-    (amount as u128)
-        .saturating_mul(acc_rpt)
-        .checked_div(RPT_SCALE)
-        .unwrap_or(0)
+    let (acc_reward_per_token, emitted) = stream_totals(v);
+    emit!(Deposited {
+        vault: vault_key,
+        user: user_stake.owner,
+        amount,
+        lock_until: user_stake.lock_until,
+        user_amount: user_stake.amount,
+        weight: user_stake.weight,
+        total_staked: v.total_staked,
+        total_weighted_stake: v.total_weighted_stake,
+        acc_reward_per_token,
+        emitted,
+        timestamp: now,
+    });
+    Ok(())
 }
 
+/// Pays out what the user is owed on one stream, as far as the reward ATA
+/// covers it; any shortfall stays in `owed`. Call `settle_pending` first.
+/// Returns the amount paid.
 #[allow(clippy::too_many_arguments)]
 fn payout_pending<'info>(
     v: &mut Account<'info, Vault>,
-    user: &Account<'info, UserStake>,
+    user: &mut UserStake,
+    stream_index: usize,
     reward_vault_ata: AccountInfo<'info>,
-    user_token_ata: AccountInfo<'info>,
+    user_reward_ata: AccountInfo<'info>,
+    reward_mint: &InterfaceAccount<'info, Mint>,
     token_program: AccountInfo<'info>,
     vault_reward_authority: AccountInfo<'info>,
-) -> Result<()> {
-    let pend = pending_rewards(user.amount, user.reward_debt, v.acc_reward_per_token);
-    if pend == 0 {
-        return Ok(());
+) -> Result<u64> {
+    let owed = user.owed[stream_index];
+    if owed == 0 {
+        return Ok(0);
     }
 
     let reward_balance = accessor::amount(&reward_vault_ata)?;
-    let to_pay = pend.min(reward_balance);
+    let to_pay = owed.min(reward_balance);
+    if to_pay == 0 {
+        return Ok(0);
+    }
 
-    token::transfer(
+    token_interface::transfer_checked(
         CpiContext::new_with_signer(
             token_program,
-            Transfer {
+            TransferChecked {
                 from: reward_vault_ata,
-                to: user_token_ata,
+                mint: reward_mint.to_account_info(),
+                to: user_reward_ata,
                 authority: vault_reward_authority,
             },
             &[&[b"vault_reward", v.key().as_ref(), &[v.vault_reward_bump]]],
         ),
         to_pay,
+        reward_mint.decimals,
     )?;
+    record_payout(v, user, stream_index, to_pay)?;
+    Ok(to_pay)
+}
+
+/// Per-stream accumulator and emitted totals, in stream order, for events.
+fn stream_totals(v: &Vault) -> (Vec<u128>, Vec<u64>) {
+    (
+        v.streams.iter().map(|s| s.acc_reward_per_token).collect(),
+        v.streams.iter().map(|s| s.emitted).collect(),
+    )
+}
+
+fn emit_claimed(v: &Account<Vault>, user: &UserStake, paid: Vec<u64>, now: i64) -> Result<()> {
+    let (acc_reward_per_token, emitted) = stream_totals(v);
+    emit!(Claimed {
+        vault: v.key(),
+        user: user.owner,
+        paid,
+        owed: user.owed[..v.streams.len()].to_vec(),
+        total_staked: v.total_staked,
+        acc_reward_per_token,
+        emitted,
+        timestamp: now,
+    });
     Ok(())
 }
 
-// ─────────────────────────────────────────────────────────────────────────────
-// Accounts
-// ─────────────────────────────────────────────────────────────────────────────
+/// Accrues `v` to `now` and pays the crank tip if one is due.
+fn crank_vault<'info>(
+    v: &mut Account<'info, Vault>,
+    state: &GlobalState,
+    pool: Option<&mut Account<'info, CrankPool>>,
+    cranker: &Signer<'info>,
+    now: i64,
+) -> Result<()> {
+    let accrue_to = sync_global_pause(v, state, now);
+    let stale_since: Vec<i64> = v.streams.iter().map(|s| s.last_update_time).collect();
+    update_rewards_at(v, accrue_to)?;
+    let tip = match pool {
+        Some(pool) if crank_tip_due(state, v, &stale_since) => {
+            let info = pool.to_account_info();
+            let tip = state.crank_tip_lamports.min(crank_pool_available(&info)?);
+            **info.try_borrow_mut_lamports()? -= tip;
+            **cranker.try_borrow_mut_lamports()? += tip;
+            pool.total_tipped = pool.total_tipped.saturating_add(tip);
+            tip
+        }
+        _ => 0,
+    };
+    // Only a paid crank counts, so an untipped one cannot push the next tip back
+    if tip > 0 {
+        v.last_cranked_at = now;
+        v.crank_tips_paid += 1;
+    }
 
+    emit!(VaultCranked {
+        vault: v.key(),
+        emitted: v.streams.iter().map(|s| s.emitted).collect(),
+        acc_reward_per_token: v.streams.iter().map(|s| s.acc_reward_per_token).collect(),
+        tip,
+        timestamp: now,
+    });
+    Ok(())
+}
 
-#[derive(Accounts)]
-#[instruction(vault_id: u64)] // allow using the instruction arg in PDA seeds
-pub struct CreateVault<'info> {
-    #[account(
-        seeds = [b"state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
+/// Tips only go to cranks that do work nobody else did: the vault has
+/// stakers, is under max_crank_tips_per_vault, and the crank
+/// moved some stream's last update forward from one at least
+/// crank_interval_secs old (`last_update_times` is from before the crank).
+/// Paused, unstarted and finished streams do not move, so never qualify.
+fn crank_tip_due(state: &GlobalState, v: &Vault, last_update_times: &[i64]) -> bool {
+    state.crank_tip_lamports > 0
+        && v.total_staked > 0
+        && v.crank_tips_paid < state.max_crank_tips_per_vault
+        && v.streams.iter().zip(last_update_times).any(|(s, &before)| {
+            s.last_update_time >= before.saturating_add(state.crank_interval_secs)
+        })
+}
 
-    #[account(mut)]
-    pub creator: Signer<'info>,
+/// Lamports the pool can pay out while staying rent-exempt.
+fn crank_pool_available(pool: &AccountInfo) -> Result<u64> {
+    let floor = Rent::get()?.minimum_balance(pool.data_len());
+    Ok(pool.lamports().saturating_sub(floor))
+}
 
-    // Fixed dev treasury (SOL receiver for the creation fee)
-    #[account(mut, address = DEV_TREASURY)]
-    pub dev_treasury: SystemAccount<'info>,
+fn emit_crank_pool_changed(
+    pool: &Account<CrankPool>,
+    by: Pubkey,
+    deposited: u64,
+    withdrawn: u64,
+) -> Result<()> {
+    emit!(CrankPoolChanged {
+        by,
+        deposited,
+        withdrawn,
+        available: crank_pool_available(&pool.to_account_info())?,
+        total_tipped: pool.total_tipped,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
 
-    pub mint: Account<'info, Mint>,
+fn emit_withdrawn(
+    v: &Account<Vault>,
+    user: &UserStake,
+    amount: u64,
+    penalty: u64,
+    now: i64,
+) -> Result<()> {
+    let (acc_reward_per_token, emitted) = stream_totals(v);
+    emit!(Withdrawn {
+        vault: v.key(),
+        user: user.owner,
+        amount,
+        penalty,
+        user_amount: user.amount,
+        total_staked: v.total_staked,
+        total_weighted_stake: v.total_weighted_stake,
+        acc_reward_per_token,
+        emitted,
+        timestamp: now,
+    });
+    Ok(())
+}
 
-    // The vault state. Bump captured in `ctx.bumps.vault`.
-    #[account(
-        init,
-        payer = creator,
+fn emit_vault_closed(v: &Account<Vault>, principal_swept: u64, now: i64) -> Result<()> {
+    let (acc_reward_per_token, emitted) = stream_totals(v);
+    emit!(VaultClosed {
+        vault: v.key(),
+        principal_swept,
+        total_staked: v.total_staked,
+        acc_reward_per_token,
+        emitted,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+/// Whether create_vault accepts the mint whose `MintRule` PDA this is. The
+/// PDA only exists for listed mints.
+fn mint_allowed(state: &GlobalState, rule: &AccountInfo) -> Result<bool> {
+    if rule.data_is_empty() {
+        return Ok(!state.mint_allowlist_only);
+    }
+    let rule = MintRule::try_deserialize(&mut &rule.try_borrow_data()?[..])?;
+    Ok(rule.listing == MintListing::Allowed)
+}
+
+fn emit_global_state_changed(state: &GlobalState) -> Result<()> {
+    emit!(GlobalStateChanged {
+        admin: state.admin,
+        new_vaults_disabled: state.new_vaults_disabled,
+        min_duration_secs: state.min_duration_secs,
+        max_duration_secs: state.max_duration_secs,
+        fee_recipient: state.fee_recipient,
+        creation_fee_lamports: state.creation_fee_lamports,
+        fee_bps: state.fee_bps,
+        pending_admin: state.pending_admin,
+        retired_at: state.retired_at,
+        retired_grace_secs: state.retired_grace_secs,
+        unretire_requested_at: state.unretire_requested_at,
+        mint_allowlist_only: state.mint_allowlist_only,
+        paused: state.paused,
+        paused_at: state.paused_at,
+        paused_secs: state.paused_secs,
+        min_start_lead_secs: state.min_start_lead_secs,
+        max_start_horizon_secs: state.max_start_horizon_secs,
+        max_start_backdate_secs: state.max_start_backdate_secs,
+        cancel_fee_lamports: state.cancel_fee_lamports,
+        crank_tip_lamports: state.crank_tip_lamports,
+        crank_interval_secs: state.crank_interval_secs,
+        cancel_refunds_fee: state.cancel_refunds_fee,
+        max_crank_tips_per_vault: state.max_crank_tips_per_vault,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// The escrow must already be drained. Harvests withheld fees, then closes
+/// the escrow ATA and the vault to `dev_treasury` (the creator on cancel).
+fn close_escrow_and_vault<'info>(
+    vault: &mut Account<'info, Vault>,
+    vault_escrow_ata: &mut InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault_escrow_authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    dev_treasury: AccountInfo<'info>,
+) -> Result<()> {
+    let vault_key = vault.key();
+    vault_escrow_ata.reload()?;
+    require!(vault_escrow_ata.amount == 0, VaultError::EscrowNotEmpty);
+
+    // Token-2022 refuses to close accounts holding withheld transfer fees
+    harvest_withheld_fees(
+        token_program.clone(),
+        mint.to_account_info(),
+        vault_escrow_ata.to_account_info(),
+    )?;
+
+    // Close escrow ATA (lamports to dev treasury)
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program,
+        CloseAccount {
+            account: vault_escrow_ata.to_account_info(),
+            destination: dev_treasury.clone(),
+            authority: vault_escrow_authority,
+        },
+        &[&[b"vault_escrow", vault_key.as_ref(), &[vault.vault_escrow_bump]]],
+    ))?;
+
+    vault.close(dev_treasury)
+}
+
+/// Sweeps each stream's reward ATA to its treasury ATA and closes it to
+/// `dev_treasury`; `accs` follows `v.streams`.
+fn sweep_reward_atas<'a, 'info: 'a>(
+    v: &Account<'info, Vault>,
+    accs: impl Iterator<Item = &'a ExtraStreamAccounts<'info>>,
+    vault_reward_authority: AccountInfo<'info>,
+    dev_treasury: AccountInfo<'info>,
+) -> Result<()> {
+    let vault_key = v.key();
+    let bump = [v.vault_reward_bump];
+    let signer: &[&[u8]] = &[b"vault_reward", vault_key.as_ref(), &bump];
+    for acc in accs.take(v.streams.len()) {
+        let left = accessor::amount(&acc.reward_vault_ata)?;
+        if left > 0 {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    acc.token_program.clone(),
+                    TransferChecked {
+                        from: acc.reward_vault_ata.clone(),
+                        mint: acc.mint.to_account_info(),
+                        to: acc.counterparty_ata.clone(),
+                        authority: vault_reward_authority.clone(),
+                    },
+                    &[signer],
+                ),
+                left,
+                acc.mint.decimals,
+            )?;
+        }
+
+        require!(accessor::amount(&acc.reward_vault_ata)? == 0, VaultError::RewardNotEmpty);
+        // Token-2022 refuses to close accounts holding withheld transfer fees
+        harvest_withheld_fees(
+            acc.token_program.clone(),
+            acc.mint.to_account_info(),
+            acc.reward_vault_ata.clone(),
+        )?;
+        token_interface::close_account(CpiContext::new_with_signer(
+            acc.token_program.clone(),
+            CloseAccount {
+                account: acc.reward_vault_ata.clone(),
+                destination: dev_treasury.clone(),
+                authority: vault_reward_authority.clone(),
+            },
+            &[signer],
+        ))?;
+    }
+    Ok(())
+}
+
+/// Accounts for one extra reward stream, taken from `remaining_accounts`.
+/// `counterparty_ata` is the user's (claim/withdraw), treasury's (close) or
+/// stream funder's (cancel) ATA.
+struct ExtraStreamAccounts<'info> {
+    mint: InterfaceAccount<'info, Mint>,
+    reward_vault_ata: AccountInfo<'info>,
+    counterparty_ata: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+}
+
+/// Validates `[mint, reward_vault_ata, counterparty_ata, token_program]` for
+/// every stream after the primary one, in stream order. `counterparty` names
+/// the owner each stream's `counterparty_ata` must have.
+fn extra_stream_accounts<'info>(
+    v: &Vault,
+    vault_reward_authority: &Pubkey,
+    counterparty: impl Fn(&RewardStream) -> Pubkey,
+    remaining: &'info [AccountInfo<'info>],
+) -> Result<Vec<ExtraStreamAccounts<'info>>> {
+    let extra = &v.streams[1..];
+    require!(
+        remaining.len() == extra.len() * STREAM_ACCOUNTS,
+        VaultError::MissingStreamAccounts
+    );
+
+    extra
+        .iter()
+        .zip(remaining.chunks(STREAM_ACCOUNTS))
+        .map(|(s, chunk)| {
+            let [mint, reward_vault_ata, counterparty_ata, token_program] = chunk else {
+                return err!(VaultError::MissingStreamAccounts);
+            };
+            let program_key = token_program.key();
+            require!(
+                program_key == anchor_spl::token::ID || program_key == anchor_spl::token_2022::ID,
+                VaultError::MissingStreamAccounts
+            );
+            require!(mint.key() == s.mint, VaultError::MissingStreamAccounts);
+            require!(
+                reward_vault_ata.key()
+                    == get_associated_token_address_with_program_id(
+                        vault_reward_authority,
+                        &s.mint,
+                        &program_key,
+                    ),
+                VaultError::BadMint
+            );
+            require!(
+                *counterparty_ata.owner == program_key
+                    && accessor::mint(counterparty_ata)? == s.mint
+                    && accessor::authority(counterparty_ata)? == counterparty(s),
+                VaultError::BadMint
+            );
+            Ok(ExtraStreamAccounts {
+                mint: InterfaceAccount::try_from(mint)?,
+                reward_vault_ata: reward_vault_ata.clone(),
+                counterparty_ata: counterparty_ata.clone(),
+                token_program: token_program.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Sweep withheld Token-2022 transfer fees from `account` into its mint.
+/// No-op for legacy SPL mints and mints without the transfer-fee extension.
+fn harvest_withheld_fees<'info>(
+    token_program: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    account: AccountInfo<'info>,
+) -> Result<()> {
+    if token_program.key() != anchor_spl::token_2022::ID
+        || get_mint_extension_data::<TransferFeeConfig>(&mint).is_err()
+    {
+        return Ok(());
+    }
+    token_interface::harvest_withheld_tokens_to_mint(
+        CpiContext::new(
+            token_program.clone(),
+            HarvestWithheldTokensToMint {
+                token_program_id: token_program,
+                mint,
+            },
+        ),
+        vec![account],
+    )
+}
+
+/// Grows `info` to `space`, topping its rent up from `payer`, and writes
+/// `value` over it. For migrating accounts from an older layout.
+fn rewrite_account<'info, T: AccountSerialize>(
+    info: &AccountInfo<'info>,
+    value: &T,
+    space: usize,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+) -> Result<()> {
+    let top_up = Rent::get()?.minimum_balance(space).saturating_sub(info.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(&payer.key(), &info.key(), top_up),
+            &[payer, info.clone(), system_program],
+        )?;
+    }
+    info.realloc(space, false)?;
+    value.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Accounts
+// ─────────────────────────────────────────────────────────────────────────────
+
+
+#[derive(Accounts)]
+// Anchor decodes #[instruction] args positionally, so every arg up to vault_id must be listed
+#[instruction(reward_net: u64, maybe_start_time: Option<i64>, vault_id: u64)]
+pub struct CreateVault<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    // Fee recipient from GlobalState (SOL receiver for the creation fee)
+    #[account(mut, address = global_state.fee_recipient @ VaultError::NotTreasury)]
+    pub dev_treasury: SystemAccount<'info>,
+
+    /// Staked token
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Reward token (may equal `mint`)
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // The vault state. Bump captured in `ctx.bumps.vault`.
+    #[account(
+        init,
+        payer = creator,
         space = 8 + Vault::SPACE,
         seeds = [
             b"vault",
@@ -533,83 +2234,741 @@ pub struct CreateVault<'info> {
     #[account(mut)]
     pub reward_vault_ata: UncheckedAccount<'info>,
 
-    /// CHECK: creator’s funding ATA (must exist)
+    /// CHECK: creator’s funding ATA in the reward mint (must exist)
     #[account(mut)]
     pub creator_token_ata: UncheckedAccount<'info>,
 
-    /// CHECK: dev treasury’s ATA (pre-created)
+    /// CHECK: dev treasury’s reward-mint ATA (pre-created)
     #[account(mut)]
     pub dev_token_ata: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     // intentionally dropping associated_token_program + rent to reduce stack
+
+    /// CHECK: `MintRule` PDA for `mint`; absent unless the mint is listed
+    #[account(seeds = [b"mint_rule", mint.key().as_ref()], bump)]
+    pub mint_rule: UncheckedAccount<'info>,
+
+    /// CHECK: `MintRule` PDA for `reward_mint`; absent unless the mint is listed
+    #[account(seeds = [b"mint_rule", reward_mint.key().as_ref()], bump)]
+    pub reward_mint_rule: UncheckedAccount<'info>,
+}
+
+
+#[derive(Accounts)]
+pub struct RescheduleStart<'info> {
+    pub creator: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(mut, has_one = creator @ VaultError::Unauthorized)]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+pub struct CancelVault<'info> {
+    /// Receives the refund and all closed-account rent; pays the cancel fee
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    /// Fee recipient; receives cancel_fee_lamports
+    #[account(mut, address = global_state.fee_recipient @ VaultError::NotTreasury)]
+    pub dev_treasury: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = creator @ VaultError::Unauthorized,
+        has_one = mint,
+        constraint = vault.streams[0].mint == reward_mint.key() @ VaultError::BadMint
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Writable so withheld Token-2022 fees can be harvested before closing
+    #[account(mut, mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Reward PDA signer
+    #[account(
+        seeds = [b"vault_reward", vault.key().as_ref()],
+        bump = vault.vault_reward_bump
+    )]
+    pub vault_reward_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Escrow PDA signer
+    #[account(
+        seeds = [b"vault_escrow", vault.key().as_ref()],
+        bump = vault.vault_escrow_bump
+    )]
+    pub vault_escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = vault_reward_authority,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = vault.mint,
+        associated_token::authority = vault_escrow_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_escrow_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Creator reward-mint ATA (receives the primary refund; create if missing)
+    #[account(
+        init_if_needed,
+        payer = creator,
+        associated_token::mint = reward_mint,
+        associated_token::authority = creator,
+        associated_token::token_program = reward_token_program
+    )]
+    pub creator_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Creator stake-mint ATA (receives escrow dust; same as `creator_token_ata` when mints match)
+    #[account(
+        init_if_needed,
+        payer = creator,
+        associated_token::mint = mint,
+        associated_token::authority = creator,
+        associated_token::token_program = token_program
+    )]
+    pub creator_stake_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddRewardStream<'info> {
+    /// Vault creator authorises every stream added to their vault
+    pub creator: Signer<'info>,
+
+    /// Pays the rewards, fee and any ATA rent (creator or a partner)
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(address = global_state.fee_recipient @ VaultError::NotTreasury)]
+    pub dev_treasury: SystemAccount<'info>,
+
+    #[account(mut, has_one = creator)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: PDA signer for reward ATAs
+    #[account(
+        seeds = [b"vault_reward", vault.key().as_ref()],
+        bump = vault.vault_reward_bump
+    )]
+    pub vault_reward_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = funder,
+        associated_token::mint = reward_mint,
+        associated_token::authority = vault_reward_authority,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = funder,
+        associated_token::token_program = reward_token_program
+    )]
+    pub funder_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = funder,
+        associated_token::mint = reward_mint,
+        associated_token::authority = dev_treasury,
+        associated_token::token_program = reward_token_program
+    )]
+    pub dev_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_index: u8)]
+pub struct TopUpRewards<'info> {
+    /// Anyone may top up; pays the rewards, fee and any ATA rent
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(address = global_state.fee_recipient @ VaultError::NotTreasury)]
+    pub dev_treasury: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = (stream_index as usize) < vault.streams.len() @ VaultError::MissingStreamAccounts,
+        constraint = vault.streams[stream_index as usize].mint == reward_mint.key() @ VaultError::BadMint
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: PDA signer for reward ATAs
+    #[account(
+        seeds = [b"vault_reward", vault.key().as_ref()],
+        bump = vault.vault_reward_bump
+    )]
+    pub vault_reward_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = vault_reward_authority,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = funder,
+        associated_token::token_program = reward_token_program
+    )]
+    pub funder_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = funder,
+        associated_token::mint = reward_mint,
+        associated_token::authority = dev_treasury,
+        associated_token::token_program = reward_token_program
+    )]
+    pub dev_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_index: u8)]
+pub struct ReclaimUnallocated<'info> {
+    /// Vault creator decides when; the tokens always go to the stream's funder
+    pub creator: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(
+        mut,
+        has_one = creator @ VaultError::Unauthorized,
+        constraint = (stream_index as usize) < vault.streams.len() @ VaultError::MissingStreamAccounts,
+        constraint = vault.streams[stream_index as usize].mint == reward_mint.key() @ VaultError::BadMint
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: PDA signer for reward ATAs
+    #[account(
+        seeds = [b"vault_reward", vault.key().as_ref()],
+        bump = vault.vault_reward_bump
+    )]
+    pub vault_reward_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = vault_reward_authority,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::token_program = reward_token_program,
+        constraint = funder_token_ata.owner == vault.streams[stream_index as usize].funder @ VaultError::BadMint
+    )]
+    pub funder_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(mut, has_one = mint)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: signer PDA for the escrow ATA
+    #[account(
+        seeds = [b"vault_escrow", vault.key().as_ref()],
+        bump = vault.vault_escrow_bump
+    )]
+
+    pub vault_escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = vault.mint,
+        associated_token::authority = vault_escrow_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_escrow_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserStake::SPACE,
+        seeds = [b"user", vault.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    #[account(
+        mut,
+        associated_token::mint = vault.mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(stream_index: u8)]
+pub struct ReconcileRewards<'info> {
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(
+        constraint = (stream_index as usize) < vault.streams.len() @ VaultError::MissingStreamAccounts
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: PDA owner of the reward ATAs
+    #[account(
+        seeds = [b"vault_reward", vault.key().as_ref()],
+        bump = vault.vault_reward_bump
+    )]
+    pub vault_reward_authority: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = reward_vault_ata.mint == vault.streams[stream_index as usize].mint @ VaultError::BadMint,
+        constraint = reward_vault_ata.owner == vault_reward_authority.key() @ VaultError::BadMint,
+    )]
+    pub reward_vault_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct PreviewPending<'info> {
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        seeds = [b"user", vault.key().as_ref(), user_stake.owner.as_ref()],
+        bump
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
+
+#[derive(Accounts)]
+pub struct PreviewVaultState<'info> {
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+pub struct Crank<'info> {
+    /// Anyone; receives the tip
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    /// Omit to crank without a tip
+    #[account(mut, seeds = [b"crank_pool"], bump = crank_pool.bump)]
+    pub crank_pool: Option<Account<'info, CrankPool>>,
+}
+
+#[derive(Accounts)]
+pub struct CrankMany<'info> {
+    /// Anyone; receives the tips
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    /// Omit to crank without tips
+    #[account(mut, seeds = [b"crank_pool"], bump = crank_pool.bump)]
+    pub crank_pool: Option<Account<'info, CrankPool>>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireLock<'info> {
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+        mut,
+        seeds = [b"user", vault.key().as_ref(), user_stake.owner.as_ref()],
+        bump
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
+
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(
+        mut,
+        constraint = vault.streams[0].mint == reward_mint.key() @ VaultError::BadMint
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"user", vault.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    /// CHECK: PDA signer for reward ATA
+    #[account(
+        seeds = [b"vault_reward", vault.key().as_ref()],
+        bump = vault.vault_reward_bump
+    )]
+    pub vault_reward_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = vault_reward_authority,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = reward_mint,
+        associated_token::authority = user,
+        associated_token::token_program = reward_token_program
+    )]
+    pub user_reward_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(
+        mut,
+        has_one = mint,
+        constraint = vault.streams[0].mint == reward_mint.key() @ VaultError::BadMint
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"user", vault.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    /// CHECK: PDA signer for escrow ATA
+    #[account(
+        seeds = [b"vault_escrow", vault.key().as_ref()],
+        bump = vault.vault_escrow_bump
+    )]
+    pub vault_escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = vault.mint,
+        associated_token::authority = vault_escrow_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_escrow_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: PDA signer for reward ATA (if claim_all)
+    #[account(
+        seeds = [b"vault_reward", vault.key().as_ref()],
+        bump = vault.vault_reward_bump
+    )]
+    pub vault_reward_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = vault_reward_authority,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Principal is returned here
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Rewards are paid here (same account as `user_token_ata` when mints match)
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = reward_mint,
+        associated_token::authority = user,
+        associated_token::token_program = reward_token_program
+    )]
+    pub user_reward_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
+#[derive(Accounts)]
+pub struct CloseUserStake<'info> {
+    /// Owner of the record; receives its rent
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: may already be closed by close_vault; decoded by hand otherwise
+    #[account(address = user_stake.vault)]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"user", vault.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
+
+#[derive(Accounts)]
+pub struct AdminCloseExpiredStake<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(mut, address = global_state.fee_recipient @ VaultError::NotTreasury)]
+    pub dev_treasury: SystemAccount<'info>,
+
+    /// Close the UserStake PDA to the dev treasury after grace
+    #[account(
+        mut,
+        close = dev_treasury,
+        constraint = user_stake.vault == vault.key(),
+        // 3-year grace after vault term, shorter once the program is retired
+        constraint = Clock::get()?.unix_timestamp >= global_state.grace_ends_at(vault.end_time) @ VaultError::GraceNotElapsed,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    /// Needed for end_time; must be typed to pass constraint above
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+pub struct CloseVaultTreasuryOnly<'info> {
+    /// Admin signs and pays for any missing treasury ATAs
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    /// Fee recipient; receives the sweeps and closed-account rent
+    #[account(mut, address = global_state.fee_recipient @ VaultError::NotTreasury)]
+    pub dev_treasury: SystemAccount<'info>,
+
+    /// Vault state is closed to treasury
+    #[account(
+        mut,
+        has_one = mint,
+        constraint = vault.streams[0].mint == reward_mint.key() @ VaultError::BadMint
+    )]
+    pub vault: Account<'info, Vault>,
+
+    // Writable so withheld Token-2022 fees can be harvested before closing
+    #[account(mut, mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
 
-#[derive(Accounts)]
-pub struct Deposit<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+    #[account(mut, mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
 
-    #[account(mut)]
-    pub vault: Account<'info, Vault>,
+    /// CHECK: Reward PDA signer
+    #[account(
+        seeds = [b"vault_reward", vault.key().as_ref()],
+        bump = vault.vault_reward_bump
+    )]
+    pub vault_reward_authority: UncheckedAccount<'info>,
 
-    /// CHECK: signer PDA for the escrow ATA
+    /// CHECK: Escrow PDA signer
     #[account(
         seeds = [b"vault_escrow", vault.key().as_ref()],
         bump = vault.vault_escrow_bump
     )]
-
     pub vault_escrow_authority: UncheckedAccount<'info>,
 
+    // Vault reward ATA (owned by reward PDA)
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = vault_reward_authority,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Vault escrow ATA (owned by escrow PDA)
     #[account(
         mut,
         associated_token::mint = vault.mint,
-        associated_token::authority = vault_escrow_authority
+        associated_token::authority = vault_escrow_authority,
+        associated_token::token_program = token_program
     )]
-    pub vault_escrow_ata: Account<'info, TokenAccount>,
+    pub vault_escrow_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    // Treasury reward-mint ATA (recipient of leftover rewards; create if missing)
     #[account(
         init_if_needed,
-        payer = user,
-        space = 8 + UserStake::SPACE,
-        seeds = [b"user", vault.key().as_ref(), user.key().as_ref()],
-        bump
+        payer = admin,
+        associated_token::mint = reward_mint,
+        associated_token::authority = dev_treasury,
+        associated_token::token_program = reward_token_program
     )]
-    pub user_stake: Account<'info, UserStake>,
+    pub dev_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    // Treasury stake-mint ATA (recipient of leftover escrow; same as `dev_token_ata` when mints match)
     #[account(
-        mut,
-        associated_token::mint = vault.mint,
-        associated_token::authority = user
+        init_if_needed,
+        payer = admin,
+        associated_token::mint = mint,
+        associated_token::authority = dev_treasury,
+        associated_token::token_program = token_program
     )]
-    pub user_token_ata: Account<'info, TokenAccount>,
+    pub dev_stake_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct Claim<'info> {
+pub struct ReleasePosition<'info> {
+    /// Anyone; pays for the owner's UnclaimedPrincipal, its ATA and any
+    /// missing reward ATA
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub payer: Signer<'info>,
 
-    #[account(mut, has_one = mint)]
-    pub vault: Account<'info, Vault>,
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
 
-    pub mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint,
+        constraint = vault.policy.principal == PrincipalPolicy::HoldForOwners @ VaultError::InvalidPolicy,
+        constraint = vault.streams[0].mint == reward_mint.key() @ VaultError::BadMint
+    )]
+    pub vault: Account<'info, Vault>,
 
     #[account(
         mut,
-        seeds = [b"user", vault.key().as_ref(), user.key().as_ref()],
+        seeds = [b"user", vault.key().as_ref(), user_stake.owner.as_ref()],
         bump
     )]
     pub user_stake: Account<'info, UserStake>,
 
-    /// CHECK: PDA signer for reward ATA
+    /// CHECK: only receives tokens and rent; pinned to the position's owner
+    #[account(address = user_stake.owner)]
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Reward PDA signer
     #[account(
         seeds = [b"vault_reward", vault.key().as_ref()],
         bump = vault.vault_reward_bump
@@ -618,34 +2977,114 @@ pub struct Claim<'info> {
 
     #[account(
         mut,
-        associated_token::mint = vault.mint,
-        associated_token::authority = vault_reward_authority
+        associated_token::mint = reward_mint,
+        associated_token::authority = vault_reward_authority,
+        associated_token::token_program = reward_token_program
     )]
-    pub reward_vault_ata: Account<'info, TokenAccount>,
+    pub reward_vault_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         init_if_needed,
-        payer = user,
+        payer = payer,
+        associated_token::mint = reward_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = reward_token_program
+    )]
+    pub owner_reward_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Escrow PDA signer
+    #[account(
+        seeds = [b"vault_escrow", vault.key().as_ref()],
+        bump = vault.vault_escrow_bump
+    )]
+    pub vault_escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
         associated_token::mint = mint,
-        associated_token::authority = user
+        associated_token::authority = vault_escrow_authority,
+        associated_token::token_program = token_program
     )]
-    pub user_token_ata: Account<'info, TokenAccount>,
+    pub vault_escrow_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + UnclaimedPrincipal::SPACE,
+        seeds = [b"unclaimed", vault.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub unclaimed_principal: Box<Account<'info, UnclaimedPrincipal>>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = unclaimed_principal,
+        associated_token::token_program = token_program
+    )]
+    pub unclaimed_principal_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct Withdraw<'info> {
+pub struct OwnerReclaim<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"unclaimed", unclaimed_principal.vault.as_ref(), owner.key().as_ref()],
+        bump = unclaimed_principal.bump,
+        has_one = owner @ VaultError::Unauthorized,
+        has_one = mint
+    )]
+    pub unclaimed_principal: Box<Account<'info, UnclaimedPrincipal>>,
+
+    // Writable so withheld Token-2022 fees can be harvested before closing
+    #[account(mut, mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = unclaimed_principal,
+        associated_token::token_program = token_program
+    )]
+    pub unclaimed_principal_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program
+    )]
+    pub user_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
+    #[account(seeds = [b"state"], bump)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
     #[account(mut, has_one = mint)]
     pub vault: Account<'info, Vault>,
 
-    pub mint: Account<'info, Mint>,
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         mut,
@@ -663,161 +3102,290 @@ pub struct Withdraw<'info> {
 
     #[account(
         mut,
-        associated_token::mint = vault.mint,
-        associated_token::authority = vault_escrow_authority
-    )]
-    pub vault_escrow_ata: Account<'info, TokenAccount>,
-
-    /// CHECK: PDA signer for reward ATA (if claim_all)
-    #[account(
-        seeds = [b"vault_reward", vault.key().as_ref()],
-        bump = vault.vault_reward_bump
-    )]
-    pub vault_reward_authority: UncheckedAccount<'info>,
-
-    #[account(
-        mut,
-        associated_token::mint = vault.mint,
-        associated_token::authority = vault_reward_authority
+        associated_token::mint = mint,
+        associated_token::authority = vault_escrow_authority,
+        associated_token::token_program = token_program
     )]
-    pub reward_vault_ata: Account<'info, TokenAccount>,
+    pub vault_escrow_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program
     )]
-    pub user_token_ata: Account<'info, TokenAccount>,
+    pub user_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct AdminCloseExpiredStake<'info> {
+pub struct InitGlobalState<'info> {
+    #[account(
+        init,
+        payer = dev_treasury,
+        space = 8 + GlobalState::SPACE,
+        seeds = [b"state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
     #[account(mut, address = DEV_TREASURY)]
     pub dev_treasury: Signer<'info>,
 
-    /// Close the UserStake PDA to the dev treasury after grace
-    #[account(
-        mut,
-        close = dev_treasury,
-        constraint = user_stake.vault == vault.key(),
-        // 3-year grace after vault term
-        constraint = Clock::get()?.unix_timestamp >= vault.end_time + THREE_YEARS_SECS @ VaultError::GraceNotElapsed,
-    )]
-    pub user_stake: Account<'info, UserStake>,
+    pub system_program: Program<'info, System>,
+}
 
-    /// Needed for end_time; must be typed to pass constraint above
-    pub vault: Account<'info, Vault>,
+#[derive(Accounts)]
+pub struct MigrateGlobalState<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// CHECK: still in the original layout, so decoded by hand; seeds and owner pin it
+    #[account(mut, seeds = [b"state"], bump, owner = crate::ID)]
+    pub global_state: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CloseVaultTreasuryOnly<'info> {
-    /// Treasury must sign and be the fixed DEV_TREASURY
-    #[account(mut, address = DEV_TREASURY)]
+pub struct MigrateVault<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    /// CHECK: still in the v1 layout, so decoded by hand; owner and discriminator pin it
+    #[account(mut, owner = crate::ID)]
+    pub vault: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserStake<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: still in the v1 layout, so decoded by hand; owner and discriminator pin it
+    #[account(mut, owner = crate::ID)]
+    pub user_stake: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetNewVaultsDisabled<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+/// Same accounts as when it was introduced; `dev_treasury` is checked
+/// against the current admin, since that role can now move.
+#[derive(Accounts)]
+pub struct DisableNewVaults<'info> {
+    #[account(mut)]
     pub dev_treasury: Signer<'info>,
 
-    /// Vault state is closed to treasury
     #[account(
         mut,
-        has_one = mint,
-        close = dev_treasury
+        seeds = [b"state"],
+        bump,
+        constraint = global_state.admin == dev_treasury.key() @ VaultError::Unauthorized
     )]
-    pub vault: Account<'info, Vault>,
+    pub global_state: Account<'info, GlobalState>,
+}
 
-    pub mint: Account<'info, Mint>,
+#[derive(Accounts)]
+pub struct SetMintAllowlistOnly<'info> {
+    pub admin: Signer<'info>,
 
-    /// CHECK: Reward PDA signer
-    #[account(
-        seeds = [b"vault_reward", vault.key().as_ref()],
-        bump = vault.vault_reward_bump
-    )]
-    pub vault_reward_authority: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct SetMintListing<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: only its key seeds the rule, so mints can be listed before they exist
+    pub mint: UncheckedAccount<'info>,
 
-    /// CHECK: Escrow PDA signer
     #[account(
-        seeds = [b"vault_escrow", vault.key().as_ref()],
-        bump = vault.vault_escrow_bump
+        init_if_needed,
+        payer = admin,
+        space = 8 + MintRule::SPACE,
+        seeds = [b"mint_rule", mint.key().as_ref()],
+        bump
     )]
-    pub vault_escrow_authority: UncheckedAccount<'info>,
+    pub mint_rule: Account<'info, MintRule>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClearMintListing<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
 
-    // Vault reward ATA (owned by reward PDA)
     #[account(
         mut,
-        associated_token::mint = vault.mint,
-        associated_token::authority = vault_reward_authority
+        close = admin,
+        seeds = [b"mint_rule", mint_rule.mint.as_ref()],
+        bump
     )]
-    pub reward_vault_ata: Account<'info, TokenAccount>,
+    pub mint_rule: Account<'info, MintRule>,
+}
+
+#[derive(Accounts)]
+pub struct SetDurationBounds<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct SetStartBounds<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub pending_admin: Signer<'info>,
 
-    // Vault escrow ATA (owned by escrow PDA)
     #[account(
         mut,
-        associated_token::mint = vault.mint,
-        associated_token::authority = vault_escrow_authority
+        seeds = [b"state"],
+        bump,
+        has_one = pending_admin @ VaultError::Unauthorized
     )]
-    pub vault_escrow_ata: Account<'info, TokenAccount>,
+    pub global_state: Account<'info, GlobalState>,
+}
 
-    // Treasury ATA (recipient of leftovers; create if missing)
-    #[account(
-        init_if_needed,
-        payer = dev_treasury,
-        associated_token::mint = mint,
-        associated_token::authority = dev_treasury
-    )]
-    pub dev_token_ata: Account<'info, TokenAccount>,
+#[derive(Accounts)]
+pub struct UpdateFees<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct RetireProgram<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct RequestUnretire<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct UnretireProgram<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct SetGlobalPause<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[derive(Accounts)]
+pub struct SetCrankTip<'info> {
+    pub admin: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
+    #[account(mut, seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
 }
 
 #[derive(Accounts)]
-pub struct InitConfig<'info> {
-    #[account(mut, address = DEV_TREASURY)]
-    pub dev_treasury: Signer<'info>,
+pub struct FundCrankPool<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
 
     #[account(
-        init,
-        payer = dev_treasury,
-        // Anchor adds the 8-byte discriminator; you add your raw struct size.
-        space = 8 + Config::SPACE,
-        seeds = [b"config"],
+        init_if_needed,
+        payer = funder,
+        space = 8 + CrankPool::SPACE,
+        seeds = [b"crank_pool"],
         bump
     )]
-    pub config: Account<'info, Config>,
+    pub crank_pool: Account<'info, CrankPool>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct InitGlobalState<'info> {
-    #[account(
-        init,
-        payer = dev_treasury,
-        space = 8 + GlobalState::SPACE,
-        seeds = [b"state"],
-        bump
-    )]
+pub struct WithdrawCrankPool<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
     pub global_state: Account<'info, GlobalState>,
 
-    #[account(mut, address = DEV_TREASURY)]
-    pub dev_treasury: Signer<'info>,
+    #[account(mut, address = global_state.fee_recipient @ VaultError::NotTreasury)]
+    pub dev_treasury: SystemAccount<'info>,
 
-    pub system_program: Program<'info, System>,
+    #[account(mut, seeds = [b"crank_pool"], bump = crank_pool.bump)]
+    pub crank_pool: Account<'info, CrankPool>,
 }
 
 #[derive(Accounts)]
-pub struct DisableNewVaults<'info> {
-    #[account(mut, address = DEV_TREASURY)]
-    pub dev_treasury: Signer<'info>,
+pub struct PauseVault<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+pub struct UnpauseVault<'info> {
+    pub admin: Signer<'info>,
 
-    #[account(mut, seeds = [b"state"], bump)]
+    #[account(seeds = [b"state"], bump, has_one = admin @ VaultError::Unauthorized)]
     pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub vault: Account<'info, Vault>,
 }
 // ─────────────────────────────────────────────────────────────────────────────
 // State
@@ -827,66 +3395,745 @@ pub struct Vault {
     // immutable params
     pub id: u64,
     pub creator: Pubkey,
+    pub mint: Pubkey, // staked token
+    pub start_time: i64,
+    pub end_time: i64,
+    pub duration_secs: i64,
+
+    // global accounting
+    pub total_staked: u64,
+    pub total_weighted_stake: u64, // sum of UserStake.weight; rewards split on this
+
+    // early-exit penalties (stake mint, held in escrow until compounded)
+    pub policy: VaultPolicy,
+    pub acc_penalty_per_token: u128,
+    pub penalty_pool: u64,
+
+    // set by pause_vault; emissions are frozen from here until unpause_vault (0 = running)
+    pub paused_at: i64,
+
+    // GlobalState.paused_secs already pushed into the schedule
+    pub global_paused_secs: i64,
+
+    // last crank / crank_many that paid a tip, and how many have
+    pub last_cranked_at: i64,
+    pub crank_tips_paid: u64,
+
+    // reward streams; [0] is the creator's, funded by create_vault
+    pub streams: Vec<RewardStream>,
+
+    // bumps & version
+    pub bump: u8,
+    pub vault_escrow_bump: u8,
+    pub vault_reward_bump: u8,
+    pub version: u8,
+}
+impl Vault {
+    // allocated for MAX_REWARD_STREAMS up front, so adding a stream never reallocs
+    pub const SPACE: usize =
+        8 + 32 + 32 + 8 + 8 + 8 + 8 + 8
+        + VaultPolicy::SPACE + 16 + 8 + 8 + 8 + 8 + 8
+        + (4 + MAX_REWARD_STREAMS * RewardStream::SPACE) + 4;
+}
+
+/// Per-vault rules fixed at creation. All-zero means no early-exit penalty.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct VaultPolicy {
+    pub early_exit_penalty_bps: u16, // cut of a withdrawal made before min_stake_secs
+    pub min_stake_secs: i64,
+    pub principal: PrincipalPolicy,
+    pub backlog: BacklogPolicy,
+}
+impl VaultPolicy {
+    pub const SPACE: usize = 2 + 8 + 1 + 1;
+}
+
+/// What close_vault does with principal nobody withdrew.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrincipalPolicy {
+    /// Keep it for its owners: release_position moves each position's
+    /// principal to an UnclaimedPrincipal PDA for owner_reclaim, and pays
+    /// out its owed rewards, before close_vault may close the vault
+    #[default]
+    HoldForOwners,
+    /// Sweep it to the treasury along with leftover rewards
+    SweepToTreasury,
+}
+
+/// Who gets what a stream emits while nobody is staked (`unallocated`).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BacklogPolicy {
+    /// Paid to whoever stakes next; reclaim_unallocated only after the term
+    /// has ended with nobody staked
+    #[default]
+    ToFirstStaker,
+    /// Kept aside for the stream's funder, who may reclaim it at any time
+    ReturnToFunder,
+}
+
+/// One emission engine. Each stream pays its own mint out of the reward
+/// PDA's ATA for that mint, over its own [start_time, end_time] window.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct RewardStream {
+    // immutable params
     pub mint: Pubkey,
+    pub funder: Pubkey,
     pub start_time: i64,
     pub end_time: i64,
     pub reward_net: u64,
     pub reward_fee: u64,
     pub reward_gross: u64,
 
-    // emission engine
+    // emission engine; top-ups rebase the curve at (base_time, base_emitted)
+    pub schedule: EmissionSchedule,
+    pub base_time: i64,
+    pub base_emitted: u64,
+    pub emitted: u64,
+
+    // accounting
+    pub last_update_time: i64,
+    pub acc_reward_per_token: u128,
+    pub unallocated: u64,
+    pub total_owed: u64, // sum of UserStake.owed for this stream
+    pub paid: u64,       // transferred out to stakers so far
+    pub reclaimed: u64,  // unallocated returned to the funder so far
+}
+impl RewardStream {
+    pub const SPACE: usize =
+        32 + 32 + 8 + 8 + 8 + 8 + 8 + EmissionSchedule::SPACE + 8 + 8 + 8 + 8 + 16 + 8 + 8 + 8 + 8;
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        mint: Pubkey,
+        funder: Pubkey,
+        start_time: i64,
+        end_time: i64,
+        schedule: EmissionSchedule,
+        reward_net: u64,
+        reward_fee: u64,
+        reward_gross: u64,
+    ) -> Result<Self> {
+        validate_schedule(&schedule, end_time - start_time)?;
+        Ok(Self {
+            mint,
+            funder,
+            start_time,
+            end_time,
+            reward_net,
+            reward_fee,
+            reward_gross,
+            schedule,
+            base_time: start_time,
+            base_emitted: 0,
+            emitted: 0,
+            last_update_time: start_time,
+            acc_reward_per_token: 0,
+            unallocated: 0,
+            total_owed: 0,
+            paid: 0,
+            reclaimed: 0,
+        })
+    }
+}
+
+/// Shape of a stream's emissions over its window. Offsets are seconds from
+/// the stream's start_time.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq)]
+pub enum EmissionSchedule {
+    /// Constant rate
+    #[default]
+    Linear,
+    /// Rate halves every `half_life_secs`
+    ExponentialDecay { half_life_secs: i64 },
+    /// Piecewise-constant rate; each step's relative `weight` holds until the next step
+    Stepped { steps: Vec<ScheduleStep> },
+    /// Nothing until `cliff_secs`, then a constant rate to the end
+    CliffLinear { cliff_secs: i64 },
+}
+impl EmissionSchedule {
+    // largest variant: Stepped with MAX_SCHEDULE_STEPS
+    pub const SPACE: usize = 1 + 4 + MAX_SCHEDULE_STEPS * ScheduleStep::SPACE;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleStep {
+    pub offset_secs: i64,
+    pub weight: u32,
+}
+impl ScheduleStep {
+    pub const SPACE: usize = 8 + 4;
+}
+
+#[account]
+pub struct UserStake {
+    pub owner: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+    pub weight: u64,     // amount plus lock boost; 1x once the lock runs out
+    pub lock_until: i64, // whole position is locked until then (0 = never locked)
+    pub reward_debt: [u128; MAX_REWARD_STREAMS], // per stream, same order as Vault.streams
+    pub owed: [u64; MAX_REWARD_STREAMS],         // settled but not yet paid out
+    pub penalty_debt: u128,
+    pub staked_at: i64, // amount-weighted average deposit time
+    pub initialized: u8,
+}
+impl UserStake {
+    pub const SPACE: usize =
+        32 + 32 + 8 + 8 + 8 + 16 * MAX_REWARD_STREAMS + 8 * MAX_REWARD_STREAMS + 16 + 8 + 1;
+}
+
+/// Vault layout before reward streams (`version == 1`): one linear emission
+/// of the staked mint. Read only by migrate_vault and the SDK decoder.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultV1 {
+    pub id: u64,
+    pub creator: Pubkey,
+    pub mint: Pubkey,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub reward_net: u64,
+    pub reward_fee: u64,
+    pub reward_gross: u64,
     pub rate_fp: u128,
     pub emission_acc_fp: u128,
     pub emitted: u64,
-
-    // global accounting
     pub last_update_time: i64,
     pub acc_reward_per_token: u128,
     pub unallocated: u64,
     pub total_staked: u64,
-
-    // bumps & version
     pub bump: u8,
     pub vault_escrow_bump: u8,
     pub vault_reward_bump: u8,
     pub version: u8,
 }
-impl Vault {
-    pub const SPACE: usize = 264;
-}
+impl VaultV1 {
+    pub const LEN: usize = 8 + 264;
+    // allocated but unused tail
+    pub const PADDING: usize = 264 - 196;
 
+    /// The same vault with its emission as stream 0, a Linear schedule
+    /// rebased at the last accrual so the rest of reward_net (and any
+    /// sub-token remainder) still lands by end_time. Stakes were all 1x and
+    /// no policy existed; `principal` is chosen by migrate_vault and the rest
+    /// of VaultPolicy is left at its defaults.
+    pub fn upgrade(self, principal: PrincipalPolicy) -> Vault {
+        let stream = RewardStream {
+            mint: self.mint,
+            funder: self.creator,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            reward_net: self.reward_net,
+            reward_fee: self.reward_fee,
+            reward_gross: self.reward_gross,
+            schedule: EmissionSchedule::Linear,
+            base_time: self.last_update_time,
+            base_emitted: self.emitted,
+            emitted: self.emitted,
+            last_update_time: self.last_update_time,
+            acc_reward_per_token: self.acc_reward_per_token,
+            unallocated: self.unallocated,
+            reclaimed: 0,
+            total_owed: 0,
+            paid: 0,
+        };
+        Vault {
+            id: self.id,
+            creator: self.creator,
+            mint: self.mint,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            duration_secs: self.end_time - self.start_time,
+            total_staked: self.total_staked,
+            total_weighted_stake: self.total_staked,
+            policy: VaultPolicy { principal, ..VaultPolicy::default() },
+            acc_penalty_per_token: 0,
+            penalty_pool: 0,
+            paused_at: 0,
+            global_paused_secs: 0,
+            last_cranked_at: 0,
+            crank_tips_paid: 0,
+            streams: vec![stream],
+            bump: self.bump,
+            vault_escrow_bump: self.vault_escrow_bump,
+            vault_reward_bump: self.vault_reward_bump,
+            version: VAULT_VERSION,
+        }
+    }
+}
 
-#[account]
-pub struct UserStake {
+/// UserStake layout before reward streams; told apart by its length.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct UserStakeV1 {
     pub owner: Pubkey,
     pub vault: Pubkey,
     pub amount: u64,
     pub reward_debt: u128,
     pub initialized: u8,
 }
-impl UserStake {
-    pub const SPACE: usize = 128;
+impl UserStakeV1 {
+    pub const LEN: usize = 8 + 128;
+
+    /// Weight was always the amount; the debt carries over to stream 0.
+    /// staked_at is left at 0, so no early-exit window applies.
+    pub fn upgrade(self) -> UserStake {
+        let mut reward_debt = [0; MAX_REWARD_STREAMS];
+        reward_debt[0] = self.reward_debt;
+        UserStake {
+            owner: self.owner,
+            vault: self.vault,
+            amount: self.amount,
+            weight: self.amount,
+            lock_until: 0,
+            reward_debt,
+            owed: [0; MAX_REWARD_STREAMS],
+            penalty_debt: 0,
+            staked_at: 0,
+            initialized: self.initialized,
+        }
+    }
+}
+
+/// Returned by `reconcile_rewards`. The stream still has to pay out
+/// `unemitted + total_owed + unsettled`; `shortfall`/`surplus` compare that
+/// with `balance`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RewardReconciliation {
+    pub balance: u64,    // reward_vault_ata.amount
+    pub unemitted: u64,  // reward_net - emitted
+    pub total_owed: u64, // settled, not yet paid
+    pub unsettled: u64,  // emitted, not yet settled to a staker
+    pub shortfall: u64,
+    pub surplus: u64,
+}
+
+/// Returned by `preview_pending`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PendingPreview {
+    pub owed: Vec<u64>,  // per stream, claimable now
+    pub amount: u64,     // principal, including any penalty share
+    pub weight: u64,     // effective weight (1x once the lock has run out)
+    pub lock_until: i64,
+    pub timestamp: i64,
+}
+
+/// Returned by `preview_vault_state`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultStatePreview {
+    pub timestamp: i64,
+    pub total_staked: u64,
+    pub total_weighted_stake: u64,
+    pub streams: Vec<StreamPreview>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct StreamPreview {
+    pub mint: Pubkey,
+    pub emitted: u64,
+    pub unallocated: u64,
+    pub acc_reward_per_token: u128,
+    pub rate_fp: u128,            // tokens/sec * RATE_SCALE
+    pub rate_per_weight_fp: u128, // rate_fp per unit of weighted stake; the APR numerator
+}
+
+/// One finished global pause, as recorded by set_global_pause.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PauseInterval {
+    pub started_at: i64,
+    pub secs: i64, // 0 = unused slot
+}
+impl PauseInterval {
+    pub const SPACE: usize = 8 + 8;
 }
 
 #[account]
 pub struct GlobalState {
     pub new_vaults_disabled: bool,
-    pub admin: Pubkey, // hardcoded as DEV_TREASURY on init
+    pub admin: Pubkey, // DEV_TREASURY on init; rotated via propose_admin/accept_admin
+    pub min_duration_secs: i64, // bounds for create_vault's duration_secs
+    pub max_duration_secs: i64,
+    pub fee_recipient: Pubkey,       // receives creation fees, token fees and sweeps
+    pub creation_fee_lamports: u64,  // SOL charged by create_vault
+    pub fee_bps: u16,                // skim on reward_net when funding a stream
+    pub pending_admin: Pubkey,       // default when no transfer is in flight
+    pub retired_at: i64,             // 0 while live; blocks create_vault when set
+    pub retired_grace_secs: i64,     // grace after retirement, see grace_ends_at
+    pub unretire_requested_at: i64,  // 0 unless an unretire is timelocked
+    pub mint_allowlist_only: bool,   // create_vault requires Allowed MintRules
+    pub paused: bool,                // blocks create_vault, deposit and claim everywhere
+    pub paused_at: i64,              // start of the current global pause (0 = running)
+    pub pause_history: [PauseInterval; MAX_PAUSE_HISTORY], // latest finished pauses, oldest first
+    pub paused_secs: i64,            // total length of finished global pauses
+    pub min_start_lead_secs: i64,    // notice a vault start must give
+    pub max_start_horizon_secs: i64, // how far out a start may be scheduled
+    pub max_start_backdate_secs: i64, // past starts within this count as now
+    pub cancel_fee_lamports: u64,     // SOL charged by cancel_vault
+    pub crank_tip_lamports: u64,      // paid from the crank pool per due crank
+    pub crank_interval_secs: i64,     // staleness a crank must clear to earn a tip
+    pub cancel_refunds_fee: bool,     // token fees wait in the reward ATA, so cancel_vault refunds them
+    pub max_crank_tips_per_vault: u64, // lifetime tips any one vault can pay out
 }
 impl GlobalState {
-    pub const SPACE: usize = 40;
+    pub const SPACE: usize = 1 + 32 + 8 + 8 + 32 + 8 + 2 + 32 + 8 + 8 + 8 + 1 + 1
+        + 8 + MAX_PAUSE_HISTORY * PauseInterval::SPACE + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1;
+
+    /// Defaults for a fresh (or migrated) state; `admin` also receives fees.
+    pub fn new(admin: Pubkey) -> Self {
+        Self {
+            new_vaults_disabled: false,
+            admin,
+            min_duration_secs: DEFAULT_MIN_DURATION_SECS,
+            max_duration_secs: DEFAULT_MAX_DURATION_SECS,
+            fee_recipient: admin,
+            creation_fee_lamports: DEFAULT_CREATION_FEE_LAMPORTS,
+            fee_bps: DEFAULT_FEE_BPS,
+            pending_admin: Pubkey::default(),
+            retired_at: 0,
+            retired_grace_secs: 0,
+            unretire_requested_at: 0,
+            mint_allowlist_only: false,
+            paused: false,
+            paused_at: 0,
+            pause_history: [PauseInterval::default(); MAX_PAUSE_HISTORY],
+            paused_secs: 0,
+            min_start_lead_secs: 0,
+            max_start_horizon_secs: DEFAULT_MAX_START_HORIZON_SECS,
+            max_start_backdate_secs: DEFAULT_MAX_START_BACKDATE_SECS,
+            cancel_fee_lamports: 0,
+            crank_tip_lamports: 0,
+            crank_interval_secs: DEFAULT_CRANK_INTERVAL_SECS,
+            cancel_refunds_fee: false,
+            max_crank_tips_per_vault: DEFAULT_MAX_CRANK_TIPS_PER_VAULT,
+        }
+    }
+
+    /// Ends the global pause in progress at `now`, adding it to paused_secs
+    /// and to the history, where it pushes out the oldest entry.
+    pub fn record_pause(&mut self, now: i64) -> Result<()> {
+        let secs = now - self.paused_at;
+        self.paused_secs = self.paused_secs.checked_add(secs).ok_or(VaultError::MathOverflow)?;
+        if secs > 0 {
+            self.pause_history.rotate_left(1);
+            self.pause_history[MAX_PAUSE_HISTORY - 1] = PauseInterval {
+                started_at: self.paused_at,
+                secs,
+            };
+        }
+        self.paused_at = 0;
+        Ok(())
+    }
+
+    /// Effective start for a vault asked to start at `start`. Backdating by up
+    /// to max_start_backdate_secs is absorbed (the vault starts now, so no
+    /// emissions are owed for elapsed time); otherwise the start must fall
+    /// between min_start_lead_secs and max_start_horizon_secs from now.
+    pub fn resolve_start(&self, start: i64, now: i64) -> Result<i64> {
+        require!(
+            start >= now.saturating_sub(self.max_start_backdate_secs),
+            VaultError::InvalidStartTime
+        );
+        let start = start.max(now);
+        require!(
+            start >= now.saturating_add(self.min_start_lead_secs)
+                && start <= now.saturating_add(self.max_start_horizon_secs),
+            VaultError::InvalidStartTime
+        );
+        Ok(start)
+    }
+
+    /// When a vault ending at `end_time` may be closed by the admin.
+    pub fn grace_ends_at(&self, end_time: i64) -> i64 {
+        let normal = end_time.saturating_add(THREE_YEARS_SECS);
+        if self.retired_at == 0 {
+            return normal;
+        }
+        normal.min(end_time.max(self.retired_at).saturating_add(self.retired_grace_secs))
+    }
+
+    /// Token fee on top of `amount` net rewards.
+    pub fn fee_on(&self, amount: u64) -> u64 {
+        amount.saturating_mul(self.fee_bps as u64) / 10_000
+    }
+}
+
+/// Admin's verdict on a mint for create_vault; PDA seeds `[b"mint_rule", mint]`.
+#[account]
+pub struct MintRule {
+    pub mint: Pubkey,
+    pub listing: MintListing,
+}
+impl MintRule {
+    pub const SPACE: usize = 32 + 1;
 }
 
+/// Lamports above rent pay crank tips; PDA seeds `[b"crank_pool"]`.
 #[account]
-pub struct Config {
-    pub new_vaults_disabled: u8, // 1 byte
-    pub retired_at: i64,         // 8 bytes
+pub struct CrankPool {
+    pub bump: u8,
+    pub total_tipped: u64,
 }
-impl Config {
-    // raw struct size ONLY (no discriminator here)
+impl CrankPool {
     pub const SPACE: usize = 1 + 8;
 }
 
+/// Principal release_position moved out of a HoldForOwners vault, held in
+/// this PDA's ATA until owner_reclaim; seeds `[b"unclaimed", vault, owner]`.
+#[account]
+pub struct UnclaimedPrincipal {
+    pub vault: Pubkey,
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64, // as sent from escrow, before any Token-2022 fee
+    pub bump: u8,
+}
+impl UnclaimedPrincipal {
+    pub const SPACE: usize = 32 + 32 + 32 + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MintListing {
+    /// Accepted even when the allowlist is enforced
+    Allowed,
+    /// Refused as a staking or reward mint
+    Denied,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Events
+// ─────────────────────────────────────────────────────────────────────────────
+// Vault/stake events carry per-stream `acc_reward_per_token` and `emitted`
+// after the transition, in stream order.
+
+#[event]
+pub struct VaultCreated {
+    pub vault: Pubkey,
+    pub vault_id: u64,
+    pub creator: Pubkey,
+    pub mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub reward_net: u64,
+    pub fee: u64,
+    pub total_staked: u64,
+    pub acc_reward_per_token: Vec<u128>,
+    pub emitted: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardStreamAdded {
+    pub vault: Pubkey,
+    pub stream_index: u8,
+    pub mint: Pubkey,
+    pub funder: Pubkey,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub reward_net: u64,
+    pub fee: u64,
+    pub total_staked: u64,
+    pub acc_reward_per_token: Vec<u128>,
+    pub emitted: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Deposited {
+    pub vault: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64, // received, net of transfer fees
+    pub lock_until: i64,
+    pub user_amount: u64,
+    pub weight: u64,
+    pub total_staked: u64,
+    pub total_weighted_stake: u64,
+    pub acc_reward_per_token: Vec<u128>,
+    pub emitted: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Withdrawn {
+    pub vault: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,  // principal removed, including the penalty
+    pub penalty: u64, // kept in escrow for the remaining stakers
+    pub user_amount: u64,
+    pub total_staked: u64,
+    pub total_weighted_stake: u64,
+    pub acc_reward_per_token: Vec<u128>,
+    pub emitted: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Claimed {
+    pub vault: Pubkey,
+    pub user: Pubkey,
+    pub paid: Vec<u64>, // per stream
+    pub owed: Vec<u64>, // per stream, still unpaid
+    pub total_staked: u64,
+    pub acc_reward_per_token: Vec<u128>,
+    pub emitted: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LockExpired {
+    pub vault: Pubkey,
+    pub user: Pubkey,
+    pub weight: u64,
+    pub total_weighted_stake: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultClosed {
+    pub vault: Pubkey,
+    pub principal_swept: u64, // only penalty dust under HoldForOwners
+    pub total_staked: u64,
+    pub acc_reward_per_token: Vec<u128>,
+    pub emitted: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakeClosed {
+    pub vault: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub total_staked: u64,
+    pub acc_reward_per_token: Vec<u128>, // empty once close_vault has removed the vault
+    pub emitted: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PrincipalReleased {
+    pub vault: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64, // moved to the owner's UnclaimedPrincipal
+    pub total_staked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PrincipalReclaimed {
+    pub vault: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GlobalStateChanged {
+    pub admin: Pubkey,
+    pub new_vaults_disabled: bool,
+    pub min_duration_secs: i64,
+    pub max_duration_secs: i64,
+    pub fee_recipient: Pubkey,
+    pub creation_fee_lamports: u64,
+    pub fee_bps: u16,
+    pub pending_admin: Pubkey,
+    pub retired_at: i64,
+    pub retired_grace_secs: i64,
+    pub unretire_requested_at: i64,
+    pub mint_allowlist_only: bool,
+    pub paused: bool,
+    pub paused_at: i64,
+    pub paused_secs: i64,
+    pub min_start_lead_secs: i64,
+    pub max_start_horizon_secs: i64,
+    pub max_start_backdate_secs: i64,
+    pub cancel_fee_lamports: u64,
+    pub crank_tip_lamports: u64,
+    pub crank_interval_secs: i64,
+    pub cancel_refunds_fee: bool,
+    pub max_crank_tips_per_vault: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultPauseChanged {
+    pub vault: Pubkey,
+    pub paused: bool,
+    pub start_time: i64,
+    pub end_time: i64, // shifted by the pause length on unpause
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultRescheduled {
+    pub vault: Pubkey,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultCancelled {
+    pub vault: Pubkey,
+    pub creator: Pubkey,
+    pub refunded: Vec<u64>, // per stream, to its funder
+    pub escrow_swept: u64,
+    pub cancel_fee_lamports: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MintListingChanged {
+    pub mint: Pubkey,
+    pub listing: Option<MintListing>, // None once cleared
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsToppedUp {
+    pub vault: Pubkey,
+    pub stream_index: u8,
+    pub funder: Pubkey,
+    pub amount_net: u64,
+    pub fee: u64,
+    pub reward_net: u64, // stream total after the top-up
+    pub rate_fp: u128,   // new rate, tokens/sec * RATE_SCALE
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey, // Vault or UserStake
+    pub version: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultCranked {
+    pub vault: Pubkey,
+    pub emitted: Vec<u64>,
+    pub acc_reward_per_token: Vec<u128>,
+    pub tip: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CrankPoolChanged {
+    pub by: Pubkey,
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub available: u64, // payable above rent
+    pub total_tipped: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnallocatedReclaimed {
+    pub vault: Pubkey,
+    pub stream_index: u8,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub reclaimed: u64, // stream total so far
+    pub timestamp: i64,
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // Errors
 // ─────────────────────────────────────────────────────────────────────────────
@@ -906,11 +4153,11 @@ pub enum VaultError {
     InsufficientStake,
     #[msg("Reward token account is not empty after sweep")]
     RewardNotEmpty,
-    #[msg("Provided token account does not match vault mint")]
+    #[msg("Provided token account does not match the expected vault mint")]
     BadMint,
-     #[msg("Program is retired. New vaults are disabled.")]
+    #[msg("Program is retired. New vaults are disabled.")]
     ProgramRetired,
-    #[msg("Only dev treasury may retire.")]
+    #[msg("Account is not the configured fee recipient.")]
     NotTreasury,
     #[msg("Already retired.")]
     AlreadyRetired,
@@ -922,5 +4169,53 @@ pub enum VaultError {
     Unauthorized,
     #[msg("Deposits are closed for this vault")]
     VaultEnded,
+    #[msg("Vault already has the maximum number of reward streams")]
+    TooManyRewardStreams,
+    #[msg("A reward stream for this mint already exists")]
+    DuplicateRewardMint,
+    #[msg("Reward stream window must lie within the vault term")]
+    InvalidStreamWindow,
+    #[msg("Reward stream accounts missing or out of order")]
+    MissingStreamAccounts,
+    #[msg("Account is already in the current layout")]
+    AlreadyMigrated,
+    #[msg("Vault duration is outside the allowed range")]
+    InvalidDuration,
+    #[msg("Invalid emission schedule")]
+    InvalidSchedule,
+    #[msg("Reward stream has already ended")]
+    StreamEnded,
+    #[msg("Lock duration is zero, too long, or runs past the vault end")]
+    InvalidLock,
+    #[msg("Stake is still locked")]
+    StakeLocked,
+    #[msg("Invalid vault policy")]
+    InvalidPolicy,
+    #[msg("Reward account cannot cover any of what is owed")]
+    InsufficientRewards,
+    #[msg("Fee must not exceed MAX_FEE_BPS and needs a recipient")]
+    InvalidFee,
+    #[msg("Program is not retired")]
+    NotRetired,
+    #[msg("Unretire timelock has not elapsed")]
+    TimelockNotElapsed,
+    #[msg("Mint is not allowed for new vaults")]
+    MintNotAllowed,
+    #[msg("Paused by the admin")]
+    Paused,
+    #[msg("Vault is not paused")]
+    NotPaused,
+    #[msg("Start time is backdated, too soon or too far out")]
+    InvalidStartTime,
+    #[msg("Vault has already started")]
+    AlreadyStarted,
+    #[msg("Vault has stake or has emitted rewards")]
+    CancelNotAllowed,
+    #[msg("Unclaimed rewards are still owed")]
+    RewardsOwed,
+    #[msg("Crank pool cannot cover that")]
+    InsufficientCrankPool,
+    #[msg("Stream's schedule has no emissions left to spread a top-up over")]
+    NoEmissionsLeft,
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,477 @@
+//! Pure reward accounting, shared by the on-chain program and the off-chain
+//! SDK. Nothing here reads sysvars or touches accounts; callers pass `now`.
+
+use anchor_lang::prelude::*;
+
+use crate::{
+    BacklogPolicy, EmissionSchedule, GlobalState, RewardStream, UserStake, Vault, VaultError,
+    MAX_HALVINGS, MAX_LOCK_BOOST_BPS, MAX_LOCK_SECS, MAX_PAUSE_HISTORY, MAX_SCHEDULE_STEPS,
+    RATE_SCALE, RPT_SCALE,
+};
+
+/// Accrues every stream up to `now`. Views call this on a copy with a
+/// caller-chosen timestamp. A paused vault accrues nothing past `paused_at`.
+pub fn update_rewards_at(v: &mut Vault, now: i64) -> Result<()> {
+    let now = if v.paused_at != 0 { now.min(v.paused_at) } else { now };
+    let total_weight = v.total_weighted_stake;
+    let flush_backlog = v.policy.backlog == BacklogPolicy::ToFirstStaker;
+    for s in v.streams.iter_mut() {
+        update_stream(s, total_weight, now, flush_backlog)?;
+    }
+    Ok(())
+}
+
+/// Moves everything still ahead of `from` by `secs` (later if positive), so
+/// a paused schedule resumes where it stopped, or an unstarted one starts
+/// elsewhere. Streams are shifted as a whole (start, end, rebase point and
+/// last update), which keeps `emitted_at` continuous; streams already over
+/// at `from` are left alone.
+pub fn shift_schedule(v: &mut Vault, from: i64, secs: i64) {
+    for s in v.streams.iter_mut().filter(|s| s.end_time > from) {
+        s.start_time += secs;
+        s.end_time += secs;
+        s.base_time += secs;
+        s.last_update_time += secs;
+    }
+    if v.start_time > from {
+        v.start_time += secs;
+    }
+    if v.end_time > from {
+        v.end_time += secs;
+    }
+}
+
+/// Catches `v` up on global pauses and returns the time it may accrue to,
+/// which stops at the start of a global pause in progress. Each finished
+/// pause the vault has not seen yet pushes the rest of its schedule back
+/// from that pause's own start, oldest first. A vault that missed more than
+/// the history holds takes the excess at the oldest recorded start. A vault
+/// paused on its own only marks them seen, since unpause_vault shifts it by
+/// the whole time it was stopped.
+pub fn sync_global_pause(v: &mut Vault, state: &GlobalState, now: i64) -> i64 {
+    let mut unseen = state.paused_secs - v.global_paused_secs;
+    if unseen > 0 && v.paused_at == 0 {
+        // Walk back from the latest pause until the unseen time is covered
+        let mut first = MAX_PAUSE_HISTORY;
+        while unseen > 0 && first > 0 && state.pause_history[first - 1].secs > 0 {
+            first -= 1;
+            unseen -= state.pause_history[first].secs;
+        }
+        for (i, p) in state.pause_history[first..].iter().enumerate() {
+            // The oldest also carries pauses that fell out of the history
+            let secs = if i == 0 { p.secs + unseen } else { p.secs };
+            shift_schedule(v, p.started_at, secs);
+        }
+    }
+    v.global_paused_secs = state.paused_secs;
+    if state.paused_at != 0 {
+        now.min(state.paused_at)
+    } else {
+        now
+    }
+}
+
+/// `flush_backlog` pays `unallocated` out to the current stakers; without it
+/// the backlog stays parked for reclaim_unallocated.
+pub fn update_stream(
+    v: &mut RewardStream,
+    total_weight: u64,
+    now: i64,
+    flush_backlog: bool,
+) -> Result<()> {
+    let capped_now = now.min(v.end_time);
+    if capped_now <= v.last_update_time
+        && (total_weight == 0 || v.unallocated == 0 || !flush_backlog)
+    {
+        return Ok(());
+    }
+
+    // The schedule gives a cumulative target, so emissions over any dt are
+    // exact and never exceed reward_net; no per-tick remainder to carry.
+    let to_emit = if capped_now > v.last_update_time {
+        emitted_at(v, capped_now)?.saturating_sub(v.emitted)
+    } else {
+        0
+    };
+
+    if to_emit > 0 {
+        if total_weight == 0 {
+            v.unallocated = v.unallocated.saturating_add(to_emit);
+        } else {
+            let backlog = if flush_backlog { v.unallocated } else { 0 };
+            let total = (backlog as u128)
+                .checked_add(to_emit as u128).ok_or(VaultError::MathOverflow)?;
+            let incr = total
+                .checked_mul(RPT_SCALE).ok_or(VaultError::MathOverflow)?
+                .checked_div(total_weight as u128).ok_or(VaultError::MathOverflow)?;
+            v.acc_reward_per_token = v.acc_reward_per_token
+                .checked_add(incr).ok_or(VaultError::MathOverflow)?;
+            v.unallocated -= backlog;
+        }
+        v.emitted = v.emitted.saturating_add(to_emit);
+    } else {
+        // NEW: If the term is over (or no fresh emission this tick), but there are stakers
+        // and unallocated rewards, flush the backlog now so late-but-still-in-term stakers
+        // receive prior empty-time emissions. This includes a first deposit made
+        // exactly at end_time, after which capped_now never moves again.
+        if flush_backlog && total_weight > 0 && v.unallocated > 0 {
+            let incr = (v.unallocated as u128)
+                .checked_mul(RPT_SCALE).ok_or(VaultError::MathOverflow)?
+                .checked_div(total_weight as u128).ok_or(VaultError::MathOverflow)?;
+            v.acc_reward_per_token = v.acc_reward_per_token
+                .checked_add(incr).ok_or(VaultError::MathOverflow)?;
+            v.unallocated = 0;
+        }
+    }
+
+    v.last_update_time = v.last_update_time.max(capped_now);
+    Ok(())
+}
+
+/// Total a stream should have emitted by `at`. Integer split of what was
+/// unemitted at `base_time` along the rest of the schedule curve; lands on
+/// exactly reward_net at end_time.
+pub fn emitted_at(s: &RewardStream, at: i64) -> Result<u64> {
+    if at >= s.end_time {
+        return Ok(s.reward_net);
+    }
+    let duration = s.end_time - s.start_time;
+    let base_w = schedule_weight(&s.schedule, s.base_time - s.start_time, duration);
+    let remaining_w = schedule_weight(&s.schedule, duration, duration) - base_w;
+    if remaining_w == 0 {
+        return Ok(s.base_emitted);
+    }
+    let elapsed_w = schedule_weight(&s.schedule, at - s.start_time, duration).saturating_sub(base_w);
+    let emitted = ((s.reward_net - s.base_emitted) as u128)
+        .checked_mul(elapsed_w).ok_or(VaultError::MathOverflow)?
+        / remaining_w;
+    Ok(s.base_emitted + emitted as u64)
+}
+
+/// Schedule weight still ahead of `from` (from the stream's start at the
+/// earliest); zero once only zero-weight steps are left.
+pub fn remaining_schedule_weight(s: &RewardStream, from: i64) -> u128 {
+    let duration = s.end_time - s.start_time;
+    schedule_weight(&s.schedule, duration, duration)
+        - schedule_weight(&s.schedule, from - s.start_time, duration)
+}
+
+/// Instantaneous emission rate at `at`, in tokens/sec * RATE_SCALE (for events).
+pub fn current_rate_fp(s: &RewardStream, at: i64) -> u128 {
+    if at < s.start_time || at >= s.end_time {
+        return 0;
+    }
+    let duration = s.end_time - s.start_time;
+    let t = at - s.start_time;
+    let remaining_w = schedule_weight(&s.schedule, duration, duration)
+        - schedule_weight(&s.schedule, s.base_time - s.start_time, duration);
+    if remaining_w == 0 {
+        return 0;
+    }
+    // d/dt of the weight curve at t
+    let slope = schedule_weight(&s.schedule, t + 1, duration) - schedule_weight(&s.schedule, t, duration);
+    let scaled = ((s.reward_net - s.base_emitted) as u128) * slope;
+    scaled
+        .checked_mul(RATE_SCALE)
+        .map(|x| x / remaining_w)
+        .unwrap_or_else(|| (scaled / remaining_w).saturating_mul(RATE_SCALE))
+}
+
+/// Cumulative emission weight of the first `t` seconds of a `duration`-long
+/// window. Only ratios of weights matter; every variant stays below 2^64.
+pub fn schedule_weight(schedule: &EmissionSchedule, t: i64, duration: i64) -> u128 {
+    let t = t.clamp(0, duration);
+    match schedule {
+        EmissionSchedule::Linear => t as u128,
+        EmissionSchedule::ExponentialDecay { half_life_secs } => {
+            // Period p (of n) runs at rate 2^(n-1-p), so the last period has rate 1
+            let k = *half_life_secs;
+            let n = ((duration + k - 1) / k) as u32;
+            let p = ((t / k) as u32).min(n);
+            let full = (k as u128) * ((1u128 << n) - (1u128 << (n - p)));
+            if p == n {
+                full
+            } else {
+                full + (((t - p as i64 * k) as u128) << (n - 1 - p))
+            }
+        }
+        EmissionSchedule::Stepped { steps } => steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let step_end = steps.get(i + 1).map_or(duration, |next| next.offset_secs);
+                let covered = t.min(step_end) - step.offset_secs;
+                if covered > 0 {
+                    covered as u128 * step.weight as u128
+                } else {
+                    0
+                }
+            })
+            .sum(),
+        EmissionSchedule::CliffLinear { cliff_secs } => (t - cliff_secs).max(0) as u128,
+    }
+}
+
+pub fn validate_schedule(schedule: &EmissionSchedule, duration: i64) -> Result<()> {
+    require!(duration > 0, VaultError::InvalidStreamWindow);
+    match schedule {
+        EmissionSchedule::Linear => {}
+        EmissionSchedule::ExponentialDecay { half_life_secs } => {
+            require!(
+                *half_life_secs > 0
+                    && (duration + half_life_secs - 1) / half_life_secs <= MAX_HALVINGS,
+                VaultError::InvalidSchedule
+            );
+        }
+        EmissionSchedule::Stepped { steps } => {
+            require!(
+                !steps.is_empty() && steps.len() <= MAX_SCHEDULE_STEPS,
+                VaultError::InvalidSchedule
+            );
+            require!(
+                steps[0].offset_secs == 0
+                    && steps.windows(2).all(|w| w[0].offset_secs < w[1].offset_secs)
+                    && steps[steps.len() - 1].offset_secs < duration,
+                VaultError::InvalidSchedule
+            );
+            require!(steps.iter().any(|s| s.weight > 0), VaultError::InvalidSchedule);
+        }
+        EmissionSchedule::CliffLinear { cliff_secs } => {
+            require!(
+                *cliff_secs >= 0 && *cliff_secs < duration,
+                VaultError::InvalidSchedule
+            );
+        }
+    }
+    Ok(())
+}
+
+pub fn pending_rewards(weight: u64, reward_debt: u128, acc_rpt: u128) -> u64 {
+    if weight == 0 {
+        return 0;
+    }
+    let accrued = (weight as u128)
+        .saturating_mul(acc_rpt)
+        .checked_div(RPT_SCALE)
+        .unwrap_or(0);
+    let diff = accrued.saturating_sub(reward_debt);
+    diff.min(u128::from(u64::MAX)) as u64
+}
+
+/// Rounded up, so that the floored accrual in `pending_rewards` can never
+/// credit a position with dust it did not earn; otherwise every re-sync could
+/// hand out one extra unit and payouts could drift past `emitted`.
+pub fn reward_debt(weight: u64, acc_rpt: u128) -> u128 {
+    (weight as u128)
+        .saturating_mul(acc_rpt)
+        .div_ceil(RPT_SCALE)
+}
+
+pub fn sync_reward_debts(user: &mut UserStake, v: &Vault) {
+    for (debt, s) in user.reward_debt.iter_mut().zip(v.streams.iter()) {
+        *debt = reward_debt(user.weight, s.acc_reward_per_token);
+    }
+    user.penalty_debt = reward_debt(user.weight, v.acc_penalty_per_token);
+}
+
+/// Moves each stream's pending rewards into `owed` and re-syncs debts, so the
+/// position's weight can change without forfeiting what it already earned.
+/// The position's share of early-exit penalties is compounded into its stake.
+pub fn settle_pending(user: &mut UserStake, v: &mut Vault) -> Result<()> {
+    for (i, s) in v.streams.iter_mut().enumerate() {
+        let pend = pending_rewards(user.weight, user.reward_debt[i], s.acc_reward_per_token);
+        user.owed[i] = user.owed[i].checked_add(pend).ok_or(VaultError::MathOverflow)?;
+        s.total_owed = s.total_owed.checked_add(pend).ok_or(VaultError::MathOverflow)?;
+    }
+
+    settle_penalty_share(user, v)?;
+    sync_reward_debts(user, v);
+    Ok(())
+}
+
+/// Compounds the position's share of early-exit penalties into its stake (at
+/// 1x weight). The tokens already sit in escrow; they just move from the pool
+/// to the position. Debts are left for the caller to re-sync.
+pub fn settle_penalty_share(user: &mut UserStake, v: &mut Vault) -> Result<()> {
+    let share = pending_rewards(user.weight, user.penalty_debt, v.acc_penalty_per_token)
+        .min(v.penalty_pool);
+    if share > 0 {
+        v.penalty_pool -= share;
+        v.total_staked = v.total_staked.checked_add(share).ok_or(VaultError::MathOverflow)?;
+        user.amount = user.amount.checked_add(share).ok_or(VaultError::MathOverflow)?;
+        let weight = user.weight.checked_add(share).ok_or(VaultError::MathOverflow)?;
+        set_weight(v, user, weight)?;
+    }
+    Ok(())
+}
+
+/// Swaps a position's weight in the vault total. Pending rewards must have
+/// been settled at the old weight first.
+pub fn set_weight(v: &mut Vault, user: &mut UserStake, weight: u64) -> Result<()> {
+    v.total_weighted_stake = v.total_weighted_stake
+        .checked_sub(user.weight).ok_or(VaultError::MathOverflow)?
+        .checked_add(weight).ok_or(VaultError::MathOverflow)?;
+    user.weight = weight;
+    Ok(())
+}
+
+/// Spreads an early-exit penalty over the current total weight.
+pub fn distribute_penalty(v: &mut Vault, penalty: u64) -> Result<()> {
+    if penalty == 0 || v.total_weighted_stake == 0 {
+        return Ok(());
+    }
+    let incr = (penalty as u128)
+        .checked_mul(RPT_SCALE).ok_or(VaultError::MathOverflow)?
+        .checked_div(v.total_weighted_stake as u128).ok_or(VaultError::MathOverflow)?;
+    v.acc_penalty_per_token = v.acc_penalty_per_token
+        .checked_add(incr).ok_or(VaultError::MathOverflow)?;
+    v.penalty_pool = v.penalty_pool.checked_add(penalty).ok_or(VaultError::MathOverflow)?;
+    Ok(())
+}
+
+/// `amount` boosted linearly by the lock time left, up to MAX_LOCK_BOOST_BPS
+/// at MAX_LOCK_SECS.
+pub fn locked_weight(amount: u64, remaining_secs: i64) -> u64 {
+    let remaining = remaining_secs.clamp(0, MAX_LOCK_SECS) as u128;
+    let boost_bps = MAX_LOCK_BOOST_BPS as u128 * remaining / MAX_LOCK_SECS as u128;
+    let weight = (amount as u128) * (10_000 + boost_bps) / 10_000;
+    weight.min(u128::from(u64::MAX)) as u64
+}
+
+// Position transitions. The instruction handlers move tokens and call these
+// for the state change, so off-chain replays apply exactly the same steps.
+// Callers accrue the vault (`update_rewards_at`) first where noted.
+
+/// Adds `amount` (as received) to a position, locking it until `now +
+/// lock_secs` or its current lock, if later; `lock_secs == 0` leaves any
+/// lock as it is. The vault must be accrued to `now`.
+pub fn apply_deposit(
+    v: &mut Vault,
+    user: &mut UserStake,
+    amount: u64,
+    lock_secs: i64,
+    now: i64,
+) -> Result<()> {
+    let lock_until = if lock_secs > 0 {
+        user.lock_until.max(now + lock_secs)
+    } else {
+        user.lock_until
+    };
+    require!(lock_secs == 0 || lock_until <= v.end_time, VaultError::InvalidLock);
+
+    // Bank what accrued at the old weight before it changes
+    settle_pending(user, v)?;
+    let remaining = (lock_until - now).max(0);
+
+    // Existing stake keeps its boost while locked and is re-boosted if the
+    // lock was extended; new stake is boosted for the time left on the lock.
+    let mut weight = if now >= user.lock_until { user.amount } else { user.weight };
+    if lock_until > user.lock_until {
+        weight = weight.max(locked_weight(user.amount, remaining));
+    }
+    weight = weight
+        .checked_add(locked_weight(amount, remaining))
+        .ok_or(VaultError::MathOverflow)?;
+
+    // min_stake_secs runs from the amount-weighted average deposit time
+    let total_amount = (user.amount as i128) + (amount as i128);
+    user.staked_at = ((user.amount as i128) * (user.staked_at as i128)
+        + (amount as i128) * (now as i128))
+        .checked_div(total_amount)
+        .ok_or(VaultError::MathOverflow)? as i64;
+
+    user.lock_until = lock_until;
+    v.total_staked = v.total_staked.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+    user.amount = user.amount.checked_add(amount).ok_or(VaultError::MathOverflow)?;
+    set_weight(v, user, weight)?;
+    sync_reward_debts(user, v);
+    Ok(())
+}
+
+/// Accrues the vault and settles a position ahead of a payout, dropping a
+/// boost whose lock has run out. Pay with `record_payout`, then
+/// `sync_reward_debts`.
+pub fn prepare_claim(v: &mut Vault, user: &mut UserStake, now: i64) -> Result<()> {
+    update_rewards_at(v, now)?;
+    settle_pending(user, v)?;
+    if now >= user.lock_until && user.weight != user.amount {
+        let base_weight = user.amount;
+        set_weight(v, user, base_weight)?;
+    }
+    Ok(())
+}
+
+/// Books `paid` of a position's `owed` on one stream as transferred.
+pub fn record_payout(v: &mut Vault, user: &mut UserStake, stream_index: usize, paid: u64) -> Result<()> {
+    user.owed[stream_index] =
+        user.owed[stream_index].checked_sub(paid).ok_or(VaultError::MathOverflow)?;
+    let s = &mut v.streams[stream_index];
+    s.total_owed = s.total_owed.saturating_sub(paid);
+    s.paid = s.paid.checked_add(paid).ok_or(VaultError::MathOverflow)?;
+    Ok(())
+}
+
+/// Removes `amount` of settled principal from an unlocked position. Returns
+/// the early-exit penalty, which stays in escrow for the remaining stakers;
+/// the caller transfers `amount - penalty`.
+pub fn apply_withdraw(v: &mut Vault, user: &mut UserStake, amount: u64, now: i64) -> Result<u64> {
+    require!(now >= user.lock_until, VaultError::StakeLocked);
+    require!(user.amount >= amount, VaultError::InsufficientStake);
+    let remaining_amount = user.amount - amount;
+    let penalty = early_exit_penalty(v, user, amount, now)?;
+
+    user.amount = remaining_amount;
+    v.total_staked = v.total_staked.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
+    // Lock is over, so the position drops back to 1x
+    set_weight(v, user, remaining_amount)?;
+    // Penalty stays in escrow; debts are synced after so the leaver gets none of it
+    distribute_penalty(v, penalty)?;
+    sync_reward_debts(user, v);
+    Ok(penalty)
+}
+
+/// Empties a position without accruing the vault. What it earned up to the
+/// last accrual is settled into `owed` and stays claimable; emissions not yet
+/// accrued go to whoever remains. Its share of earlier penalties is stake
+/// rather than reward, so it is compounded in and leaves with the principal
+/// instead of being stranded in the pool. Lock and penalty are waived while
+/// `paused`. Returns the principal removed and the penalty; the caller
+/// transfers `amount - penalty`.
+pub fn apply_emergency_withdraw(
+    v: &mut Vault,
+    user: &mut UserStake,
+    paused: bool,
+    now: i64,
+) -> Result<(u64, u64)> {
+    settle_pending(user, v)?;
+    let amount = user.amount;
+    require!(amount > 0, VaultError::ZeroAmount);
+    let penalty = if paused {
+        0
+    } else {
+        require!(now >= user.lock_until, VaultError::StakeLocked);
+        early_exit_penalty(v, user, amount, now)?
+    };
+
+    user.amount = 0;
+    v.total_staked = v.total_staked.checked_sub(amount).ok_or(VaultError::MathOverflow)?;
+    set_weight(v, user, 0)?;
+    distribute_penalty(v, penalty)?;
+    sync_reward_debts(user, v);
+    Ok((amount, penalty))
+}
+
+/// Cut of `amount` kept for whoever stays when leaving before
+/// min_stake_secs. Waived once the term is over, and with nobody left to
+/// receive it.
+pub fn early_exit_penalty(v: &Vault, user: &UserStake, amount: u64, now: i64) -> Result<u64> {
+    let weight_after = v.total_weighted_stake
+        .checked_sub(user.weight).ok_or(VaultError::MathOverflow)?
+        .checked_add(user.amount - amount).ok_or(VaultError::MathOverflow)?;
+    let early = now < v.end_time && now < user.staked_at.saturating_add(v.policy.min_stake_secs);
+    Ok(if early && weight_after > 0 {
+        amount.saturating_mul(v.policy.early_exit_penalty_bps as u64) / 10_000
+    } else {
+        0
+    })
+}
@@ -0,0 +1,64 @@
+//! Off-chain companion to the `driplet_vaults` program. Account layouts and
+//! the reward math are re-exported from the program crate itself, so backend
+//! services and the chain always compute the same numbers.
+
+use anchor_lang::prelude::*;
+use anchor_lang::AccountDeserialize;
+
+pub use driplet_vaults::{
+    math, BacklogPolicy, CrankPool, EmissionSchedule, GlobalState, MintListing, MintRule,
+    PauseInterval, PrincipalPolicy, RewardStream, ScheduleStep, UnclaimedPrincipal, UserStake,
+    UserStakeV1, Vault, VaultError, VaultPolicy, VaultV1, ID, MAX_PAUSE_HISTORY,
+    MAX_REWARD_STREAMS, RATE_SCALE, RPT_SCALE, VAULT_VERSION,
+};
+
+pub mod pda;
+
+/// Decodes a `Vault` account, discriminator included. A vault still in the
+/// v1 layout comes back as `migrate_vault` would rewrite it, with the
+/// SweepToTreasury policy the v1 program applied until the admin picks one.
+pub fn decode_vault(mut data: &[u8]) -> Result<Vault> {
+    if data.len() == VaultV1::LEN && data.starts_with(Vault::DISCRIMINATOR) {
+        let old = VaultV1::deserialize(&mut &data[8..])?;
+        return Ok(old.upgrade(PrincipalPolicy::SweepToTreasury));
+    }
+    Vault::try_deserialize(&mut data)
+}
+
+/// Decodes a `UserStake` account, discriminator included. A v1 record comes
+/// back as `migrate_user_stake` would rewrite it.
+pub fn decode_user_stake(mut data: &[u8]) -> Result<UserStake> {
+    if data.len() == UserStakeV1::LEN && data.starts_with(UserStake::DISCRIMINATOR) {
+        return Ok(UserStakeV1::deserialize(&mut &data[8..])?.upgrade());
+    }
+    UserStake::try_deserialize(&mut data)
+}
+
+/// The vault as the program would see it after accruing every stream to
+/// `now`, caught up on global pauses first as the program's views are.
+/// Deterministic: the clock is an argument, not a sysvar.
+pub fn simulate(vault: &Vault, state: &GlobalState, now: i64) -> Result<Vault> {
+    let mut v = vault.clone();
+    let accrue_to = math::sync_global_pause(&mut v, state, now);
+    math::update_rewards_at(&mut v, accrue_to)?;
+    Ok(v)
+}
+
+/// What `claim` would pay `user` on each stream at `now`, before any
+/// reward-account shortfall. Matches `preview_pending`.
+pub fn pending(vault: &Vault, state: &GlobalState, user: &UserStake, now: i64) -> Result<Vec<u64>> {
+    let mut v = simulate(vault, state, now)?;
+    let mut u = user.clone();
+    math::settle_pending(&mut u, &mut v)?;
+    Ok(u.owed[..v.streams.len()].to_vec())
+}
+
+/// Total each stream will have emitted by `at`, following its schedule as
+/// moved by global pauses. A paused vault, or a program paused globally, is
+/// projected as staying paused.
+pub fn projected_emissions(vault: &Vault, state: &GlobalState, at: i64) -> Result<Vec<u64>> {
+    let mut v = vault.clone();
+    let at = math::sync_global_pause(&mut v, state, at);
+    let at = if v.paused_at != 0 { at.min(v.paused_at) } else { at };
+    v.streams.iter().map(|s| math::emitted_at(s, at)).collect()
+}
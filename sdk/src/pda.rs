@@ -0,0 +1,47 @@
+//! PDA derivations, mirroring the seeds in the program's account structs.
+
+use anchor_lang::prelude::Pubkey;
+use driplet_vaults::ID;
+
+pub fn vault(mint: &Pubkey, creator: &Pubkey, vault_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"vault", mint.as_ref(), creator.as_ref(), &vault_id.to_le_bytes()],
+        &ID,
+    )
+}
+
+/// Owner of the vault's stake-mint escrow ATA.
+pub fn vault_escrow(vault: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vault_escrow", vault.as_ref()], &ID)
+}
+
+/// Owner of the vault's reward ATAs, one per stream mint.
+pub fn vault_reward(vault: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vault_reward", vault.as_ref()], &ID)
+}
+
+/// A staker's `UserStake` record.
+pub fn user(vault: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"user", vault.as_ref(), owner.as_ref()], &ID)
+}
+
+/// An owner's `UnclaimedPrincipal`, created by release_position; it also
+/// owns the ATA the principal waits in.
+pub fn unclaimed(vault: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"unclaimed", vault.as_ref(), owner.as_ref()], &ID)
+}
+
+/// The singleton `GlobalState`.
+pub fn state() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"state"], &ID)
+}
+
+/// The `CrankPool` paying crank tips; exists once first funded.
+pub fn crank_pool() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"crank_pool"], &ID)
+}
+
+/// A mint's `MintRule` listing; only exists for listed mints.
+pub fn mint_rule(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"mint_rule", mint.as_ref()], &ID)
+}
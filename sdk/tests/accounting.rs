@@ -0,0 +1,510 @@
+//! Property tests for the reward accounting. Random sequences of deposits,
+//! withdrawals, claims, pauses, backlog reclaims and clock warps are replayed
+//! through the same `math` transitions the instruction handlers call, with
+//! token balances tracked alongside. Every invariant is checked after every step.
+
+use anchor_lang::prelude::Pubkey;
+use driplet_vaults_sdk::{
+    math, BacklogPolicy, EmissionSchedule, GlobalState, PrincipalPolicy, RewardStream,
+    ScheduleStep, UserStake, Vault, VaultPolicy, MAX_REWARD_STREAMS,
+};
+use proptest::prelude::*;
+
+const USERS: usize = 4;
+const START: i64 = 1_700_000_000;
+const MAX_LOCK_SECS: i64 = driplet_vaults::MAX_LOCK_SECS;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Deposit {
+        user: usize,
+        amount: u64,
+        lock_secs: i64,
+    },
+    Withdraw {
+        user: usize,
+        bps: u16,
+    },
+    Claim {
+        user: usize,
+    },
+    EmergencyWithdraw {
+        user: usize,
+    },
+    TogglePause,
+    Reclaim {
+        stream: usize,
+    },
+    Warp {
+        secs: i64,
+    },
+}
+
+/// Schedule and window of one stream, as fractions of the vault term so
+/// they can be drawn before the duration is known.
+#[derive(Clone, Debug)]
+struct StreamSpec {
+    reward_net: u64,
+    start_frac: u16,
+    end_frac: u16,
+    kind: u8,
+    param: u16,
+    steps: Vec<(u16, u32)>,
+}
+
+/// Vault state plus the token balances the handlers move with it.
+struct Ledger {
+    v: Vault,
+    users: Vec<UserStake>,
+    escrow: u64,
+    reward_balances: Vec<u64>,
+    now: i64,
+}
+
+fn frac(total: i64, f: u16) -> i64 {
+    ((total as i128) * (f as i128) / 65_536) as i64
+}
+
+fn schedule(spec: &StreamSpec, duration: i64) -> EmissionSchedule {
+    match spec.kind % 4 {
+        0 => EmissionSchedule::Linear,
+        1 => {
+            let min = (duration + driplet_vaults::MAX_HALVINGS - 1) / driplet_vaults::MAX_HALVINGS;
+            EmissionSchedule::ExponentialDecay {
+                half_life_secs: frac(duration, spec.param).max(min).max(1),
+            }
+        }
+        2 => EmissionSchedule::CliffLinear {
+            cliff_secs: frac(duration, spec.param),
+        },
+        _ => {
+            let mut offsets: Vec<i64> =
+                spec.steps.iter().map(|(f, _)| frac(duration, *f)).collect();
+            offsets[0] = 0;
+            offsets.sort_unstable();
+            offsets.dedup();
+            EmissionSchedule::Stepped {
+                steps: offsets
+                    .into_iter()
+                    .zip(spec.steps.iter())
+                    .map(|(offset_secs, (_, weight))| ScheduleStep {
+                        offset_secs,
+                        weight: *weight,
+                    })
+                    .collect(),
+            }
+        }
+    }
+}
+
+fn build(
+    duration: i64,
+    penalty_bps: u16,
+    min_stake_frac: u16,
+    backlog: BacklogPolicy,
+    specs: &[StreamSpec],
+) -> Option<Ledger> {
+    let end = START + duration;
+    let mut streams = Vec::new();
+    for spec in specs {
+        let start = START + frac(duration - 1, spec.start_frac);
+        let stop = start + 1 + frac(end - start - 1, spec.end_frac);
+        let s = RewardStream::new(
+            Pubkey::new_unique(),
+            Pubkey::default(),
+            start,
+            stop,
+            schedule(spec, stop - start),
+            spec.reward_net,
+            0,
+            spec.reward_net,
+        )
+        .ok()?;
+        streams.push(s);
+    }
+    let reward_balances = streams.iter().map(|s| s.reward_net).collect();
+    let v = Vault {
+        id: 0,
+        creator: Pubkey::default(),
+        mint: Pubkey::default(),
+        start_time: START,
+        end_time: end,
+        duration_secs: duration,
+        total_staked: 0,
+        total_weighted_stake: 0,
+        policy: VaultPolicy {
+            early_exit_penalty_bps: penalty_bps,
+            min_stake_secs: frac(duration, min_stake_frac),
+            principal: PrincipalPolicy::HoldForOwners,
+            backlog,
+        },
+        acc_penalty_per_token: 0,
+        penalty_pool: 0,
+        paused_at: 0,
+        global_paused_secs: 0,
+        last_cranked_at: 0,
+        crank_tips_paid: 0,
+        streams,
+        bump: 0,
+        vault_escrow_bump: 0,
+        vault_reward_bump: 0,
+        version: 2,
+    };
+    let users = (0..USERS)
+        .map(|_| UserStake {
+            owner: Pubkey::new_unique(),
+            vault: Pubkey::default(),
+            amount: 0,
+            weight: 0,
+            lock_until: 0,
+            reward_debt: [0; MAX_REWARD_STREAMS],
+            owed: [0; MAX_REWARD_STREAMS],
+            penalty_debt: 0,
+            staked_at: 0,
+            initialized: 0,
+        })
+        .collect();
+    Some(Ledger {
+        v,
+        users,
+        escrow: 0,
+        reward_balances,
+        now: START,
+    })
+}
+
+impl Ledger {
+    /// Mirrors `stake`. Returns false where the handler would reject.
+    fn deposit(&mut self, i: usize, amount: u64, lock_secs: i64) -> bool {
+        let now = self.now;
+        if self.v.paused_at != 0 || now < self.v.start_time || now > self.v.end_time {
+            return false;
+        }
+        let mut v = self.v.clone();
+        let mut u = self.users[i].clone();
+        math::update_rewards_at(&mut v, now).unwrap();
+        if u.initialized == 0 {
+            u.staked_at = now;
+            u.initialized = 1;
+        }
+        if math::apply_deposit(&mut v, &mut u, amount, lock_secs, now).is_err() {
+            return false;
+        }
+        self.escrow += amount;
+        self.v = v;
+        self.users[i] = u;
+        true
+    }
+
+    /// Mirrors `withdraw` without `claim_all`.
+    fn withdraw(&mut self, i: usize, bps: u16) -> bool {
+        let now = self.now;
+        if self.users[i].initialized == 0 {
+            return false;
+        }
+        // Size the withdrawal off the settled principal, which includes any
+        // penalty share; only commit if the handler would go through.
+        let mut v = self.v.clone();
+        let mut u = self.users[i].clone();
+        math::update_rewards_at(&mut v, now).unwrap();
+        math::settle_pending(&mut u, &mut v).unwrap();
+        let amount = (u.amount as u128 * bps as u128 / 10_000) as u64;
+        if amount == 0 {
+            return false;
+        }
+        let Ok(penalty) = math::apply_withdraw(&mut v, &mut u, amount, now) else {
+            return false;
+        };
+
+        self.escrow -= amount - penalty;
+        self.v = v;
+        self.users[i] = u;
+        true
+    }
+
+    /// Mirrors `claim`, paying every stream from its balance.
+    fn claim(&mut self, i: usize) -> bool {
+        let now = self.now;
+        let v = &mut self.v;
+        let u = &mut self.users[i];
+        if u.initialized == 0 || v.paused_at != 0 {
+            return false;
+        }
+        math::prepare_claim(v, u, now).unwrap();
+        let owed_total: u64 = u.owed.iter().sum();
+        let mut any_paid = false;
+        for idx in 0..v.streams.len() {
+            let pay = u.owed[idx].min(self.reward_balances[idx]);
+            math::record_payout(v, u, idx, pay).unwrap();
+            self.reward_balances[idx] -= pay;
+            any_paid |= pay > 0;
+        }
+        // The handler fails with InsufficientRewards here; it must never happen
+        assert!(
+            owed_total == 0 || any_paid,
+            "owed {owed_total} but nothing payable"
+        );
+        math::sync_reward_debts(u, v);
+        true
+    }
+
+    /// Mirrors `emergency_withdraw`: nothing is accrued or paid.
+    fn emergency_withdraw(&mut self, i: usize) -> bool {
+        let now = self.now;
+        let paused = self.v.paused_at != 0;
+        let mut v = self.v.clone();
+        let mut u = self.users[i].clone();
+        let Ok((amount, penalty)) = math::apply_emergency_withdraw(&mut v, &mut u, paused, now)
+        else {
+            return false;
+        };
+        self.escrow -= amount - penalty;
+        self.v = v;
+        self.users[i] = u;
+        true
+    }
+
+    /// Mirrors `pause_vault` / `unpause_vault`.
+    fn toggle_pause(&mut self) {
+        let now = self.now;
+        let v = &mut self.v;
+        if v.paused_at == 0 {
+            math::update_rewards_at(v, now).unwrap();
+            v.paused_at = now;
+        } else {
+            let paused_at = v.paused_at;
+            math::shift_schedule(v, paused_at, now - paused_at);
+            v.paused_at = 0;
+        }
+    }
+
+    /// Mirrors `reclaim_unallocated`.
+    fn reclaim(&mut self, idx: usize) -> bool {
+        let now = self.now;
+        let v = &mut self.v;
+        if v.policy.backlog == BacklogPolicy::ToFirstStaker && now <= v.end_time {
+            return false;
+        }
+        math::update_rewards_at(v, now).unwrap();
+        let s = &mut v.streams[idx];
+        if s.unallocated == 0 {
+            return false;
+        }
+        self.reward_balances[idx] -= s.unallocated;
+        s.reclaimed += s.unallocated;
+        s.unallocated = 0;
+        true
+    }
+
+    fn apply(&mut self, op: &Op) {
+        match *op {
+            Op::Deposit {
+                user,
+                amount,
+                lock_secs,
+            } => {
+                self.deposit(user, amount, lock_secs);
+            }
+            Op::Withdraw { user, bps } => {
+                self.withdraw(user, bps);
+            }
+            Op::Claim { user } => {
+                self.claim(user);
+            }
+            Op::EmergencyWithdraw { user } => {
+                self.emergency_withdraw(user);
+            }
+            Op::TogglePause => self.toggle_pause(),
+            Op::Reclaim { stream } => {
+                self.reclaim(stream % self.v.streams.len());
+            }
+            Op::Warp { secs } => self.now += secs,
+        }
+    }
+
+    fn check(&self) -> Result<(), TestCaseError> {
+        let v = &self.v;
+        prop_assert_eq!(self.escrow, v.total_staked + v.penalty_pool);
+        prop_assert_eq!(
+            v.total_staked,
+            self.users.iter().map(|u| u.amount).sum::<u64>()
+        );
+        prop_assert_eq!(
+            v.total_weighted_stake,
+            self.users.iter().map(|u| u.weight).sum::<u64>()
+        );
+
+        let penalty_pending: u64 = self
+            .users
+            .iter()
+            .map(|u| math::pending_rewards(u.weight, u.penalty_debt, v.acc_penalty_per_token))
+            .sum();
+        prop_assert!(penalty_pending <= v.penalty_pool);
+
+        // Global pauses are covered by the integration suite
+        let state = GlobalState::new(Pubkey::default());
+        let sim = driplet_vaults_sdk::simulate(v, &state, self.now).unwrap();
+        let pending: Vec<Vec<u64>> = self
+            .users
+            .iter()
+            .map(|u| driplet_vaults_sdk::pending(v, &state, u, self.now).unwrap())
+            .collect();
+        // Nothing accrues past the moment the vault was paused
+        let accrued_to = if v.paused_at != 0 {
+            self.now.min(v.paused_at)
+        } else {
+            self.now
+        };
+        for (idx, (s, sim_s)) in v.streams.iter().zip(sim.streams.iter()).enumerate() {
+            prop_assert!(s.emitted <= s.reward_net);
+            prop_assert!(sim_s.emitted <= sim_s.reward_net);
+            prop_assert_eq!(
+                sim_s.emitted,
+                math::emitted_at(s, accrued_to.min(s.end_time).max(s.last_update_time)).unwrap()
+            );
+            prop_assert_eq!(
+                s.total_owed,
+                self.users.iter().map(|u| u.owed[idx]).sum::<u64>()
+            );
+
+            // Owed after settling everyone at `now`, plus what was paid or
+            // reclaimed, can never exceed what the stream has emitted by then
+            let owed_now: u64 = pending.iter().map(|p| p[idx]).sum();
+            prop_assert!(
+                s.paid + s.reclaimed + owed_now <= sim_s.emitted,
+                "stream {}: paid {} + reclaimed {} + owed {} > emitted {}",
+                idx,
+                s.paid,
+                s.reclaimed,
+                owed_now,
+                sim_s.emitted
+            );
+
+            // The reward account covers everything still to be paid out
+            prop_assert_eq!(
+                self.reward_balances[idx],
+                s.reward_net - s.paid - s.reclaimed
+            );
+            prop_assert!(
+                self.reward_balances[idx] >= owed_now + (sim_s.reward_net - sim_s.emitted)
+            );
+        }
+        Ok(())
+    }
+}
+
+fn stream_spec(max_reward: u64) -> impl Strategy<Value = StreamSpec> {
+    (
+        1..=max_reward,
+        any::<u16>(),
+        any::<u16>(),
+        any::<u8>(),
+        any::<u16>(),
+        prop::collection::vec(
+            (any::<u16>(), 0u32..1_000),
+            1..=driplet_vaults::MAX_SCHEDULE_STEPS,
+        ),
+    )
+        .prop_map(
+            |(reward_net, start_frac, end_frac, kind, param, mut steps)| {
+                steps[0].1 = steps[0].1.max(1);
+                StreamSpec {
+                    reward_net,
+                    start_frac,
+                    end_frac,
+                    kind,
+                    param,
+                    steps,
+                }
+            },
+        )
+}
+
+fn op(max_amount: u64, max_warp: i64) -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (0..USERS, 1..=max_amount, prop_oneof![Just(0i64), 1..=MAX_LOCK_SECS])
+            .prop_map(|(user, amount, lock_secs)| Op::Deposit { user, amount, lock_secs }),
+        3 => (0..USERS, 1u16..=10_000).prop_map(|(user, bps)| Op::Withdraw { user, bps }),
+        3 => (0..USERS).prop_map(|user| Op::Claim { user }),
+        1 => (0..USERS).prop_map(|user| Op::EmergencyWithdraw { user }),
+        1 => Just(Op::TogglePause),
+        1 => (0..MAX_REWARD_STREAMS).prop_map(|stream| Op::Reclaim { stream }),
+        4 => (0..=max_warp).prop_map(|secs| Op::Warp { secs }),
+    ]
+}
+
+fn run(
+    duration: i64,
+    penalty_bps: u16,
+    min_stake_frac: u16,
+    backlog_to_funder: bool,
+    specs: Vec<StreamSpec>,
+    ops: Vec<Op>,
+) -> Result<(), TestCaseError> {
+    let backlog = if backlog_to_funder {
+        BacklogPolicy::ReturnToFunder
+    } else {
+        BacklogPolicy::ToFirstStaker
+    };
+    let Some(mut ledger) = build(duration, penalty_bps, min_stake_frac, backlog, &specs) else {
+        return Err(TestCaseError::reject("schedule does not fit its window"));
+    };
+    ledger.check()?;
+    for op in &ops {
+        ledger.apply(op);
+        ledger.check()?;
+    }
+    // Everyone who can leaves after the term; what is left is rounding dust
+    if ledger.v.paused_at != 0 {
+        ledger.toggle_pause();
+    }
+    ledger.now = ledger
+        .now
+        .max(ledger.v.end_time)
+        .max(ledger.users.iter().map(|u| u.lock_until).max().unwrap());
+    for i in 0..USERS {
+        if ledger.claim(i) {
+            ledger.withdraw(i, 10_000);
+        }
+        ledger.check()?;
+    }
+    prop_assert_eq!(ledger.v.total_staked, 0);
+    prop_assert_eq!(ledger.escrow, ledger.v.penalty_pool);
+    // With nobody left, any backlog can go back to its funder
+    for idx in 0..ledger.v.streams.len() {
+        ledger.reclaim(idx);
+        ledger.check()?;
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    /// Everyday magnitudes: token amounts, durations from an hour to two years.
+    #[test]
+    fn invariants_hold_for_realistic_vaults(
+        duration in 3_600i64..=63_072_000,
+        penalty_bps in prop_oneof![Just(0u16), 1u16..=5_000],
+        min_stake_frac in any::<u16>(),
+        backlog_to_funder in any::<bool>(),
+        specs in prop::collection::vec(stream_spec(1_000_000_000_000), 1..=MAX_REWARD_STREAMS),
+        ops in prop::collection::vec(op(1_000_000_000, 30 * 86_400), 1..60),
+    ) {
+        run(duration, penalty_bps, min_stake_frac, backlog_to_funder, specs, ops)?;
+    }
+
+    /// Extreme magnitudes, to reach the saturating and dust paths: huge
+    /// rewards over dust-sized stakes, short windows and long warps.
+    #[test]
+    fn invariants_hold_at_extremes(
+        duration in 2i64..=86_400,
+        penalty_bps in 0u16..=5_000,
+        min_stake_frac in any::<u16>(),
+        backlog_to_funder in any::<bool>(),
+        specs in prop::collection::vec(stream_spec(1 << 56), 1..=MAX_REWARD_STREAMS),
+        ops in prop::collection::vec(op(1 << 40, 10 * 86_400), 1..60),
+    ) {
+        run(duration, penalty_bps, min_stake_frac, backlog_to_funder, specs, ops)?;
+    }
+}
@@ -0,0 +1,76 @@
+//! Account decoding, including layouts older than the current program.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorSerialize, Discriminator};
+use driplet_vaults_sdk::{
+    decode_user_stake, decode_vault, math, PrincipalPolicy, UserStake, UserStakeV1, Vault, VaultV1,
+    VAULT_VERSION,
+};
+
+fn v1_account<T: AnchorSerialize>(discriminator: &[u8], value: &T, len: usize) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    value.serialize(&mut data).unwrap();
+    assert!(data.len() <= len);
+    data.resize(len, 0);
+    data
+}
+
+#[test]
+fn v1_vault_decodes_as_one_linear_stream_that_finishes_on_time() {
+    let old = VaultV1 {
+        id: 7,
+        creator: Pubkey::new_unique(),
+        mint: Pubkey::new_unique(),
+        start_time: 1_000,
+        end_time: 1_000 + 182 * 86_400,
+        reward_net: 1_000_000,
+        reward_fee: 30_000,
+        reward_gross: 1_030_000,
+        rate_fp: 0,
+        emission_acc_fp: 0,
+        emitted: 333_333,
+        last_update_time: 5_000_000,
+        acc_reward_per_token: 42,
+        unallocated: 0,
+        total_staked: 500,
+        bump: 1,
+        vault_escrow_bump: 2,
+        vault_reward_bump: 3,
+        version: 1,
+    };
+    let data = v1_account(Vault::DISCRIMINATOR, &old, VaultV1::LEN);
+    // migrate_vault reads the version byte at the end of the v1 fields
+    assert_eq!(data[VaultV1::LEN - VaultV1::PADDING - 1], 1);
+    let mut v = decode_vault(&data).unwrap();
+    assert_eq!(v.version, VAULT_VERSION);
+    assert_eq!((v.id, v.total_staked, v.total_weighted_stake), (7, 500, 500));
+    // v1 always swept unwithdrawn principal; holding it is a choice made on migration
+    assert!(v.policy.principal == PrincipalPolicy::SweepToTreasury);
+    assert_eq!(v.streams.len(), 1);
+    let s = &v.streams[0];
+    assert_eq!((s.mint, s.funder), (old.mint, old.creator));
+    assert_eq!((s.emitted, s.acc_reward_per_token), (333_333, 42));
+
+    // Emission continues from where v1 left off and lands on reward_net
+    math::update_rewards_at(&mut v, old.last_update_time).unwrap();
+    assert_eq!(v.streams[0].emitted, 333_333);
+    math::update_rewards_at(&mut v, old.end_time).unwrap();
+    assert_eq!(v.streams[0].emitted, 1_000_000);
+}
+
+#[test]
+fn v1_user_stake_keeps_its_debt_on_stream_zero() {
+    let old = UserStakeV1 {
+        owner: Pubkey::new_unique(),
+        vault: Pubkey::new_unique(),
+        amount: 250,
+        reward_debt: 99,
+        initialized: 1,
+    };
+    let data = v1_account(UserStake::DISCRIMINATOR, &old, UserStakeV1::LEN);
+    let u = decode_user_stake(&data).unwrap();
+    assert_eq!((u.owner, u.vault), (old.owner, old.vault));
+    assert_eq!((u.amount, u.weight, u.lock_until), (250, 250, 0));
+    assert_eq!(u.reward_debt[0], 99);
+    assert!(u.reward_debt[1..].iter().all(|d| *d == 0));
+}
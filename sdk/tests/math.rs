@@ -0,0 +1,86 @@
+//! Focused checks on the stream accrual primitives in `math`.
+
+use anchor_lang::prelude::Pubkey;
+use driplet_vaults_sdk::{math, EmissionSchedule, RewardStream, ScheduleStep, RPT_SCALE};
+
+fn stream(reward_net: u64) -> RewardStream {
+    let mint = Pubkey::new_unique();
+    RewardStream::new(
+        mint,
+        Pubkey::new_unique(),
+        0,
+        100,
+        EmissionSchedule::Linear,
+        reward_net,
+        0,
+        reward_net,
+    )
+    .unwrap()
+}
+
+#[test]
+fn backlog_flushes_to_a_first_staker_arriving_at_end_time() {
+    let mut s = stream(1_000);
+    // Nobody staked for the whole term: everything is parked
+    math::update_stream(&mut s, 0, 100, true).unwrap();
+    assert_eq!((s.emitted, s.unallocated, s.last_update_time), (1_000, 1_000, 100));
+
+    // A deposit at exactly end_time accrues with capped_now == last_update_time
+    math::update_stream(&mut s, 10, 100, true).unwrap();
+    assert_eq!(s.unallocated, 0);
+    assert_eq!(s.acc_reward_per_token, 100 * RPT_SCALE);
+
+    // Later accruals neither emit nor flush again
+    math::update_stream(&mut s, 10, 500, true).unwrap();
+    assert_eq!((s.emitted, s.acc_reward_per_token), (1_000, 100 * RPT_SCALE));
+}
+
+#[test]
+fn an_earlier_timestamp_never_rewinds_last_update_time() {
+    let mut s = stream(1_000);
+    math::update_stream(&mut s, 0, 60, true).unwrap();
+    math::update_stream(&mut s, 10, 40, true).unwrap();
+    assert_eq!(s.last_update_time, 60);
+    assert_eq!(s.unallocated, 0);
+
+    // Emissions resume from 60, not from 40
+    math::update_stream(&mut s, 10, 80, true).unwrap();
+    assert_eq!(s.emitted, 800);
+    assert_eq!(s.acc_reward_per_token, 80 * RPT_SCALE);
+}
+
+#[test]
+fn reward_debt_rounds_up_so_resyncs_credit_no_dust() {
+    // A floored debt here would be 0 and credit a whole unit for 1e-12 earned
+    let debt = math::reward_debt(1, RPT_SCALE - 1);
+    assert_eq!(debt, 1);
+    assert_eq!(math::pending_rewards(1, debt, RPT_SCALE), 0);
+
+    // Exact products are unaffected
+    assert_eq!(math::reward_debt(3, 2 * RPT_SCALE), 6);
+    assert_eq!(math::pending_rewards(3, 6, 3 * RPT_SCALE), 3);
+}
+
+#[test]
+fn remaining_weight_is_zero_in_a_zero_weight_tail() {
+    let steps = vec![
+        ScheduleStep { offset_secs: 0, weight: 2 },
+        ScheduleStep { offset_secs: 60, weight: 0 },
+    ];
+    let schedule = EmissionSchedule::Stepped { steps };
+    let s = RewardStream::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        0,
+        100,
+        schedule,
+        1_000,
+        0,
+        1_000,
+    )
+    .unwrap();
+    assert_eq!(math::remaining_schedule_weight(&s, 0), 120);
+    assert_eq!(math::remaining_schedule_weight(&s, 59), 2);
+    assert_eq!(math::remaining_schedule_weight(&s, 60), 0);
+    assert_eq!(math::emitted_at(&s, 80).unwrap(), 1_000);
+}
@@ -0,0 +1,130 @@
+//! The off-chain views, which must agree with `preview_pending` and
+//! `preview_vault_state` across global pauses.
+
+use anchor_lang::prelude::Pubkey;
+use driplet_vaults_sdk::{
+    pending, projected_emissions, simulate, BacklogPolicy, EmissionSchedule, GlobalState,
+    PauseInterval, PrincipalPolicy, RewardStream, UserStake, Vault, VaultPolicy,
+    MAX_PAUSE_HISTORY, MAX_REWARD_STREAMS,
+};
+
+/// One linear 1_000-token stream over [0, 100], with `staker` holding all
+/// of the weight since 0 and the vault untouched since.
+fn fixture() -> (Vault, UserStake) {
+    let stream = RewardStream::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        0,
+        100,
+        EmissionSchedule::Linear,
+        1_000,
+        0,
+        1_000,
+    )
+    .unwrap();
+    let vault = Vault {
+        id: 0,
+        creator: Pubkey::default(),
+        mint: Pubkey::default(),
+        start_time: 0,
+        end_time: 100,
+        duration_secs: 100,
+        total_staked: 10,
+        total_weighted_stake: 10,
+        policy: VaultPolicy {
+            early_exit_penalty_bps: 0,
+            min_stake_secs: 0,
+            principal: PrincipalPolicy::HoldForOwners,
+            backlog: BacklogPolicy::ToFirstStaker,
+        },
+        acc_penalty_per_token: 0,
+        penalty_pool: 0,
+        paused_at: 0,
+        global_paused_secs: 0,
+        last_cranked_at: 0,
+        crank_tips_paid: 0,
+        streams: vec![stream],
+        bump: 0,
+        vault_escrow_bump: 0,
+        vault_reward_bump: 0,
+        version: 2,
+    };
+    let staker = UserStake {
+        owner: Pubkey::new_unique(),
+        vault: Pubkey::default(),
+        amount: 10,
+        weight: 10,
+        lock_until: 0,
+        reward_debt: [0; MAX_REWARD_STREAMS],
+        owed: [0; MAX_REWARD_STREAMS],
+        penalty_debt: 0,
+        staked_at: 0,
+        initialized: 1,
+    };
+    (vault, staker)
+}
+
+#[test]
+fn a_global_pause_in_progress_freezes_every_view() {
+    let (v, staker) = fixture();
+    let mut state = GlobalState::new(Pubkey::default());
+    state.paused = true;
+    state.paused_at = 40;
+
+    assert_eq!(simulate(&v, &state, 80).unwrap().streams[0].emitted, 400);
+    assert_eq!(pending(&v, &state, &staker, 80).unwrap(), vec![400]);
+    assert_eq!(projected_emissions(&v, &state, 100).unwrap(), vec![400]);
+}
+
+#[test]
+fn a_finished_global_pause_pushes_the_schedule_back() {
+    let (v, staker) = fixture();
+    // Paused from 40 to 70; the vault has not been touched since
+    let mut state = GlobalState::new(Pubkey::default());
+    state.paused_at = 40;
+    state.record_pause(70).unwrap();
+
+    assert_eq!(simulate(&v, &state, 80).unwrap().streams[0].emitted, 500);
+    assert_eq!(pending(&v, &state, &staker, 80).unwrap(), vec![500]);
+    assert_eq!(projected_emissions(&v, &state, 100).unwrap(), vec![700]);
+    assert_eq!(projected_emissions(&v, &state, 130).unwrap(), vec![1_000]);
+
+    // A vault that has already seen the pause is not shifted twice
+    let mut seen = simulate(&v, &state, 70).unwrap();
+    seen.global_paused_secs = state.paused_secs;
+    assert_eq!(simulate(&seen, &state, 80).unwrap().streams[0].emitted, 500);
+}
+
+#[test]
+fn missed_global_pauses_each_shift_from_their_own_start() {
+    let (mut v, _) = fixture();
+    // Deposits open at 30, between a pause from 10 to 20 and one from 40 to 70
+    v.start_time = 30;
+    let mut state = GlobalState::new(Pubkey::default());
+    for (from, to) in [(10, 20), (40, 70)] {
+        state.paused_at = from;
+        state.record_pause(to).unwrap();
+    }
+
+    let caught_up = simulate(&v, &state, 80).unwrap();
+    assert_eq!((caught_up.start_time, caught_up.end_time), (40, 140));
+    assert_eq!(caught_up.streams[0].emitted, 400);
+    assert_eq!(caught_up.global_paused_secs, 40);
+}
+
+#[test]
+fn pauses_beyond_the_history_fold_into_the_oldest_kept() {
+    let mut state = GlobalState::new(Pubkey::default());
+    for i in 0..=MAX_PAUSE_HISTORY as i64 {
+        state.paused_at = 200 + 10 * i;
+        state.record_pause(205 + 10 * i).unwrap();
+    }
+    let PauseInterval { started_at, secs } = state.pause_history[0];
+    assert_eq!((started_at, secs), (210, 5));
+
+    // The dropped pause at 200 is applied at 210, still ahead of the end
+    let (mut v, _) = fixture();
+    v.end_time = 250;
+    let caught_up = simulate(&v, &state, 300).unwrap();
+    assert_eq!(caught_up.end_time, 250 + 5 * (MAX_PAUSE_HISTORY as i64 + 1));
+}